@@ -3,8 +3,10 @@
 use sea_orm::entity::prelude::*;
 use sea_orm::prelude::async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize, ToSchema)]
+#[schema(as = BoardGame)]
 #[sea_orm(table_name = "board_game")]
 pub struct Model {
     #[sea_orm(primary_key)]
@@ -23,12 +25,30 @@ pub struct Model {
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::board_game_category::Entity")]
+    BoardGameCategory,
+    #[sea_orm(has_many = "super::board_game_tag::Entity")]
+    BoardGameTag,
     #[sea_orm(has_many = "super::favourite::Entity")]
     Favourite,
-    #[sea_orm(has_one = "super::rental::Entity")]
-    Rental,
+    #[sea_orm(has_many = "super::game_copy::Entity")]
+    GameCopy,
     #[sea_orm(has_many = "super::rental_history::Entity")]
     RentalHistory,
+    #[sea_orm(has_many = "super::waitlist::Entity")]
+    Waitlist,
+}
+
+impl Related<super::board_game_category::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::BoardGameCategory.def()
+    }
+}
+
+impl Related<super::board_game_tag::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::BoardGameTag.def()
+    }
 }
 
 impl Related<super::favourite::Entity> for Entity {
@@ -37,9 +57,18 @@ impl Related<super::favourite::Entity> for Entity {
     }
 }
 
+impl Related<super::game_copy::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::GameCopy.def()
+    }
+}
+
 impl Related<super::rental::Entity> for Entity {
     fn to() -> RelationDef {
-        Relation::Rental.def()
+        super::game_copy::Relation::Rental.def()
+    }
+    fn via() -> Option<RelationDef> {
+        Some(super::game_copy::Relation::BoardGame.def().rev())
     }
 }
 
@@ -49,6 +78,30 @@ impl Related<super::rental_history::Entity> for Entity {
     }
 }
 
+impl Related<super::waitlist::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Waitlist.def()
+    }
+}
+
+impl Related<super::category::Entity> for Entity {
+    fn to() -> RelationDef {
+        super::board_game_category::Relation::Category.def()
+    }
+    fn via() -> Option<RelationDef> {
+        Some(super::board_game_category::Relation::BoardGame.def().rev())
+    }
+}
+
+impl Related<super::tag::Entity> for Entity {
+    fn to() -> RelationDef {
+        super::board_game_tag::Relation::Tag.def()
+    }
+    fn via() -> Option<RelationDef> {
+        Some(super::board_game_tag::Relation::BoardGame.def().rev())
+    }
+}
+
 impl Related<super::user::Entity> for Entity {
     fn to() -> RelationDef {
         super::favourite::Relation::User.def()
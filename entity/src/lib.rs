@@ -3,7 +3,20 @@
 pub mod prelude;
 
 pub mod board_game;
+pub mod board_game_category;
+pub mod board_game_tag;
+pub mod category;
 pub mod favourite;
+pub mod game_copy;
+pub mod password_reset_token;
+pub mod penalty_event;
+pub mod recovery_code;
 pub mod rental;
 pub mod rental_history;
+pub mod role;
+pub mod role_permission;
+pub mod sea_orm_active_enums;
+pub mod session;
+pub mod tag;
 pub mod user;
+pub mod waitlist;
@@ -1,7 +1,19 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.3
 
 pub use super::board_game::Entity as BoardGame;
+pub use super::board_game_category::Entity as BoardGameCategory;
+pub use super::board_game_tag::Entity as BoardGameTag;
+pub use super::category::Entity as Category;
 pub use super::favourite::Entity as Favourite;
+pub use super::game_copy::Entity as GameCopy;
+pub use super::password_reset_token::Entity as PasswordResetToken;
+pub use super::penalty_event::Entity as PenaltyEvent;
+pub use super::recovery_code::Entity as RecoveryCode;
 pub use super::rental::Entity as Rental;
 pub use super::rental_history::Entity as RentalHistory;
+pub use super::role::Entity as Role;
+pub use super::role_permission::Entity as RolePermission;
+pub use super::session::Entity as Session;
+pub use super::tag::Entity as Tag;
 pub use super::user::Entity as User;
+pub use super::waitlist::Entity as Waitlist;
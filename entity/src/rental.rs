@@ -3,15 +3,16 @@
 use sea_orm::entity::prelude::*;
 use sea_orm::prelude::async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize, ToSchema)]
+#[schema(as = Rental)]
 #[sea_orm(table_name = "rental")]
 pub struct Model {
     #[sea_orm(primary_key)]
     #[serde(skip_deserializing)]
     pub id: i32,
-    #[sea_orm(unique)]
-    pub game_id: i32,
+    pub copy_id: i32,
     pub user_id: i32,
     pub rental_date: Date,
     pub return_date: Date,
@@ -19,18 +20,26 @@ pub struct Model {
     pub extension_date: Option<Date>,
     #[sea_orm(default_value = false)]
     pub picked_up: bool,
+    #[sea_orm(nullable)]
+    pub picked_up_at: Option<DateTime>,
+    #[sea_orm(nullable)]
+    pub picked_up_by: Option<i32>,
+    #[sea_orm(nullable)]
+    pub overdue_since: Option<Date>,
+    #[sea_orm(nullable)]
+    pub reminder_sent_at: Option<DateTime>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
     #[sea_orm(
-        belongs_to = "super::board_game::Entity",
-        from = "Column::GameId",
-        to = "super::board_game::Column::Id",
+        belongs_to = "super::game_copy::Entity",
+        from = "Column::CopyId",
+        to = "super::game_copy::Column::Id",
         on_update = "Cascade",
         on_delete = "Cascade"
     )]
-    BoardGame,
+    GameCopy,
     #[sea_orm(
         belongs_to = "super::user::Entity",
         from = "Column::UserId",
@@ -41,9 +50,18 @@ pub enum Relation {
     User,
 }
 
+impl Related<super::game_copy::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::GameCopy.def()
+    }
+}
+
 impl Related<super::board_game::Entity> for Entity {
     fn to() -> RelationDef {
-        Relation::BoardGame.def()
+        super::game_copy::Relation::BoardGame.def()
+    }
+    fn via() -> Option<RelationDef> {
+        Some(Relation::GameCopy.def())
     }
 }
 
@@ -59,12 +77,19 @@ impl ActiveModelBehavior for ActiveModel {
     where
         C: ConnectionTrait,
     {
-        if self.rental_date.as_ref() > self.return_date.as_ref() {
-            return Err(DbErr::Custom("rental_date cannot be greater than return_date".into()));
+        // Partial updates don't carry all the dates, so only the ones present are compared.
+        if let (Some(rental_date), Some(return_date)) =
+            (self.rental_date.try_as_ref(), self.return_date.try_as_ref())
+        {
+            if rental_date > return_date {
+                return Err(DbErr::Custom("rental_date cannot be greater than return_date".into()));
+            }
         }
-        
-        if let Some(extension_date) = self.extension_date.as_ref() {
-            if self.return_date.as_ref() >= extension_date {
+
+        if let (Some(return_date), Some(Some(extension_date))) =
+            (self.return_date.try_as_ref(), self.extension_date.try_as_ref())
+        {
+            if return_date >= extension_date {
                 return Err(DbErr::Custom("return_date cannot be greater than or equal to extension_date".into()));
             }
         }
@@ -15,6 +15,14 @@ pub struct Model {
     pub rental_date: Date,
     pub return_date: Date,
     pub picked_up: bool,
+    #[sea_orm(nullable)]
+    pub picked_up_at: Option<DateTime>,
+    #[sea_orm(nullable)]
+    pub picked_up_by: Option<i32>,
+    #[sea_orm(nullable)]
+    pub returned_at: Option<DateTime>,
+    #[sea_orm(nullable)]
+    pub returned_by: Option<i32>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -2,8 +2,10 @@
 
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize, ToSchema)]
+#[schema(as = User)]
 #[sea_orm(table_name = "user")]
 pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
@@ -16,20 +18,49 @@ pub struct Model {
     pub password_hash: String,
     #[sea_orm(nullable)]
     pub confirmation_token: Option<Uuid>,
+    #[sea_orm(nullable)]
+    pub confirmation_sent_at: Option<DateTime>,
+    #[sea_orm(default_value = 1)]
+    pub role_id: i32,
     #[sea_orm(default_value = 0)]
-    pub penalty_points: u8,
+    #[serde(skip)]
+    pub token_version: i32,
+    #[sea_orm(nullable)]
+    #[serde(skip)]
+    pub totp_secret: Option<String>,
     #[sea_orm(default_value = false)]
-    pub is_admin: bool,
+    pub totp_enabled: bool,
+    #[sea_orm(nullable)]
+    #[serde(skip)]
+    pub totp_last_step: Option<i64>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
     #[sea_orm(has_many = "super::favourite::Entity")]
     Favourite,
+    #[sea_orm(has_many = "super::password_reset_token::Entity")]
+    PasswordResetToken,
+    #[sea_orm(has_many = "super::penalty_event::Entity")]
+    PenaltyEvent,
+    #[sea_orm(has_many = "super::recovery_code::Entity")]
+    RecoveryCode,
     #[sea_orm(has_many = "super::rental::Entity")]
     Rental,
     #[sea_orm(has_many = "super::rental_history::Entity")]
     RentalHistory,
+    #[sea_orm(
+        belongs_to = "super::role::Entity",
+        from = "Column::RoleId",
+        to = "super::role::Column::Id",
+        on_update = "NoAction",
+        on_delete = "NoAction"
+    )]
+    Role,
+    #[sea_orm(has_many = "super::session::Entity")]
+    Session,
+    #[sea_orm(has_many = "super::waitlist::Entity")]
+    Waitlist,
 }
 
 impl Related<super::favourite::Entity> for Entity {
@@ -38,6 +69,24 @@ impl Related<super::favourite::Entity> for Entity {
     }
 }
 
+impl Related<super::password_reset_token::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PasswordResetToken.def()
+    }
+}
+
+impl Related<super::penalty_event::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PenaltyEvent.def()
+    }
+}
+
+impl Related<super::recovery_code::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RecoveryCode.def()
+    }
+}
+
 impl Related<super::rental::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Rental.def()
@@ -50,6 +99,24 @@ impl Related<super::rental_history::Entity> for Entity {
     }
 }
 
+impl Related<super::role::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Role.def()
+    }
+}
+
+impl Related<super::session::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Session.def()
+    }
+}
+
+impl Related<super::waitlist::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Waitlist.def()
+    }
+}
+
 impl Related<super::board_game::Entity> for Entity {
     fn to() -> RelationDef {
         super::favourite::Relation::BoardGame.def()
@@ -4,6 +4,19 @@ mod m20241225_180742_create_users_table;
 mod m20241225_182718_create_rentals_table;
 mod m20241225_190739_create_rental_history_table;
 mod m20241226_145812_create_favourites_table;
+mod m20250104_153021_create_game_copies_table;
+mod m20250104_160544_rental_reference_game_copy;
+mod m20250111_142237_create_waitlist_table;
+mod m20250118_103415_add_check_in_columns;
+mod m20250125_171802_create_penalty_events_table;
+mod m20250201_091530_add_overdue_since_column;
+mod m20250208_184410_create_password_reset_tokens_table;
+mod m20250215_120918_add_confirmation_sent_at_column;
+mod m20250222_163027_create_sessions_table;
+mod m20250301_110352_add_two_factor_auth;
+mod m20250308_142615_create_roles_tables;
+mod m20250315_153740_create_tags_tables;
+mod m20250322_101204_add_reminder_sent_at_column;
 
 pub struct Migrator;
 
@@ -16,6 +29,19 @@ impl MigratorTrait for Migrator {
             Box::new(m20241225_182718_create_rentals_table::Migration),
             Box::new(m20241225_190739_create_rental_history_table::Migration),
             Box::new(m20241226_145812_create_favourites_table::Migration),
+            Box::new(m20250104_153021_create_game_copies_table::Migration),
+            Box::new(m20250104_160544_rental_reference_game_copy::Migration),
+            Box::new(m20250111_142237_create_waitlist_table::Migration),
+            Box::new(m20250118_103415_add_check_in_columns::Migration),
+            Box::new(m20250125_171802_create_penalty_events_table::Migration),
+            Box::new(m20250201_091530_add_overdue_since_column::Migration),
+            Box::new(m20250208_184410_create_password_reset_tokens_table::Migration),
+            Box::new(m20250215_120918_add_confirmation_sent_at_column::Migration),
+            Box::new(m20250222_163027_create_sessions_table::Migration),
+            Box::new(m20250301_110352_add_two_factor_auth::Migration),
+            Box::new(m20250308_142615_create_roles_tables::Migration),
+            Box::new(m20250315_153740_create_tags_tables::Migration),
+            Box::new(m20250322_101204_add_reminder_sent_at_column::Migration),
         ]
     }
 }
\ No newline at end of file
@@ -0,0 +1,68 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+pub enum GameCopy {
+    Table,
+    Id,
+    GameId,
+    Label,
+}
+
+#[derive(DeriveIden)]
+pub enum BoardGame {
+    Table,
+    Id,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(GameCopy::Table)
+                    .if_not_exists()
+                    .col(pk_auto(GameCopy::Id))
+                    .col(integer(GameCopy::GameId))
+                    .col(string_null(GameCopy::Label))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_game_copy_game")
+                            .from(GameCopy::Table, GameCopy::GameId)
+                            .to(BoardGame::Table, BoardGame::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Every existing board game gets exactly one copy, sharing its ID,
+        // so that existing rentals can be pointed at it.
+        manager
+            .exec_stmt(
+                Query::insert()
+                    .into_table(GameCopy::Table)
+                    .columns([GameCopy::Id, GameCopy::GameId])
+                    .select_from(
+                        Query::select()
+                            .column(BoardGame::Id)
+                            .column(BoardGame::Id)
+                            .from(BoardGame::Table)
+                            .to_owned(),
+                    )
+                    .map_err(|e| DbErr::Migration(e.to_string()))?
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(GameCopy::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,205 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+#[allow(clippy::enum_variant_names)]
+pub enum Rental {
+    Table,
+    Id,
+    GameId,
+    CopyId,
+    UserId,
+    RentalDate,
+    ReturnDate,
+    ExtensionDate,
+    PickedUp,
+}
+
+#[derive(DeriveIden)]
+pub enum RentalOld {
+    Table,
+}
+
+#[derive(DeriveIden)]
+pub enum GameCopy {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+pub enum BoardGame {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+pub enum User {
+    Table,
+    Id,
+}
+
+// SQLite cannot drop a column constraint, so the rental table
+// is rebuilt from scratch and the existing rows are copied over.
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .rename_table(
+                Table::rename()
+                    .table(Rental::Table, RentalOld::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(Rental::Table)
+                    .col(pk_auto(Rental::Id))
+                    .col(integer(Rental::CopyId))
+                    .col(integer(Rental::UserId))
+                    .col(date(Rental::RentalDate))
+                    .col(date(Rental::ReturnDate))
+                    .col(date_null(Rental::ExtensionDate))
+                    .col(boolean(Rental::PickedUp).default(false))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rental_copy")
+                            .from(Rental::Table, Rental::CopyId)
+                            .to(GameCopy::Table, GameCopy::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rental_user")
+                            .from(Rental::Table, Rental::UserId)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_rental_copy")
+                    .table(Rental::Table)
+                    .col(Rental::CopyId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Copies created in the previous migration share IDs with their games.
+        manager
+            .exec_stmt(
+                Query::insert()
+                    .into_table(Rental::Table)
+                    .columns(Self::data_columns(Rental::CopyId))
+                    .select_from(
+                        Query::select()
+                            .columns(Self::data_columns(Rental::GameId))
+                            .from(RentalOld::Table)
+                            .to_owned(),
+                    )
+                    .map_err(|e| DbErr::Migration(e.to_string()))?
+                    .to_owned(),
+            )
+            .await?;
+        Self::copy_sequence(manager).await?;
+
+        manager
+            .drop_table(Table::drop().table(RentalOld::Table).to_owned())
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .rename_table(
+                Table::rename()
+                    .table(Rental::Table, RentalOld::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(Rental::Table)
+                    .col(pk_auto(Rental::Id))
+                    .col(integer_uniq(Rental::GameId))
+                    .col(integer(Rental::UserId))
+                    .col(date(Rental::RentalDate))
+                    .col(date(Rental::ReturnDate))
+                    .col(date_null(Rental::ExtensionDate))
+                    .col(boolean(Rental::PickedUp).default(false))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rental_game")
+                            .from(Rental::Table, Rental::GameId)
+                            .to(BoardGame::Table, BoardGame::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rental_user")
+                            .from(Rental::Table, Rental::UserId)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Only one rental per game can survive the downgrade.
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"INSERT INTO "rental" ("id", "game_id", "user_id", "rental_date", "return_date", "extension_date", "picked_up")
+                SELECT MIN("rental_old"."id"), "game_copy"."game_id", "rental_old"."user_id", "rental_old"."rental_date",
+                    "rental_old"."return_date", "rental_old"."extension_date", "rental_old"."picked_up"
+                FROM "rental_old" INNER JOIN "game_copy" ON "rental_old"."copy_id" = "game_copy"."id"
+                GROUP BY "game_copy"."game_id""#,
+            )
+            .await?;
+        Self::copy_sequence(manager).await?;
+
+        manager
+            .drop_table(Table::drop().table(RentalOld::Table).to_owned())
+            .await
+    }
+}
+
+impl Migration {
+    // Rental IDs are reused as rental history IDs, so the autoincrement
+    // counter has to carry on from where the old table left off.
+    async fn copy_sequence(manager: &SchemaManager<'_>) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"DELETE FROM "sqlite_sequence" WHERE "name" = 'rental';
+                INSERT INTO "sqlite_sequence" ("name", "seq")
+                SELECT 'rental', "seq" FROM "sqlite_sequence" WHERE "name" = 'rental_old'"#,
+            )
+            .await?;
+        Ok(())
+    }
+
+    fn data_columns(item_column: Rental) -> [Rental; 7] {
+        [
+            Rental::Id,
+            item_column,
+            Rental::UserId,
+            Rental::RentalDate,
+            Rental::ReturnDate,
+            Rental::ExtensionDate,
+            Rental::PickedUp,
+        ]
+    }
+}
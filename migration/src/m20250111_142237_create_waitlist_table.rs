@@ -0,0 +1,90 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Waitlist {
+    Table,
+    Id,
+    GameId,
+    UserId,
+    CreatedAt,
+    HoldCopyId,
+    HoldExpiresAt,
+}
+
+#[derive(DeriveIden)]
+pub enum BoardGame {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+pub enum GameCopy {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+pub enum User {
+    Table,
+    Id,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Waitlist::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Waitlist::Id))
+                    .col(integer(Waitlist::GameId))
+                    .col(integer(Waitlist::UserId))
+                    .col(date_time(Waitlist::CreatedAt))
+                    .col(integer_null(Waitlist::HoldCopyId))
+                    .col(date_time_null(Waitlist::HoldExpiresAt))
+                    .index(
+                        Index::create()
+                            .name("idx_waitlist_game_user")
+                            .col(Waitlist::GameId)
+                            .col(Waitlist::UserId)
+                            .unique(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_waitlist_game")
+                            .from(Waitlist::Table, Waitlist::GameId)
+                            .to(BoardGame::Table, BoardGame::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_waitlist_user")
+                            .from(Waitlist::Table, Waitlist::UserId)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_waitlist_hold_copy")
+                            .from(Waitlist::Table, Waitlist::HoldCopyId)
+                            .to(GameCopy::Table, GameCopy::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Waitlist::Table).to_owned())
+            .await
+    }
+}
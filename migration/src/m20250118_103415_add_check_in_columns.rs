@@ -0,0 +1,58 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+pub enum Rental {
+    Table,
+    PickedUpAt,
+    PickedUpBy,
+}
+
+#[derive(DeriveIden)]
+pub enum RentalHistory {
+    Table,
+    PickedUpAt,
+    PickedUpBy,
+    ReturnedAt,
+    ReturnedBy,
+}
+
+// SQLite allows only one column to be added per statement.
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let columns = [
+            (Rental::Table.into_iden(), date_time_null(Rental::PickedUpAt)),
+            (Rental::Table.into_iden(), integer_null(Rental::PickedUpBy)),
+            (RentalHistory::Table.into_iden(), date_time_null(RentalHistory::PickedUpAt)),
+            (RentalHistory::Table.into_iden(), integer_null(RentalHistory::PickedUpBy)),
+            (RentalHistory::Table.into_iden(), date_time_null(RentalHistory::ReturnedAt)),
+            (RentalHistory::Table.into_iden(), integer_null(RentalHistory::ReturnedBy)),
+        ];
+        for (table, column) in columns {
+            manager
+                .alter_table(Table::alter().table(table).add_column(column).to_owned())
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let columns = [
+            (Rental::Table.into_iden(), Rental::PickedUpAt.into_iden()),
+            (Rental::Table.into_iden(), Rental::PickedUpBy.into_iden()),
+            (RentalHistory::Table.into_iden(), RentalHistory::PickedUpAt.into_iden()),
+            (RentalHistory::Table.into_iden(), RentalHistory::PickedUpBy.into_iden()),
+            (RentalHistory::Table.into_iden(), RentalHistory::ReturnedAt.into_iden()),
+            (RentalHistory::Table.into_iden(), RentalHistory::ReturnedBy.into_iden()),
+        ];
+        for (table, column) in columns {
+            manager
+                .alter_table(Table::alter().table(table).drop_column(column).to_owned())
+                .await?;
+        }
+        Ok(())
+    }
+}
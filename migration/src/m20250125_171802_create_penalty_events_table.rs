@@ -0,0 +1,115 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum PenaltyEvent {
+    Table,
+    Id,
+    UserId,
+    RentalId,
+    Reason,
+    Points,
+    Description,
+    CreatedAt,
+    ExpiresAt,
+}
+
+#[derive(DeriveIden)]
+pub enum User {
+    Table,
+    Id,
+    PenaltyPoints,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PenaltyEvent::Table)
+                    .if_not_exists()
+                    .col(pk_auto(PenaltyEvent::Id))
+                    .col(integer(PenaltyEvent::UserId))
+                    .col(integer_null(PenaltyEvent::RentalId))
+                    .col(string_len(PenaltyEvent::Reason, 16))
+                    .col(integer(PenaltyEvent::Points))
+                    .col(text_null(PenaltyEvent::Description))
+                    .col(date_time(PenaltyEvent::CreatedAt))
+                    .col(date_time_null(PenaltyEvent::ExpiresAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_penalty_event_user")
+                            .from(PenaltyEvent::Table, PenaltyEvent::UserId)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Points given by hand so far are kept as non-expiring manual events.
+        manager
+            .exec_stmt(
+                Query::insert()
+                    .into_table(PenaltyEvent::Table)
+                    .columns([
+                        PenaltyEvent::UserId,
+                        PenaltyEvent::Reason,
+                        PenaltyEvent::Points,
+                        PenaltyEvent::CreatedAt,
+                    ])
+                    .select_from(
+                        Query::select()
+                            .column(User::Id)
+                            .expr(Expr::val("manual"))
+                            .column(User::PenaltyPoints)
+                            .expr(Expr::current_timestamp())
+                            .from(User::Table)
+                            .and_where(Expr::col(User::PenaltyPoints).gt(0))
+                            .to_owned(),
+                    )
+                    .map_err(|e| DbErr::Migration(e.to_string()))?
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .drop_column(User::PenaltyPoints)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(tiny_unsigned(User::PenaltyPoints).default(0))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"UPDATE "user" SET "penalty_points" = MIN(255, (
+                    SELECT COALESCE(SUM("points"), 0) FROM "penalty_event"
+                    WHERE "penalty_event"."user_id" = "user"."id"
+                        AND ("expires_at" IS NULL OR "expires_at" > CURRENT_TIMESTAMP)
+                ))"#,
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(PenaltyEvent::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,47 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+pub enum User {
+    Table,
+    ConfirmationToken,
+    ConfirmationSentAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(date_time_null(User::ConfirmationSentAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Tokens sent before the migration are treated as sent now, so they don't expire at once.
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(User::Table)
+                    .value(User::ConfirmationSentAt, Expr::current_timestamp())
+                    .and_where(Expr::col(User::ConfirmationToken).is_not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .drop_column(User::ConfirmationSentAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,74 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+pub enum User {
+    Table,
+    Id,
+    TotpSecret,
+    TotpEnabled,
+    TotpLastStep,
+}
+
+#[derive(DeriveIden)]
+enum RecoveryCode {
+    Table,
+    Id,
+    UserId,
+    CodeHash,
+    UsedAt,
+}
+
+// SQLite allows only one column to be added per statement.
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let columns = [
+            string_null(User::TotpSecret),
+            boolean(User::TotpEnabled).default(false).to_owned(),
+            big_integer_null(User::TotpLastStep),
+        ];
+        for column in columns {
+            manager
+                .alter_table(Table::alter().table(User::Table).add_column(column).to_owned())
+                .await?;
+        }
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(RecoveryCode::Table)
+                    .if_not_exists()
+                    .col(pk_auto(RecoveryCode::Id))
+                    .col(integer(RecoveryCode::UserId))
+                    .col(string(RecoveryCode::CodeHash))
+                    .col(date_time_null(RecoveryCode::UsedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_recovery_code_user")
+                            .from(RecoveryCode::Table, RecoveryCode::UserId)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(RecoveryCode::Table).to_owned())
+            .await?;
+
+        let columns = [User::TotpSecret, User::TotpEnabled, User::TotpLastStep];
+        for column in columns {
+            manager
+                .alter_table(Table::alter().table(User::Table).drop_column(column).to_owned())
+                .await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,177 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Role {
+    Table,
+    Id,
+    Name,
+}
+
+#[derive(DeriveIden)]
+enum RolePermission {
+    Table,
+    RoleId,
+    Permission,
+}
+
+#[derive(DeriveIden)]
+pub enum User {
+    Table,
+    RoleId,
+    IsAdmin,
+}
+
+const MEMBER_ROLE_ID: i32 = 1;
+const SUPERADMIN_ROLE_ID: i32 = 4;
+
+/// Default roles along with their permissions.
+const ROLES: [(i32, &str, &[&str]); 4] = [
+    (MEMBER_ROLE_ID, "member", &[]),
+    (2, "desk_staff", &["view_users", "manage_rentals"]),
+    (3, "catalog_editor", &["manage_catalog"]),
+    (
+        SUPERADMIN_ROLE_ID,
+        "superadmin",
+        &[
+            "view_users",
+            "manage_users",
+            "manage_roles",
+            "manage_catalog",
+            "manage_rentals",
+        ],
+    ),
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Role::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Role::Id))
+                    .col(string_uniq(Role::Name))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(RolePermission::Table)
+                    .if_not_exists()
+                    .col(integer(RolePermission::RoleId))
+                    .col(string_len(RolePermission::Permission, 32))
+                    .primary_key(
+                        Index::create()
+                            .col(RolePermission::RoleId)
+                            .col(RolePermission::Permission),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_role_permission_role")
+                            .from(RolePermission::Table, RolePermission::RoleId)
+                            .to(Role::Table, Role::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        for (id, name, permissions) in ROLES {
+            manager
+                .exec_stmt(
+                    Query::insert()
+                        .into_table(Role::Table)
+                        .columns([Role::Id, Role::Name])
+                        .values_panic([id.into(), name.into()])
+                        .to_owned(),
+                )
+                .await?;
+            for permission in permissions {
+                manager
+                    .exec_stmt(
+                        Query::insert()
+                            .into_table(RolePermission::Table)
+                            .columns([RolePermission::RoleId, RolePermission::Permission])
+                            .values_panic([id.into(), (*permission).into()])
+                            .to_owned(),
+                    )
+                    .await?;
+            }
+        }
+
+        // SQLite can't add a foreign key to an existing table, so `role_id` is checked
+        // by the application instead.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(integer(User::RoleId).default(MEMBER_ROLE_ID))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Admins so far become superadmins.
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(User::Table)
+                    .value(User::RoleId, SUPERADMIN_ROLE_ID)
+                    .and_where(Expr::col(User::IsAdmin).eq(true))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .drop_column(User::IsAdmin)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(boolean(User::IsAdmin).default(false))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(User::Table)
+                    .value(User::IsAdmin, true)
+                    .and_where(Expr::col(User::RoleId).eq(SUPERADMIN_ROLE_ID))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .drop_column(User::RoleId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(RolePermission::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Role::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,180 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Category {
+    Table,
+    Id,
+    Name,
+}
+
+#[derive(DeriveIden)]
+enum Tag {
+    Table,
+    Id,
+    Name,
+}
+
+#[derive(DeriveIden)]
+enum BoardGameCategory {
+    Table,
+    GameId,
+    CategoryId,
+}
+
+#[derive(DeriveIden)]
+enum BoardGameTag {
+    Table,
+    GameId,
+    TagId,
+}
+
+#[derive(DeriveIden)]
+enum BoardGame {
+    Table,
+    Id,
+}
+
+/// Categories describe what kind of game it is.
+const CATEGORIES: [&str; 4] = ["family", "party", "strategy", "abstract"];
+
+/// Tags describe the mechanics of the game.
+const TAGS: [&str; 5] = [
+    "cooperative",
+    "deck-building",
+    "worker-placement",
+    "bluffing",
+    "drafting",
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Category::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Category::Id))
+                    .col(string_uniq(Category::Name))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(Tag::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Tag::Id))
+                    .col(string_uniq(Tag::Name))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(BoardGameCategory::Table)
+                    .if_not_exists()
+                    .col(integer(BoardGameCategory::GameId))
+                    .col(integer(BoardGameCategory::CategoryId))
+                    .primary_key(
+                        Index::create()
+                            .col(BoardGameCategory::GameId)
+                            .col(BoardGameCategory::CategoryId),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_board_game_category_game")
+                            .from(BoardGameCategory::Table, BoardGameCategory::GameId)
+                            .to(BoardGame::Table, BoardGame::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_board_game_category_category")
+                            .from(BoardGameCategory::Table, BoardGameCategory::CategoryId)
+                            .to(Category::Table, Category::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(BoardGameTag::Table)
+                    .if_not_exists()
+                    .col(integer(BoardGameTag::GameId))
+                    .col(integer(BoardGameTag::TagId))
+                    .primary_key(
+                        Index::create()
+                            .col(BoardGameTag::GameId)
+                            .col(BoardGameTag::TagId),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_board_game_tag_game")
+                            .from(BoardGameTag::Table, BoardGameTag::GameId)
+                            .to(BoardGame::Table, BoardGame::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_board_game_tag_tag")
+                            .from(BoardGameTag::Table, BoardGameTag::TagId)
+                            .to(Tag::Table, Tag::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        for name in CATEGORIES {
+            manager
+                .exec_stmt(
+                    Query::insert()
+                        .into_table(Category::Table)
+                        .columns([Category::Name])
+                        .values_panic([name.into()])
+                        .to_owned(),
+                )
+                .await?;
+        }
+        for name in TAGS {
+            manager
+                .exec_stmt(
+                    Query::insert()
+                        .into_table(Tag::Table)
+                        .columns([Tag::Name])
+                        .values_panic([name.into()])
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(BoardGameTag::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(BoardGameCategory::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Tag::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Category::Table).to_owned())
+            .await
+    }
+}
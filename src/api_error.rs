@@ -0,0 +1,536 @@
+use crate::mailer::MailerError;
+use crate::media_store::MediaError;
+use crate::rental_policy::RentalDenial;
+use crate::validation::{FieldError, FieldErrorResponse};
+use actix_web::body::MessageBody;
+use actix_web::dev::ServiceResponse;
+use actix_web::http::header::{self, AcceptLanguage, Header, Preference};
+use actix_web::http::StatusCode;
+use actix_web::middleware::ErrorHandlerResponse;
+use actix_web::{HttpRequest, HttpResponse, ResponseError};
+use argon2::password_hash;
+use chrono::{DateTime, Utc};
+use sea_orm::{DbErr, SqlErr};
+use serde::Serialize;
+use std::fmt;
+use utoipa::ToSchema;
+
+/// Language of the messages shown to the user.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum Language {
+    #[default]
+    Pl,
+    En,
+}
+
+impl Language {
+    /// Picks the supported language the client prefers the most according to `Accept-Language`.
+    pub(crate) fn from_request(req: &HttpRequest) -> Self {
+        let preferences = match AcceptLanguage::parse(req) {
+            Ok(accept_language) => accept_language.ranked(),
+            Err(_) => return Language::default(),
+        };
+        preferences
+            .iter()
+            .find_map(|preference| match preference {
+                Preference::Specific(tag) => match tag.primary_language() {
+                    "pl" => Some(Language::Pl),
+                    "en" => Some(Language::En),
+                    _ => None,
+                },
+                Preference::Any => Some(Language::default()),
+            })
+            .unwrap_or_default()
+    }
+
+    fn pick(self, pl: &'static str, en: &'static str) -> &'static str {
+        match self {
+            Language::Pl => pl,
+            Language::En => en,
+        }
+    }
+}
+
+/// Error returned by the API. Every error has a stable code the clients can rely on
+/// and a message that can be shown to the user.
+#[derive(Debug)]
+pub(crate) enum ApiError {
+    // Authentication and authorization
+    MissingToken,
+    InvalidToken,
+    TokenRevoked,
+    Forbidden,
+    TwoFactorRequired,
+    InvalidCredentials,
+    AccountNotConfirmed,
+    SessionExpired,
+    LoginChallengeExpired,
+    InvalidTwoFactorCode,
+    TwoFactorAlreadyEnabled,
+    TwoFactorNotSetUp,
+    TwoFactorNotEnabled,
+    TooManyLoginAttempts {
+        retry_at: DateTime<Utc>,
+    },
+    TooManyRegistrations {
+        retry_at: DateTime<Utc>,
+    },
+    TooManyConfirmationEmails {
+        retry_at: DateTime<Utc>,
+    },
+    AccountLocked {
+        retry_at: DateTime<Utc>,
+    },
+    InvalidConfirmationLink,
+    AccountAlreadyConfirmed,
+    ConfirmationLinkExpired,
+    InvalidPasswordResetLink,
+
+    // Missing resources
+    NotFound,
+    UserNotFound,
+    RoleNotFound,
+    BoardGameNotFound,
+    RentalNotFound,
+    ExtensionRequestNotFound,
+    NotInWaitlist,
+
+    // Invalid requests
+    InvalidRequest(String),
+    /// A model failed validation in `before_save`, see the `entity` crate.
+    Validation(String),
+    /// Some fields of a submitted form are invalid.
+    InvalidFields(Vec<FieldError>),
+    InvalidDateFormat,
+    InvalidDateRange,
+    ReturnBeforeRentalDate,
+    RentalDateInPast,
+    ExtensionNotAfterReturnDate,
+    ImageTooLarge,
+    UnsupportedImageType,
+
+    // Conflicts with the current state
+    AlreadyExists,
+    ReferenceConflict,
+    DatesUnavailable,
+    AlreadyPickedUp,
+    /// Dates of a picked up rental can only be changed by staff, users have to request an extension.
+    ExtensionRequired,
+    NotPickedUp,
+    RentalNotStarted,
+    AlreadyInWaitlist,
+    GameAvailable,
+    RentalDenied(RentalDenial),
+
+    Internal,
+}
+
+impl ApiError {
+    pub(crate) fn code(&self) -> &'static str {
+        match self {
+            ApiError::MissingToken => "MISSING_TOKEN",
+            ApiError::InvalidToken => "INVALID_TOKEN",
+            ApiError::TokenRevoked => "TOKEN_REVOKED",
+            ApiError::Forbidden => "FORBIDDEN",
+            ApiError::TwoFactorRequired => "TWO_FACTOR_REQUIRED",
+            ApiError::InvalidCredentials => "INVALID_CREDENTIALS",
+            ApiError::AccountNotConfirmed => "ACCOUNT_NOT_CONFIRMED",
+            ApiError::SessionExpired => "SESSION_EXPIRED",
+            ApiError::LoginChallengeExpired => "LOGIN_CHALLENGE_EXPIRED",
+            ApiError::InvalidTwoFactorCode => "INVALID_TWO_FACTOR_CODE",
+            ApiError::TwoFactorAlreadyEnabled => "TWO_FACTOR_ALREADY_ENABLED",
+            ApiError::TwoFactorNotSetUp => "TWO_FACTOR_NOT_SET_UP",
+            ApiError::TwoFactorNotEnabled => "TWO_FACTOR_NOT_ENABLED",
+            ApiError::TooManyLoginAttempts { .. } => "TOO_MANY_LOGIN_ATTEMPTS",
+            ApiError::TooManyRegistrations { .. } => "TOO_MANY_REGISTRATIONS",
+            ApiError::TooManyConfirmationEmails { .. } => "TOO_MANY_CONFIRMATION_EMAILS",
+            ApiError::AccountLocked { .. } => "ACCOUNT_LOCKED",
+            ApiError::InvalidConfirmationLink => "INVALID_CONFIRMATION_LINK",
+            ApiError::AccountAlreadyConfirmed => "ACCOUNT_ALREADY_CONFIRMED",
+            ApiError::ConfirmationLinkExpired => "CONFIRMATION_LINK_EXPIRED",
+            ApiError::InvalidPasswordResetLink => "INVALID_PASSWORD_RESET_LINK",
+            ApiError::NotFound => "NOT_FOUND",
+            ApiError::UserNotFound => "USER_NOT_FOUND",
+            ApiError::RoleNotFound => "ROLE_NOT_FOUND",
+            ApiError::BoardGameNotFound => "BOARD_GAME_NOT_FOUND",
+            ApiError::RentalNotFound => "RENTAL_NOT_FOUND",
+            ApiError::ExtensionRequestNotFound => "EXTENSION_REQUEST_NOT_FOUND",
+            ApiError::NotInWaitlist => "NOT_IN_WAITLIST",
+            ApiError::InvalidRequest(_) => "INVALID_REQUEST",
+            ApiError::Validation(_) => "VALIDATION_FAILED",
+            ApiError::InvalidFields(_) => "INVALID_FIELDS",
+            ApiError::InvalidDateFormat => "INVALID_DATE_FORMAT",
+            ApiError::InvalidDateRange => "INVALID_DATE_RANGE",
+            ApiError::ReturnBeforeRentalDate => "RETURN_BEFORE_RENTAL_DATE",
+            ApiError::RentalDateInPast => "RENTAL_DATE_IN_PAST",
+            ApiError::ExtensionNotAfterReturnDate => "EXTENSION_NOT_AFTER_RETURN_DATE",
+            ApiError::ImageTooLarge => "IMAGE_TOO_LARGE",
+            ApiError::UnsupportedImageType => "UNSUPPORTED_IMAGE_TYPE",
+            ApiError::AlreadyExists => "ALREADY_EXISTS",
+            ApiError::ReferenceConflict => "REFERENCE_CONFLICT",
+            ApiError::DatesUnavailable => "DATES_UNAVAILABLE",
+            ApiError::AlreadyPickedUp => "ALREADY_PICKED_UP",
+            ApiError::ExtensionRequired => "EXTENSION_REQUIRED",
+            ApiError::NotPickedUp => "NOT_PICKED_UP",
+            ApiError::RentalNotStarted => "RENTAL_NOT_STARTED",
+            ApiError::AlreadyInWaitlist => "ALREADY_IN_WAITLIST",
+            ApiError::GameAvailable => "GAME_AVAILABLE",
+            ApiError::RentalDenied(_) => "RENTAL_DENIED",
+            ApiError::Internal => "INTERNAL_ERROR",
+        }
+    }
+
+    /// Returns a message that can be shown to the user.
+    pub(crate) fn message(&self, language: Language) -> String {
+        let (pl, en) = match self {
+            ApiError::RentalDenied(denial) => return denial.message(language),
+            ApiError::MissingToken => ("Brak tokenu uwierzytelniającego", "No token provided"),
+            ApiError::InvalidToken => ("Nieprawidłowy token", "Invalid token"),
+            ApiError::TokenRevoked => (
+                "Sesja została zakończona, zaloguj się ponownie",
+                "The session has ended, please log in again",
+            ),
+            ApiError::Forbidden => ("Brak uprawnień", "Insufficient privileges"),
+            ApiError::TwoFactorRequired => (
+                "Wymagana jest weryfikacja dwuetapowa",
+                "Two-factor authentication required",
+            ),
+            ApiError::InvalidCredentials => {
+                ("Nieprawidłowe dane logowania", "Invalid login credentials")
+            }
+            ApiError::AccountNotConfirmed => (
+                "Konto nie zostało jeszcze aktywowane",
+                "The account has not been activated yet",
+            ),
+            ApiError::SessionExpired => (
+                "Sesja wygasła, zaloguj się ponownie",
+                "The session has expired, please log in again",
+            ),
+            ApiError::LoginChallengeExpired => (
+                "Sesja logowania wygasła, zaloguj się ponownie",
+                "The login session has expired, please log in again",
+            ),
+            ApiError::InvalidTwoFactorCode => {
+                ("Nieprawidłowy kod weryfikacyjny", "Invalid verification code")
+            }
+            ApiError::TwoFactorAlreadyEnabled => (
+                "Weryfikacja dwuetapowa jest już włączona",
+                "Two-factor authentication is already enabled",
+            ),
+            ApiError::TwoFactorNotSetUp => (
+                "Najpierw należy wygenerować klucz weryfikacji dwuetapowej",
+                "A two-factor authentication key has to be generated first",
+            ),
+            ApiError::TwoFactorNotEnabled => (
+                "Weryfikacja dwuetapowa nie jest włączona",
+                "Two-factor authentication is not enabled",
+            ),
+            ApiError::TooManyLoginAttempts { .. } => (
+                "Zbyt wiele prób logowania, spróbuj ponownie później",
+                "Too many login attempts, please try again later",
+            ),
+            ApiError::TooManyRegistrations { .. } => (
+                "Zbyt wiele prób rejestracji, spróbuj ponownie później",
+                "Too many registration attempts, please try again later",
+            ),
+            ApiError::TooManyConfirmationEmails { .. } => (
+                "Zbyt wiele próśb o link potwierdzający, spróbuj ponownie później",
+                "Too many confirmation link requests, please try again later",
+            ),
+            ApiError::AccountLocked { .. } => (
+                "Konto zostało tymczasowo zablokowane z powodu zbyt wielu nieudanych prób logowania",
+                "The account has been temporarily locked after too many failed login attempts",
+            ),
+            ApiError::InvalidConfirmationLink => (
+                "Link aktywacyjny jest nieprawidłowy",
+                "The activation link is invalid",
+            ),
+            ApiError::AccountAlreadyConfirmed => (
+                "Konto zostało już aktywowane",
+                "The account has already been activated",
+            ),
+            ApiError::ConfirmationLinkExpired => {
+                ("Link aktywacyjny wygasł", "The activation link has expired")
+            }
+            ApiError::InvalidPasswordResetLink => (
+                "Link do resetowania hasła jest nieprawidłowy lub wygasł",
+                "The password reset link is invalid or has expired",
+            ),
+            ApiError::NotFound => ("Nie znaleziono zasobu", "Resource not found"),
+            ApiError::UserNotFound => ("Nie znaleziono użytkownika", "User not found"),
+            ApiError::RoleNotFound => ("Nie znaleziono roli", "Role not found"),
+            ApiError::BoardGameNotFound => ("Nie znaleziono gry", "Board game not found"),
+            ApiError::RentalNotFound => (
+                "Nie znaleziono wypożyczenia w bazie danych",
+                "Rental not found",
+            ),
+            ApiError::ExtensionRequestNotFound => (
+                "Nie znaleziono prośby o przedłużenie",
+                "Extension request not found",
+            ),
+            ApiError::NotInWaitlist => (
+                "Nie jesteś w kolejce po tę grę",
+                "You are not in the waitlist for this game",
+            ),
+            ApiError::InvalidRequest(_) => ("Nieprawidłowe zapytanie", "Invalid request"),
+            ApiError::Validation(_) => ("Nieprawidłowe dane", "Invalid data"),
+            ApiError::InvalidFields(_) => (
+                "Formularz zawiera nieprawidłowe dane",
+                "The form contains invalid data",
+            ),
+            ApiError::InvalidDateFormat => ("Niepoprawny format daty", "Invalid date format"),
+            ApiError::InvalidDateRange => ("Niepoprawny zakres dat", "Invalid date range"),
+            ApiError::ReturnBeforeRentalDate => (
+                "Data zwrotu nie może być wcześniejsza niż data wypożyczenia",
+                "The return date cannot be earlier than the rental date",
+            ),
+            ApiError::RentalDateInPast => (
+                "Nie można zarezerwować gry w przeszłości",
+                "A game cannot be booked in the past",
+            ),
+            ApiError::ExtensionNotAfterReturnDate => (
+                "Data przedłużenia musi być późniejsza niż data zwrotu",
+                "The extension date has to be later than the return date",
+            ),
+            ApiError::ImageTooLarge => (
+                "Obraz może mieć najwyżej 5 MiB i 6000 × 6000 pikseli",
+                "The image can be at most 5 MiB and 6000 × 6000 pixels",
+            ),
+            ApiError::UnsupportedImageType => (
+                "Obraz musi być w formacie PNG, JPEG lub WebP",
+                "The image has to be a PNG, JPEG or WebP file",
+            ),
+            ApiError::AlreadyExists => ("Taki rekord już istnieje", "Such a record already exists"),
+            ApiError::ReferenceConflict => (
+                "Operacja narusza powiązania z innymi danymi",
+                "The operation conflicts with related data",
+            ),
+            ApiError::DatesUnavailable => (
+                "Wybrany termin koliduje z innymi rezerwacjami tej gry",
+                "The chosen dates overlap with other bookings of this game",
+            ),
+            ApiError::AlreadyPickedUp => {
+                ("Gra została już odebrana", "The game has already been picked up")
+            }
+            ApiError::ExtensionRequired => (
+                "Gra została już odebrana, aby zatrzymać ją dłużej, poproś o przedłużenie",
+                "The game has already been picked up, request an extension to keep it longer",
+            ),
+            ApiError::NotPickedUp => (
+                "Gra nie została jeszcze odebrana",
+                "The game has not been picked up yet",
+            ),
+            ApiError::RentalNotStarted => (
+                "Wypożyczenie jeszcze się nie rozpoczęło",
+                "The rental has not started yet",
+            ),
+            ApiError::AlreadyInWaitlist => (
+                "Jesteś już w kolejce po tę grę",
+                "You are already in the waitlist for this game",
+            ),
+            ApiError::GameAvailable => (
+                "Gra jest dostępna, można ją wypożyczyć",
+                "The game is available and can be rented",
+            ),
+            ApiError::Internal => ("Wystąpił błąd serwera", "Internal server error"),
+        };
+        language.pick(pl, en).to_string()
+    }
+
+    fn details(&self) -> Option<&str> {
+        match self {
+            ApiError::InvalidRequest(details) | ApiError::Validation(details) => Some(details),
+            _ => None,
+        }
+    }
+
+    fn retry_at(&self) -> Option<DateTime<Utc>> {
+        match self {
+            ApiError::TooManyLoginAttempts { retry_at }
+            | ApiError::TooManyRegistrations { retry_at }
+            | ApiError::TooManyConfirmationEmails { retry_at }
+            | ApiError::AccountLocked { retry_at } => Some(*retry_at),
+            _ => None,
+        }
+    }
+
+    /// Builds the response with the message in the given language.
+    pub(crate) fn localized_response(&self, language: Language) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
+        if let Some(retry_at) = self.retry_at() {
+            let retry_after = (retry_at - Utc::now()).num_seconds().max(1);
+            response.insert_header((header::RETRY_AFTER, retry_after.to_string()));
+        }
+        let denial = match self {
+            ApiError::RentalDenied(denial) => Some(denial),
+            _ => None,
+        };
+        let fields = match self {
+            ApiError::InvalidFields(errors) => Some(
+                errors
+                    .iter()
+                    .map(|error| error.to_response(language))
+                    .collect(),
+            ),
+            _ => None,
+        };
+        response.json(ErrorResponse {
+            code: self.code(),
+            error: self.message(language),
+            details: self.details(),
+            fields,
+            denial,
+        })
+    }
+}
+
+/// Body of every error response.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ErrorResponse<'a> {
+    code: &'static str,
+    error: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    details: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fields: Option<Vec<FieldErrorResponse>>,
+    #[serde(flatten)]
+    denial: Option<&'a RentalDenial>,
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code(), self.message(Language::En))?;
+        match self.details() {
+            Some(details) => write!(f, " ({})", details),
+            None => Ok(()),
+        }
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::MissingToken
+            | ApiError::InvalidToken
+            | ApiError::TokenRevoked
+            | ApiError::InvalidCredentials
+            | ApiError::AccountNotConfirmed
+            | ApiError::SessionExpired
+            | ApiError::LoginChallengeExpired
+            | ApiError::InvalidTwoFactorCode => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden | ApiError::TwoFactorRequired | ApiError::RentalDenied(_) => {
+                StatusCode::FORBIDDEN
+            }
+            ApiError::TooManyLoginAttempts { .. }
+            | ApiError::TooManyRegistrations { .. }
+            | ApiError::TooManyConfirmationEmails { .. }
+            | ApiError::AccountLocked { .. } => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::NotFound
+            | ApiError::UserNotFound
+            | ApiError::RoleNotFound
+            | ApiError::BoardGameNotFound
+            | ApiError::RentalNotFound
+            | ApiError::ExtensionRequestNotFound
+            | ApiError::NotInWaitlist => StatusCode::NOT_FOUND,
+            ApiError::TwoFactorNotSetUp
+            | ApiError::TwoFactorNotEnabled
+            | ApiError::InvalidConfirmationLink
+            | ApiError::InvalidPasswordResetLink
+            | ApiError::InvalidRequest(_)
+            | ApiError::InvalidDateFormat
+            | ApiError::InvalidDateRange
+            | ApiError::ReturnBeforeRentalDate
+            | ApiError::RentalDateInPast
+            | ApiError::ExtensionNotAfterReturnDate => StatusCode::BAD_REQUEST,
+            ApiError::ImageTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            ApiError::UnsupportedImageType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ApiError::Validation(_) | ApiError::InvalidFields(_) => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            ApiError::ConfirmationLinkExpired => StatusCode::GONE,
+            ApiError::TwoFactorAlreadyEnabled
+            | ApiError::AccountAlreadyConfirmed
+            | ApiError::AlreadyExists
+            | ApiError::ReferenceConflict
+            | ApiError::DatesUnavailable
+            | ApiError::AlreadyPickedUp
+            | ApiError::ExtensionRequired
+            | ApiError::NotPickedUp
+            | ApiError::RentalNotStarted
+            | ApiError::AlreadyInWaitlist
+            | ApiError::GameAvailable => StatusCode::CONFLICT,
+            ApiError::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        self.localized_response(Language::default())
+    }
+}
+
+impl From<DbErr> for ApiError {
+    fn from(err: DbErr) -> Self {
+        match err.sql_err() {
+            Some(SqlErr::UniqueConstraintViolation(_)) => return ApiError::AlreadyExists,
+            Some(SqlErr::ForeignKeyConstraintViolation(_)) => return ApiError::ReferenceConflict,
+            _ => {}
+        }
+        match err {
+            // `DbErr::Custom` is only returned by the models' `before_save` validation.
+            DbErr::Custom(message) => ApiError::Validation(message),
+            DbErr::RecordNotFound(_) | DbErr::RecordNotUpdated => ApiError::NotFound,
+            err => internal_error("database error", err),
+        }
+    }
+}
+
+impl From<MailerError> for ApiError {
+    fn from(err: MailerError) -> Self {
+        internal_error("failed to send email", err)
+    }
+}
+
+impl From<MediaError> for ApiError {
+    fn from(err: MediaError) -> Self {
+        internal_error("failed to access media storage", err)
+    }
+}
+
+impl From<password_hash::Error> for ApiError {
+    fn from(err: password_hash::Error) -> Self {
+        internal_error("failed to hash password", err)
+    }
+}
+
+impl From<jsonwebtoken::errors::Error> for ApiError {
+    fn from(err: jsonwebtoken::errors::Error) -> Self {
+        internal_error("failed to generate token", err)
+    }
+}
+
+/// Logs an unexpected error, which is hidden from the user.
+pub(crate) fn internal_error(context: &str, err: impl fmt::Display) -> ApiError {
+    eprintln!("Internal error, {}: {}", context, err);
+    ApiError::Internal
+}
+
+/// Translates `ApiError` responses to the language preferred by the client.
+/// Registered with `ErrorHandlers`, as `ResponseError` has no access to the request.
+pub(crate) fn localize_error<B: MessageBody>(
+    res: ServiceResponse<B>,
+) -> actix_web::Result<ErrorHandlerResponse<B>> {
+    let language = Language::from_request(res.request());
+    let localized = match res.response().error() {
+        Some(err) if language != Language::default() => err
+            .as_error::<ApiError>()
+            .map(|err| err.localized_response(language)),
+        _ => None,
+    };
+
+    let res = match localized {
+        Some(response) => res.into_response(response).map_into_right_body(),
+        None => res.map_into_left_body(),
+    };
+    Ok(ErrorHandlerResponse::Response(res))
+}
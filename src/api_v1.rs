@@ -0,0 +1,1466 @@
+//! First version of the API, kept for the existing clients. It's deprecated in favour of
+//! `/api/v2`: its handlers only translate the requests and call the ones of the new version.
+
+use crate::api_error::{ApiError, ErrorResponse};
+use crate::db_manager::{
+    AvailabilityInterval, BoardGameFilter, GetBoardGamesQueryResult, GetPenaltiesQueryResult,
+    GetRentalHistoryQueryResult, GetRentalsQueryResult, GetRolesQueryResult,
+    GetUserRentalHistoryAdminQueryResult, GetUserRentalHistoryQueryResult,
+    GetUserRentalsAdminQueryResult, GetUserRentalsQueryResult, GetUsersQueryResult,
+    GetWaitlistQueryResult, Page, PageParams,
+};
+use crate::openapi::{DeprecatedAddon, SecurityAddon};
+use crate::{
+    AppState, Auth, AvailabilityQuery, BoardGameAdminResponse, BoardGameFormData,
+    BoardGameResponse, ChangePasswordFormData, ConfirmationFormData, ForgotPasswordFormData,
+    GameCopyFormData, HasToken, LabelFormData, LoginFormData, LoginResponse, ManageCatalog,
+    ManageRentals, ManageRoles, ManageUsers, PenaltyFormData, RecoveryCodesResponse,
+    RefreshTokenFormData, RegisterFormData, RentalDatesFormData, ResendConfirmationFormData,
+    ResetPasswordFormData, SetRoleFormData, TwoFactorChallengeResponse, TwoFactorCodeFormData,
+    TwoFactorLoginFormData, TwoFactorSetupResponse, ViewUsers, WithPhotoUrls,
+};
+use actix_multipart::form::MultipartForm;
+use actix_web::http::header;
+use actix_web::middleware::DefaultHeaders;
+use actix_web::web::{self, Data, Form, Json};
+use actix_web::{get, post, HttpRequest, HttpResponse};
+use chrono::NaiveDate as Date;
+use entity::category::Model as CategoryModel;
+use entity::game_copy::Model as GameCopyModel;
+use entity::tag::Model as TagModel;
+use entity::user::Model as UserModel;
+use serde::Deserialize;
+use utoipa::{OpenApi, ToSchema};
+use uuid::Uuid;
+
+/// Declares the handlers of the `/api` scope, registering them in `configure`
+/// and documenting them in `ApiDoc`, so that no route can be left out of the specification.
+macro_rules! api_handlers {
+    ($($handler:ident),* $(,)?) => {
+        pub(crate) fn configure(cfg: &mut web::ServiceConfig) {
+            $(cfg.service($handler);)*
+        }
+
+        /// OpenAPI specification of the `/api` scope, served at `/api/openapi.json`.
+        #[derive(OpenApi)]
+        #[openapi(
+            info(title = "Board game rental system API (deprecated)"),
+            servers((url = "/api")),
+            paths($($handler),*),
+            components(schemas(TwoFactorChallengeResponse, ErrorResponse)),
+            modifiers(&SecurityAddon, &DeprecatedAddon),
+        )]
+        pub(crate) struct ApiDoc;
+    };
+}
+
+api_handlers!(
+    login,
+    refresh,
+    logout,
+    logout_all,
+    login_two_factor,
+    setup_two_factor,
+    enable_two_factor,
+    disable_two_factor,
+    regenerate_recovery_codes,
+    reset_two_factor,
+    unlock_user,
+    register,
+    confirm_user,
+    resend_confirmation,
+    get_user,
+    get_users,
+    is_penalized,
+    get_my_penalties,
+    get_user_penalties,
+    add_penalty,
+    delete_penalty,
+    process_no_shows,
+    change_password,
+    forgot_password,
+    reset_password,
+    update_user,
+    set_user_role,
+    get_roles,
+    delete_user,
+    save_board_game,
+    get_board_game,
+    get_board_games,
+    search_board_games,
+    get_board_games_admin,
+    get_board_game_availability,
+    delete_board_game,
+    save_game_copy,
+    get_game_copies,
+    delete_game_copy,
+    get_categories,
+    save_category,
+    delete_category,
+    set_board_game_categories,
+    get_tags,
+    save_tag,
+    delete_tag,
+    set_board_game_tags,
+    save_rental,
+    get_rentals,
+    get_my_rentals,
+    get_user_rentals,
+    archive_rental,
+    pick_up_rental,
+    return_rental,
+    get_rental_history,
+    get_my_rental_history,
+    get_user_rental_history,
+    delete_rental_history,
+    save_extension_request,
+    accept_extension_request,
+    delete_extension_request,
+    change_favourite,
+    join_waitlist,
+    leave_waitlist,
+    get_my_waitlist,
+    get_waitlist_position,
+);
+
+/// Headers added to all responses of this version, pointing the clients to the new one.
+pub(crate) fn deprecation_headers() -> DefaultHeaders {
+    DefaultHeaders::new()
+        .add(("Deprecation", "true"))
+        .add((header::LINK, "</api/v2>; rel=\"successor-version\""))
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(as = v1::UpdateUserFormData)]
+struct UpdateUserFormData {
+    name: String,
+    surname: String,
+    email: String,
+    password: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(as = v1::BoardGameCategoriesFormData)]
+struct BoardGameCategoriesFormData {
+    category_ids: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(as = v1::BoardGameTagsFormData)]
+struct BoardGameTagsFormData {
+    tag_ids: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(as = v1::RentalFormData)]
+struct RentalFormData {
+    game_id: i32,
+    rental_date: String,
+    return_date: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(as = v1::ExtensionRequestFormData)]
+struct ExtensionRequestFormData {
+    extension_date: String,
+}
+
+/// Parses a comma-separated list of IDs.
+fn parse_ids(ids: &str) -> Result<Vec<i32>, ApiError> {
+    ids.split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(|id| {
+            id.parse()
+                .map_err(|_| ApiError::InvalidRequest(format!("invalid ID: {}", id)))
+        })
+        .collect()
+}
+
+#[utoipa::path(
+    tag = "auth",
+    responses(
+        (status = 200, description = "Session tokens, or a `TwoFactorChallengeResponse` if the user has 2FA on", body = LoginResponse),
+        (status = 401, description = "Invalid credentials or unconfirmed account", body = ErrorResponse),
+        (status = 429, description = "Too many attempts or locked account", body = ErrorResponse),
+    )
+)]
+#[post("/user/login")]
+async fn login(
+    req: HttpRequest,
+    form: Form<LoginFormData>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::login(req, Json(form.into_inner()), data).await
+}
+
+#[utoipa::path(
+    tag = "auth",
+    responses(
+        (status = 200, description = "New session tokens", body = LoginResponse),
+        (status = 401, description = "Expired, revoked or reused refresh token", body = ErrorResponse),
+    )
+)]
+#[post("/user/refresh")]
+async fn refresh(
+    form: Form<RefreshTokenFormData>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::refresh(Json(form.into_inner()), data).await
+}
+
+#[utoipa::path(
+    tag = "auth",
+    responses(
+        (status = 200, description = "Session ended"),
+    )
+)]
+#[post("/user/logout")]
+async fn logout(
+    form: Form<RefreshTokenFormData>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::logout(Json(form.into_inner()), data).await
+}
+
+#[utoipa::path(
+    tag = "auth",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "All sessions ended"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+#[post("/user/logout_all/{id}")]
+async fn logout_all(
+    id: web::Path<i32>,
+    auth: Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::logout_all(id, auth, data).await
+}
+
+#[utoipa::path(
+    tag = "auth",
+    responses(
+        (status = 200, description = "Session tokens", body = LoginResponse),
+        (status = 401, description = "Expired challenge or invalid code", body = ErrorResponse),
+        (status = 429, description = "Too many attempts or locked account", body = ErrorResponse),
+    )
+)]
+#[post("/user/2fa/login")]
+async fn login_two_factor(
+    req: HttpRequest,
+    form: Form<TwoFactorLoginFormData>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::login_two_factor(req, Json(form.into_inner()), data).await
+}
+
+#[utoipa::path(
+    tag = "auth",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "New TOTP secret", body = TwoFactorSetupResponse),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 409, description = "2FA is already on", body = ErrorResponse),
+    )
+)]
+#[post("/user/2fa/setup")]
+async fn setup_two_factor(
+    auth: Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::setup_two_factor(auth, data).await
+}
+
+#[utoipa::path(
+    tag = "auth",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Recovery codes", body = RecoveryCodesResponse),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 401, description = "Invalid token or code", body = ErrorResponse),
+        (status = 409, description = "2FA is already on", body = ErrorResponse),
+    )
+)]
+#[post("/user/2fa/enable")]
+async fn enable_two_factor(
+    form: Form<TwoFactorCodeFormData>,
+    auth: Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::enable_two_factor(Json(form.into_inner()), auth, data).await
+}
+
+#[utoipa::path(
+    tag = "auth",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "2FA turned off"),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 401, description = "Invalid token or code", body = ErrorResponse),
+    )
+)]
+#[post("/user/2fa/disable")]
+async fn disable_two_factor(
+    form: Form<TwoFactorCodeFormData>,
+    auth: Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::disable_two_factor(Json(form.into_inner()), auth, data).await
+}
+
+#[utoipa::path(
+    tag = "auth",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "New recovery codes", body = RecoveryCodesResponse),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 401, description = "Invalid token or code", body = ErrorResponse),
+    )
+)]
+#[post("/user/2fa/recovery_codes")]
+async fn regenerate_recovery_codes(
+    form: Form<TwoFactorCodeFormData>,
+    auth: Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::regenerate_recovery_codes(Json(form.into_inner()), auth, data).await
+}
+
+#[utoipa::path(
+    tag = "users",
+    security(("bearer_auth" = ["manage_users"])),
+    responses(
+        (status = 200, description = "2FA turned off"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+        (status = 404, description = "User not found", body = ErrorResponse),
+    )
+)]
+#[get("/user/2fa/reset/{id}")]
+async fn reset_two_factor(
+    id: web::Path<i32>,
+    auth: Auth<ManageUsers>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::reset_two_factor(id, auth, data).await
+}
+
+#[utoipa::path(
+    tag = "users",
+    security(("bearer_auth" = ["manage_users"])),
+    responses(
+        (status = 200, description = "Account unlocked"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+#[get("/user/unlock/{id}")]
+async fn unlock_user(
+    id: web::Path<i32>,
+    auth: Auth<ManageUsers>,
+    data: Data<AppState>,
+) -> HttpResponse {
+    crate::unlock_user(id, auth, data).await
+}
+
+#[utoipa::path(
+    tag = "auth",
+    responses(
+        (status = 200, description = "User registered", body = String, content_type = "text/plain"),
+        (status = 422, description = "Invalid form fields", body = ErrorResponse),
+        (status = 429, description = "Too many registrations", body = ErrorResponse),
+    )
+)]
+#[post("/user/register")]
+async fn register(
+    req: HttpRequest,
+    form: Form<RegisterFormData>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::register(req, Json(form.into_inner()), data).await
+}
+
+#[utoipa::path(
+    tag = "auth",
+    responses(
+        (status = 200, description = "Account confirmed"),
+        (status = 400, description = "Invalid confirmation link", body = ErrorResponse),
+        (status = 409, description = "Account is already confirmed", body = ErrorResponse),
+        (status = 410, description = "Confirmation link expired", body = ErrorResponse),
+    )
+)]
+#[get("/user/confirm/{id}/{token}")]
+async fn confirm_user(
+    path: web::Path<(i32, Uuid)>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let (id, token) = path.into_inner();
+    crate::confirm_user(id.into(), Json(ConfirmationFormData { token }), data).await
+}
+
+#[utoipa::path(
+    tag = "auth",
+    responses(
+        (status = 200, description = "Link sent if there is an unconfirmed account with this email"),
+        (status = 429, description = "Too many confirmation links requested", body = ErrorResponse),
+    )
+)]
+#[post("/user/resend_confirmation")]
+async fn resend_confirmation(
+    req: HttpRequest,
+    form: Form<ResendConfirmationFormData>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::resend_confirmation(req, Json(form.into_inner()), data).await
+}
+
+#[utoipa::path(
+    tag = "users",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "User", body = UserModel),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+        (status = 404, description = "User not found", body = ErrorResponse),
+    )
+)]
+#[get("/user/get/{id}")]
+async fn get_user(
+    id: web::Path<i32>,
+    auth: Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::get_user(id, auth, data).await
+}
+
+#[utoipa::path(
+    tag = "users",
+    params(PageParams),
+    security(("bearer_auth" = ["view_users"])),
+    responses(
+        (status = 200, description = "Page of users", body = Page<GetUsersQueryResult>),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+#[get("/user/get_all")]
+async fn get_users(
+    page: web::Query<PageParams>,
+    auth: Auth<ViewUsers>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::get_users(page, auth, data).await
+}
+
+#[utoipa::path(
+    tag = "penalties",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Whether the user is penalized", body = bool),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+#[get("/user/is_penalized/{id}")]
+async fn is_penalized(
+    id: web::Path<i32>,
+    auth: Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::is_penalized(id, auth, data).await
+}
+
+#[utoipa::path(
+    tag = "penalties",
+    params(PageParams),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Penalty points of the user and a page of their penalty events", body = GetPenaltiesQueryResult),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+    )
+)]
+#[get("/penalty/get")]
+async fn get_my_penalties(
+    page: web::Query<PageParams>,
+    auth: Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::get_my_penalties(page, auth, data).await
+}
+
+#[utoipa::path(
+    tag = "penalties",
+    params(PageParams),
+    security(("bearer_auth" = ["view_users"])),
+    responses(
+        (status = 200, description = "Penalty points of the user and a page of their penalty events", body = GetPenaltiesQueryResult),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+#[get("/penalty/get/{id}")]
+async fn get_user_penalties(
+    id: web::Path<i32>,
+    page: web::Query<PageParams>,
+    auth: Auth<ViewUsers>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::get_user_penalties(id, page, auth, data).await
+}
+
+#[utoipa::path(
+    tag = "penalties",
+    security(("bearer_auth" = ["manage_users"])),
+    responses(
+        (status = 200, description = "Penalty added"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+#[post("/penalty/add/{id}")]
+async fn add_penalty(
+    id: web::Path<i32>,
+    form: Form<PenaltyFormData>,
+    auth: Auth<ManageUsers>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::add_penalty(id, Json(form.into_inner()), auth, data).await
+}
+
+#[utoipa::path(
+    tag = "penalties",
+    security(("bearer_auth" = ["manage_users"])),
+    responses(
+        (status = 200, description = "Penalty deleted"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+#[get("/penalty/delete/{id}")]
+async fn delete_penalty(
+    id: web::Path<i32>,
+    auth: Auth<ManageUsers>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::delete_penalty(id, auth, data).await
+}
+
+#[utoipa::path(
+    tag = "penalties",
+    security(("bearer_auth" = ["manage_rentals"])),
+    responses(
+        (status = 200, description = "Number of cancelled reservations", body = u64),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+#[get("/penalty/process")]
+async fn process_no_shows(
+    auth: Auth<ManageRentals>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::process_no_shows(auth, data).await
+}
+
+#[utoipa::path(
+    tag = "users",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Password changed"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+        (status = 404, description = "User not found", body = ErrorResponse),
+        (status = 422, description = "Password too weak", body = ErrorResponse),
+    )
+)]
+#[post("/user/change_password/{id}")]
+async fn change_password(
+    id: web::Path<i32>,
+    form: Form<ChangePasswordFormData>,
+    auth: Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::change_password(id, Json(form.into_inner()), auth, data).await
+}
+
+#[utoipa::path(
+    tag = "auth",
+    responses(
+        (status = 200, description = "Link sent if there is an account with this email"),
+    )
+)]
+#[post("/user/forgot_password")]
+async fn forgot_password(form: Form<ForgotPasswordFormData>, data: Data<AppState>) -> HttpResponse {
+    crate::forgot_password(Json(form.into_inner()), data).await
+}
+
+#[utoipa::path(
+    tag = "auth",
+    responses(
+        (status = 200, description = "Password changed"),
+        (status = 400, description = "Invalid or expired link", body = ErrorResponse),
+        (status = 422, description = "Password too weak", body = ErrorResponse),
+    )
+)]
+#[post("/user/reset_password")]
+async fn reset_password(
+    form: Form<ResetPasswordFormData>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::reset_password(Json(form.into_inner()), data).await
+}
+
+#[utoipa::path(
+    tag = "users",
+    security(("bearer_auth" = ["manage_users"])),
+    responses(
+        (status = 200, description = "Done"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+        (status = 404, description = "User not found", body = ErrorResponse),
+        (status = 422, description = "Invalid form fields", body = ErrorResponse),
+    )
+)]
+#[post("/user/update/{id}")]
+async fn update_user(
+    id: web::Path<i32>,
+    form: Form<UpdateUserFormData>,
+    auth: Auth<ManageUsers>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    // An empty password means that it's not changed.
+    let form = form.into_inner();
+    let changes = crate::UpdateUserFormData {
+        name: Some(form.name),
+        surname: Some(form.surname),
+        email: Some(form.email),
+        password: Some(form.password).filter(|password| !password.is_empty()),
+    };
+    crate::update_user(id, Json(changes), auth, data).await
+}
+
+#[utoipa::path(
+    tag = "roles",
+    security(("bearer_auth" = ["manage_roles"])),
+    responses(
+        (status = 200, description = "Role assigned"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+        (status = 404, description = "User or role not found", body = ErrorResponse),
+    )
+)]
+#[post("/user/set_role/{id}")]
+async fn set_user_role(
+    id: web::Path<i32>,
+    form: Form<SetRoleFormData>,
+    auth: Auth<ManageRoles>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::set_user_role(id, Json(form.into_inner()), auth, data).await
+}
+
+#[utoipa::path(
+    tag = "roles",
+    security(("bearer_auth" = ["manage_roles"])),
+    responses(
+        (status = 200, description = "All roles", body = Vec<GetRolesQueryResult>),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+#[get("/role/get_all")]
+async fn get_roles(
+    auth: Auth<ManageRoles>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::get_roles(auth, data).await
+}
+
+#[utoipa::path(
+    tag = "users",
+    security(("bearer_auth" = ["manage_users"])),
+    responses(
+        (status = 200, description = "User deleted"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+        (status = 404, description = "User not found", body = ErrorResponse),
+    )
+)]
+#[get("/user/delete/{id}")]
+async fn delete_user(
+    id: web::Path<i32>,
+    auth: Auth<ManageUsers>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::delete_user(id, auth, data).await
+}
+
+/// id = 0 ==> insert a new board game
+#[utoipa::path(
+    tag = "board_games",
+    security(("bearer_auth" = ["manage_catalog"])),
+    request_body(content = BoardGameFormData, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "Board game saved"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+        (status = 404, description = "Board game not found", body = ErrorResponse),
+        (status = 413, description = "Image too large", body = ErrorResponse),
+        (status = 415, description = "Unsupported image type", body = ErrorResponse),
+        (status = 422, description = "Invalid form fields", body = ErrorResponse),
+    )
+)]
+#[post("/board_game/save/{id}")]
+async fn save_board_game(
+    id: web::Path<i32>,
+    form: MultipartForm<BoardGameFormData>,
+    auth: Auth<ManageCatalog>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    match id.into_inner() {
+        0 => crate::create_board_game(form, auth, data).await,
+        id => crate::update_board_game(id.into(), form, auth, data).await,
+    }
+}
+
+#[utoipa::path(
+    tag = "board_games",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Board game", body = BoardGameResponse),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 404, description = "Board game not found", body = ErrorResponse),
+    )
+)]
+#[get("/board_game/get/{id}")]
+async fn get_board_game(
+    id: web::Path<i32>,
+    auth: Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::get_board_game(id, auth, data).await
+}
+
+#[utoipa::path(
+    tag = "board_games",
+    params(PageParams),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Page of board games", body = Page<WithPhotoUrls<GetBoardGamesQueryResult>>),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+    )
+)]
+#[get("/board_game/get_all")]
+async fn get_board_games(
+    page: web::Query<PageParams>,
+    auth: Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let board_games = data
+        .db
+        .get_board_games(auth.0.sub, page.into_inner())
+        .await?
+        .map(WithPhotoUrls::from);
+    Ok(HttpResponse::Ok().json(board_games))
+}
+
+#[utoipa::path(
+    tag = "board_games",
+    params(BoardGameFilter, PageParams),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Page of matching board games", body = Page<WithPhotoUrls<GetBoardGamesQueryResult>>),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+    )
+)]
+#[get("/board_game/search")]
+async fn search_board_games(
+    query: web::Query<BoardGameFilter>,
+    page: web::Query<PageParams>,
+    auth: Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::search_board_games(query, page, auth, data).await
+}
+
+#[utoipa::path(
+    tag = "board_games",
+    params(PageParams),
+    security(("bearer_auth" = ["manage_catalog"])),
+    responses(
+        (status = 200, description = "Page of board games with their copies and rentals", body = Page<BoardGameAdminResponse>),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+#[get("/board_game/get_all_admin")]
+async fn get_board_games_admin(
+    page: web::Query<PageParams>,
+    auth: Auth<ManageCatalog>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::get_board_games_admin(page, auth, data).await
+}
+
+#[utoipa::path(
+    tag = "board_games",
+    params(AvailabilityQuery),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Availability of the copies", body = Vec<AvailabilityInterval>),
+        (status = 400, description = "Invalid dates", body = ErrorResponse),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+    )
+)]
+#[get("/board_game/availability/{id}")]
+async fn get_board_game_availability(
+    id: web::Path<i32>,
+    query: web::Query<AvailabilityQuery>,
+    auth: Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::get_board_game_availability(id, query, auth, data).await
+}
+
+#[utoipa::path(
+    tag = "board_games",
+    security(("bearer_auth" = ["manage_catalog"])),
+    responses(
+        (status = 200, description = "Board game deleted"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+        (status = 409, description = "Board game is still referenced", body = ErrorResponse),
+    )
+)]
+#[get("/board_game/delete/{id}")]
+async fn delete_board_game(
+    id: web::Path<i32>,
+    auth: Auth<ManageCatalog>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::delete_board_game(id, auth, data).await
+}
+
+/// id = 0 ==> insert a new copy of a board game
+#[utoipa::path(
+    tag = "board_games",
+    security(("bearer_auth" = ["manage_catalog"])),
+    responses(
+        (status = 200, description = "Copy saved"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+#[post("/board_game/copy/save/{id}")]
+async fn save_game_copy(
+    id: web::Path<i32>,
+    form: Form<GameCopyFormData>,
+    auth: Auth<ManageCatalog>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    match id.into_inner() {
+        0 => crate::create_game_copy(Json(form.into_inner()), auth, data).await,
+        id => crate::update_game_copy(id.into(), Json(form.into_inner()), auth, data).await,
+    }
+}
+
+#[utoipa::path(
+    tag = "board_games",
+    params(PageParams),
+    security(("bearer_auth" = ["manage_catalog"])),
+    responses(
+        (status = 200, description = "Page of the copies of the board game", body = Page<GameCopyModel>),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+#[get("/board_game/copy/get/{id}")]
+async fn get_game_copies(
+    id: web::Path<i32>,
+    page: web::Query<PageParams>,
+    auth: Auth<ManageCatalog>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::get_game_copies(id, page, auth, data).await
+}
+
+#[utoipa::path(
+    tag = "board_games",
+    security(("bearer_auth" = ["manage_catalog"])),
+    responses(
+        (status = 200, description = "Copy deleted"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+        (status = 409, description = "Copy is still referenced", body = ErrorResponse),
+    )
+)]
+#[get("/board_game/copy/delete/{id}")]
+async fn delete_game_copy(
+    id: web::Path<i32>,
+    auth: Auth<ManageCatalog>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::delete_game_copy(id, auth, data).await
+}
+
+#[utoipa::path(
+    tag = "labels",
+    params(PageParams),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Page of categories", body = Page<CategoryModel>),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+    )
+)]
+#[get("/category/get_all")]
+async fn get_categories(
+    page: web::Query<PageParams>,
+    auth: Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::get_categories(page, auth, data).await
+}
+
+/// id = 0 ==> insert a new category
+#[utoipa::path(
+    tag = "labels",
+    security(("bearer_auth" = ["manage_catalog"])),
+    responses(
+        (status = 200, description = "Category saved"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+        (status = 409, description = "Category already exists", body = ErrorResponse),
+        (status = 422, description = "Invalid form fields", body = ErrorResponse),
+    )
+)]
+#[post("/category/save/{id}")]
+async fn save_category(
+    id: web::Path<i32>,
+    form: Form<LabelFormData>,
+    auth: Auth<ManageCatalog>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    match id.into_inner() {
+        0 => crate::create_category(Json(form.into_inner()), auth, data).await,
+        id => crate::update_category(id.into(), Json(form.into_inner()), auth, data).await,
+    }
+}
+
+#[utoipa::path(
+    tag = "labels",
+    security(("bearer_auth" = ["manage_catalog"])),
+    responses(
+        (status = 200, description = "Category deleted"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+#[get("/category/delete/{id}")]
+async fn delete_category(
+    id: web::Path<i32>,
+    auth: Auth<ManageCatalog>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::delete_category(id, auth, data).await
+}
+
+#[utoipa::path(
+    tag = "labels",
+    security(("bearer_auth" = ["manage_catalog"])),
+    responses(
+        (status = 200, description = "Categories set"),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+#[post("/board_game/categories/{id}")]
+async fn set_board_game_categories(
+    id: web::Path<i32>,
+    form: Form<BoardGameCategoriesFormData>,
+    auth: Auth<ManageCatalog>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let category_ids = parse_ids(&form.category_ids)?;
+    let form = crate::BoardGameCategoriesFormData { category_ids };
+    crate::set_board_game_categories(id, Json(form), auth, data).await
+}
+
+#[utoipa::path(
+    tag = "labels",
+    params(PageParams),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Page of tags", body = Page<TagModel>),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+    )
+)]
+#[get("/tag/get_all")]
+async fn get_tags(
+    page: web::Query<PageParams>,
+    auth: Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::get_tags(page, auth, data).await
+}
+
+/// id = 0 ==> insert a new tag
+#[utoipa::path(
+    tag = "labels",
+    security(("bearer_auth" = ["manage_catalog"])),
+    responses(
+        (status = 200, description = "Tag saved"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+        (status = 409, description = "Tag already exists", body = ErrorResponse),
+        (status = 422, description = "Invalid form fields", body = ErrorResponse),
+    )
+)]
+#[post("/tag/save/{id}")]
+async fn save_tag(
+    id: web::Path<i32>,
+    form: Form<LabelFormData>,
+    auth: Auth<ManageCatalog>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    match id.into_inner() {
+        0 => crate::create_tag(Json(form.into_inner()), auth, data).await,
+        id => crate::update_tag(id.into(), Json(form.into_inner()), auth, data).await,
+    }
+}
+
+#[utoipa::path(
+    tag = "labels",
+    security(("bearer_auth" = ["manage_catalog"])),
+    responses(
+        (status = 200, description = "Tag deleted"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+#[get("/tag/delete/{id}")]
+async fn delete_tag(
+    id: web::Path<i32>,
+    auth: Auth<ManageCatalog>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::delete_tag(id, auth, data).await
+}
+
+#[utoipa::path(
+    tag = "labels",
+    security(("bearer_auth" = ["manage_catalog"])),
+    responses(
+        (status = 200, description = "Tags set"),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+#[post("/board_game/tags/{id}")]
+async fn set_board_game_tags(
+    id: web::Path<i32>,
+    form: Form<BoardGameTagsFormData>,
+    auth: Auth<ManageCatalog>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let tag_ids = parse_ids(&form.tag_ids)?;
+    let form = crate::BoardGameTagsFormData { tag_ids };
+    crate::set_board_game_tags(id, Json(form), auth, data).await
+}
+
+/// id = 0 ==> insert new rental
+#[utoipa::path(
+    tag = "rentals",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Rental saved"),
+        (status = 400, description = "Invalid dates", body = ErrorResponse),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Rental denied by the rental policy, or someone else's rental", body = ErrorResponse),
+        (status = 404, description = "Rental not found", body = ErrorResponse),
+        (status = 409, description = "No copy is free on these dates, or the game is picked up and needs an extension request", body = ErrorResponse),
+    )
+)]
+#[post("/rental/save/{id}")]
+async fn save_rental(
+    id: web::Path<i32>,
+    form: Form<RentalFormData>,
+    auth: Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let parse_date = |date: &str| {
+        Date::parse_from_str(date, "%d.%m.%Y").map_err(|_| ApiError::InvalidDateFormat)
+    };
+    let rental_date = parse_date(&form.rental_date)?;
+    let return_date = parse_date(&form.return_date)?;
+    match id.into_inner() {
+        0 => {
+            let form = crate::RentalFormData {
+                game_id: form.game_id,
+                rental_date,
+                return_date,
+            };
+            crate::create_rental(Json(form), auth, data).await
+        }
+        id => {
+            let form = RentalDatesFormData {
+                rental_date,
+                return_date,
+            };
+            crate::update_rental(id.into(), Json(form), auth, data).await
+        }
+    }
+}
+
+#[utoipa::path(
+    tag = "rentals",
+    params(PageParams),
+    security(("bearer_auth" = ["manage_rentals"])),
+    responses(
+        (status = 200, description = "Page of rentals", body = Page<WithPhotoUrls<GetRentalsQueryResult>>),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+#[get("/rental/get_all")]
+async fn get_rentals(
+    page: web::Query<PageParams>,
+    auth: Auth<ManageRentals>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::get_rentals(page, auth, data).await
+}
+
+#[utoipa::path(
+    tag = "rentals",
+    params(PageParams),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Page of the user's rentals", body = Page<WithPhotoUrls<GetUserRentalsQueryResult>>),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+    )
+)]
+#[get("/rental/get")]
+async fn get_my_rentals(
+    page: web::Query<PageParams>,
+    auth: Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::get_my_rentals(page, auth, data).await
+}
+
+#[utoipa::path(
+    tag = "rentals",
+    params(PageParams),
+    security(("bearer_auth" = ["manage_rentals"])),
+    responses(
+        (status = 200, description = "Page of the user's rentals", body = Page<WithPhotoUrls<GetUserRentalsAdminQueryResult>>),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+#[get("/rental/get/{id}")]
+async fn get_user_rentals(
+    id: web::Path<i32>,
+    page: web::Query<PageParams>,
+    auth: Auth<ManageRentals>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::get_user_rentals(id, page, auth, data).await
+}
+
+#[utoipa::path(
+    tag = "rentals",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Rental archived"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+        (status = 404, description = "Rental not found", body = ErrorResponse),
+    )
+)]
+#[get("/rental/archive/{id}")]
+async fn archive_rental(
+    id: web::Path<i32>,
+    auth: Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::archive_rental(id, auth, data).await
+}
+
+#[utoipa::path(
+    tag = "rentals",
+    security(("bearer_auth" = ["manage_rentals"])),
+    responses(
+        (status = 200, description = "Rental picked up"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+        (status = 404, description = "Rental not found", body = ErrorResponse),
+        (status = 409, description = "Rental already picked up or not started yet", body = ErrorResponse),
+    )
+)]
+#[get("/rental/pick_up/{id}")]
+async fn pick_up_rental(
+    id: web::Path<i32>,
+    auth: Auth<ManageRentals>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::pick_up_rental(id, auth, data).await
+}
+
+#[utoipa::path(
+    tag = "rentals",
+    security(("bearer_auth" = ["manage_rentals"])),
+    responses(
+        (status = 200, description = "Rental returned"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+        (status = 404, description = "Rental not found", body = ErrorResponse),
+        (status = 409, description = "Rental not picked up", body = ErrorResponse),
+    )
+)]
+#[get("/rental/return/{id}")]
+async fn return_rental(
+    id: web::Path<i32>,
+    auth: Auth<ManageRentals>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::return_rental(id, auth, data).await
+}
+
+#[utoipa::path(
+    tag = "history",
+    params(PageParams),
+    security(("bearer_auth" = ["manage_rentals"])),
+    responses(
+        (status = 200, description = "Page of finished rentals", body = Page<WithPhotoUrls<GetRentalHistoryQueryResult>>),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+#[get("/history/get_all")]
+async fn get_rental_history(
+    page: web::Query<PageParams>,
+    auth: Auth<ManageRentals>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::get_rental_history(page, auth, data).await
+}
+
+#[utoipa::path(
+    tag = "history",
+    params(PageParams),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Page of the user's finished rentals", body = Page<WithPhotoUrls<GetUserRentalHistoryQueryResult>>),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+    )
+)]
+#[get("/history/get")]
+async fn get_my_rental_history(
+    page: web::Query<PageParams>,
+    auth: Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::get_my_rental_history(page, auth, data).await
+}
+
+#[utoipa::path(
+    tag = "history",
+    params(PageParams),
+    security(("bearer_auth" = ["manage_rentals"])),
+    responses(
+        (status = 200, description = "Page of the user's finished rentals", body = Page<WithPhotoUrls<GetUserRentalHistoryAdminQueryResult>>),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+#[get("/history/get/{id}")]
+async fn get_user_rental_history(
+    id: web::Path<i32>,
+    page: web::Query<PageParams>,
+    auth: Auth<ManageRentals>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::get_user_rental_history(id, page, auth, data).await
+}
+
+#[utoipa::path(
+    tag = "history",
+    security(("bearer_auth" = ["manage_rentals"])),
+    responses(
+        (status = 200, description = "Entry deleted"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+#[get("/history/delete/{id}")]
+async fn delete_rental_history(
+    id: web::Path<i32>,
+    auth: Auth<ManageRentals>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::delete_rental_history(id, auth, data).await
+}
+
+#[utoipa::path(
+    tag = "extensions",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Extension requested"),
+        (status = 400, description = "Invalid date", body = ErrorResponse),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Extension denied by the rental policy, or someone else's rental", body = ErrorResponse),
+        (status = 404, description = "Rental not found", body = ErrorResponse),
+        (status = 409, description = "Copy is booked on these dates", body = ErrorResponse),
+    )
+)]
+#[post("/extension/save/{id}")]
+async fn save_extension_request(
+    id: web::Path<i32>,
+    form: Form<ExtensionRequestFormData>,
+    auth: Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let extension_date = Date::parse_from_str(&form.extension_date, "%Y-%m-%d")
+        .map_err(|_| ApiError::InvalidDateFormat)?;
+    let form = crate::ExtensionRequestFormData { extension_date };
+    crate::save_extension_request(id, Json(form), auth, data).await
+}
+
+#[utoipa::path(
+    tag = "extensions",
+    security(("bearer_auth" = ["manage_rentals"])),
+    responses(
+        (status = 200, description = "Extension accepted"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+        (status = 404, description = "Rental or extension request not found", body = ErrorResponse),
+        (status = 409, description = "Copy is booked on these dates", body = ErrorResponse),
+    )
+)]
+#[get("/extension/accept/{id}")]
+async fn accept_extension_request(
+    id: web::Path<i32>,
+    auth: Auth<ManageRentals>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::accept_extension_request(id, auth, data).await
+}
+
+#[utoipa::path(
+    tag = "extensions",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Extension request removed"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+        (status = 404, description = "Rental not found", body = ErrorResponse),
+    )
+)]
+#[get("/extension/delete/{id}")]
+async fn delete_extension_request(
+    id: web::Path<i32>,
+    auth: Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::delete_extension_request(id, auth, data).await
+}
+
+#[utoipa::path(
+    tag = "favourites",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Favourite toggled"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+    )
+)]
+#[get("/favourite/{id}")]
+async fn change_favourite(
+    id: web::Path<i32>,
+    auth: Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    if data.db.is_favourite(auth.0.sub, *id).await? {
+        crate::remove_favourite(id, auth, data).await
+    } else {
+        crate::add_favourite(id, auth, data).await
+    }
+}
+
+#[utoipa::path(
+    tag = "waitlist",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Joined the waitlist"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 409, description = "Already in the waitlist or a copy is available", body = ErrorResponse),
+    )
+)]
+#[get("/waitlist/join/{id}")]
+async fn join_waitlist(
+    id: web::Path<i32>,
+    auth: Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::join_waitlist(id, auth, data).await
+}
+
+#[utoipa::path(
+    tag = "waitlist",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Left the waitlist"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+    )
+)]
+#[get("/waitlist/leave/{id}")]
+async fn leave_waitlist(
+    id: web::Path<i32>,
+    auth: Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::leave_waitlist(id, auth, data).await
+}
+
+#[utoipa::path(
+    tag = "waitlist",
+    params(PageParams),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Page of the waitlist entries of the user", body = Page<GetWaitlistQueryResult>),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+    )
+)]
+#[get("/waitlist/get")]
+async fn get_my_waitlist(
+    page: web::Query<PageParams>,
+    auth: Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::get_my_waitlist(page, auth, data).await
+}
+
+#[utoipa::path(
+    tag = "waitlist",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Waitlist entry of the user", body = GetWaitlistQueryResult),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 404, description = "Waitlist entry not found", body = ErrorResponse),
+    )
+)]
+#[get("/waitlist/get/{id}")]
+async fn get_waitlist_position(
+    id: web::Path<i32>,
+    auth: Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    crate::get_waitlist_position(id, auth, data).await
+}
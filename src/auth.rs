@@ -1,37 +1,75 @@
-use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::rand_core::{OsRng, RngCore};
 use argon2::password_hash::SaltString;
 use argon2::{password_hash, Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
 use chrono::{Duration, Utc};
+use entity::sea_orm_active_enums::Permission;
+use hmac::{Hmac, Mac};
 use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation};
-use lettre::transport::smtp::{authentication::Credentials, Error as SmtpError};
-
-use lettre::{Message, SmtpTransport, Transport};
 use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
 use std::env;
-use lettre::message::Mailbox;
 use uuid::Uuid;
 
-const CONFIRMATION_EMAIL_SUBJECT: &str = "Potwierdź swój email";
 const JWT_EXPIRY_TIME: i64 = 30;
+/// Number of hours an account confirmation link stays valid.
+pub(crate) const CONFIRMATION_EXPIRY_TIME: i64 = 24;
+/// Number of days a session lasts without being refreshed.
+pub(crate) const SESSION_EXPIRY_TIME: i64 = 1;
+/// Number of days a "remember me" session lasts without being refreshed.
+pub(crate) const REMEMBER_ME_SESSION_EXPIRY_TIME: i64 = 30;
+/// Number of minutes a password reset link stays valid.
+pub(crate) const PASSWORD_RESET_EXPIRY_TIME: i64 = 60;
+/// Number of minutes the user has to enter their 2FA code after giving the password.
+const TWO_FACTOR_CHALLENGE_EXPIRY_TIME: i64 = 5;
+const TWO_FACTOR_CHALLENGE_AUDIENCE: &str = "2fa";
+
+const TOTP_ISSUER: &str = "Wypozyczalnia gier planszowych";
+/// Length of a TOTP time step in seconds.
+const TOTP_STEP: i64 = 30;
+const TOTP_DIGITS: u32 = 6;
+/// Number of time steps before and after the current one in which codes are still accepted,
+/// to allow for clock drift.
+const TOTP_ALLOWED_DRIFT: i64 = 1;
+const TOTP_SECRET_LENGTH: usize = 20;
+const RECOVERY_CODES_COUNT: usize = 10;
+/// Number of random bytes in a recovery code, i.e. 80 bits, encoded as 16 base32 characters.
+const RECOVERY_CODE_BYTES: usize = 10;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub(crate) struct Claims {
     pub(crate) sub: i32,
-    pub(crate) is_admin: bool,
+    /// Token version of the user at the time of issuing, see `user.token_version`.
+    pub(crate) ver: i32,
+    /// ID of the session the token was issued for.
+    pub(crate) sid: i32,
     iat: usize,
     exp: usize,
+    /// Permissions of the user's role. They aren't part of the token,
+    /// but are loaded from the database on every request instead.
+    #[serde(skip)]
+    pub(crate) permissions: Vec<Permission>,
+}
+
+impl Claims {
+    pub(crate) fn has_permission(&self, permission: Permission) -> bool {
+        self.permissions.contains(&permission)
+    }
 }
 
 /// Generates a JWT token for the given user.
 pub(crate) fn generate_jwt(
     user_id: i32,
-    is_admin: bool,
+    token_version: i32,
+    session_id: i32,
 ) -> Result<String, jsonwebtoken::errors::Error> {
     let claims = Claims {
         sub: user_id,
-        is_admin,
+        ver: token_version,
+        sid: session_id,
         iat: Utc::now().timestamp() as usize,
         exp: (Utc::now() + Duration::minutes(JWT_EXPIRY_TIME)).timestamp() as usize,
+        permissions: Vec::new(),
     };
     let key = env::var("JWT_SECRET").unwrap();
 
@@ -73,49 +111,268 @@ pub(crate) fn verify_password(
         .is_ok())
 }
 
-/// Sends a confirmation email to the given email address.
-pub(crate) fn send_confirmation_email(
+/// Claims of a token proving that the user has given the right password,
+/// to be exchanged for a session after giving the 2FA code.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct TwoFactorChallengeClaims {
+    pub(crate) sub: i32,
+    pub(crate) remember_me: bool,
+    aud: String,
+    exp: usize,
+}
+
+/// Generates a 2FA challenge token for the given user.
+pub(crate) fn generate_challenge_token(
     user_id: i32,
-    email: String,
-    token: Uuid,
-) -> Result<(), SmtpError> {
-    let credentials = Credentials::new(
-        env::var("MAILER_USERNAME").unwrap(),
-        env::var("MAILER_PASSWORD").unwrap(),
-    );
-
-    let smtp_server = env::var("MAILER_HOST").unwrap();
-    let smtp_port = env::var("MAILER_PORT").unwrap().parse::<u16>().unwrap();
-
-    let mailer = SmtpTransport::starttls_relay(&smtp_server)?
-        .port(smtp_port)
-        .credentials(credentials)
-        .build();
-
-    let from: Mailbox = match env::var("MAILER_USERNAME").unwrap().parse() {
-        Ok(mailbox) => mailbox,
-        Err(e) => panic!("Invalid email address: {}", e),
-    };
-    let to: Mailbox = match email.parse() {
-        Ok(mailbox) => mailbox,
-        Err(e) => panic!("Invalid email address: {}", e),
+    remember_me: bool,
+) -> Result<String, jsonwebtoken::errors::Error> {
+    let claims = TwoFactorChallengeClaims {
+        sub: user_id,
+        remember_me,
+        aud: TWO_FACTOR_CHALLENGE_AUDIENCE.into(),
+        exp: (Utc::now() + Duration::minutes(TWO_FACTOR_CHALLENGE_EXPIRY_TIME)).timestamp()
+            as usize,
     };
-    let confirmation_link = format!(
-        "{}/api/user/confirm/{}/{}",
-        env::var("FRONTEND_URL").unwrap(),
-        user_id,
-        token
-    );
-
-    let email = Message::builder()
-        .from(from)
-        .to(to)
-        .subject(CONFIRMATION_EMAIL_SUBJECT)
-        .body(confirmation_link)
-        .unwrap();
-
-    match mailer.send(&email) {
-        Ok(_) => Ok(()),
-        Err(e) => Err(e),
+    let key = env::var("JWT_SECRET").unwrap();
+
+    jsonwebtoken::encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(key.as_ref()),
+    )
+}
+
+/// Verifies a 2FA challenge token and returns the claims if the token is valid.
+/// Because of the audience, challenge tokens and access tokens can't be used in place of each other.
+pub(crate) fn verify_challenge_token(
+    token: &str,
+) -> Result<TwoFactorChallengeClaims, jsonwebtoken::errors::Error> {
+    let key = env::var("JWT_SECRET").unwrap();
+    let mut validation = Validation::default();
+    validation.set_audience(&[TWO_FACTOR_CHALLENGE_AUDIENCE]);
+    jsonwebtoken::decode::<TwoFactorChallengeClaims>(
+        token,
+        &DecodingKey::from_secret(key.as_ref()),
+        &validation,
+    )
+    .map(|data| data.claims)
+}
+
+/// Generates a random TOTP secret, encoded in base32 as expected by authenticator apps.
+pub(crate) fn generate_totp_secret() -> String {
+    let mut secret = [0u8; TOTP_SECRET_LENGTH];
+    OsRng.fill_bytes(&mut secret);
+    base32_encode(&secret)
+}
+
+/// Builds an `otpauth://` URI of the secret, to be shown as a QR code.
+pub(crate) fn totp_uri(secret: &str, user_id: i32) -> String {
+    format!(
+        "otpauth://totp/{issuer}:{user_id}?secret={secret}&issuer={issuer}&digits={TOTP_DIGITS}&period={TOTP_STEP}",
+        issuer = TOTP_ISSUER.replace(' ', "%20"),
+    )
+}
+
+/// Verifies a TOTP code (RFC 6238) at the given Unix time and returns
+/// the time step it was generated for, so that it can't be used again.
+pub(crate) fn verify_totp(secret: &str, code: &str, unix_time: i64) -> Option<i64> {
+    let key = base32_decode(secret)?;
+    let code: u32 = code.trim().parse().ok()?;
+    let current_step = unix_time / TOTP_STEP;
+
+    (current_step - TOTP_ALLOWED_DRIFT..=current_step + TOTP_ALLOWED_DRIFT)
+        .find(|&step| step >= 0 && hotp(&key, step as u64) == code)
+}
+
+/// Generates a set of single-use recovery codes, to be used when the authenticator is lost.
+pub(crate) fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODES_COUNT)
+        .map(|_| {
+            let mut code = [0u8; RECOVERY_CODE_BYTES];
+            OsRng.fill_bytes(&mut code);
+            base32_encode(&code)
+        })
+        .collect()
+}
+
+/// Calculates an HOTP value (RFC 4226) for the given counter.
+fn hotp(key: &[u8], counter: u64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(&counter.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    // Dynamic truncation.
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let value = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+    value % 10u32.pow(TOTP_DIGITS)
+}
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Encodes bytes in base32 (RFC 4648) without padding.
+fn base32_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::new();
+    let (mut buffer, mut bits) = (0u32, 0);
+    for &byte in bytes {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        encoded.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    encoded
+}
+
+/// Decodes base32 (RFC 4648), ignoring padding and case.
+fn base32_decode(encoded: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let (mut buffer, mut bits) = (0u32, 0);
+    for c in encoded.trim_end_matches('=').chars() {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|&a| a as char == c.to_ascii_uppercase())?;
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+/// Generates a random token to be sent to the user, e.g. in a password reset link.
+pub(crate) fn generate_token() -> String {
+    Uuid::new_v4().simple().to_string()
+}
+
+/// Hashes a token sent to the user, so that only the hash has to be stored.
+/// Unlike passwords, tokens are random enough not to need a salt.
+pub(crate) fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// Builds a refresh token from the ID of its session and a secret generated with [`generate_token`].
+pub(crate) fn build_refresh_token(session_id: i32, secret: &str) -> String {
+    format!("{}.{}", session_id, secret)
+}
+
+/// Splits a refresh token into the ID of its session and its secret.
+pub(crate) fn parse_refresh_token(token: &str) -> Option<(i32, &str)> {
+    let (session_id, secret) = token.split_once('.')?;
+    Some((session_id.parse().ok()?, secret))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Secret used in the test vectors of RFC 4226 and RFC 6238.
+    const RFC_SECRET: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn hotp_matches_rfc_4226_test_vectors() {
+        // RFC 4226, Appendix D.
+        let expected = [
+            755224, 287082, 359152, 969429, 338314, 254676, 287922, 162583, 399871, 520489,
+        ];
+        for (counter, &value) in expected.iter().enumerate() {
+            assert_eq!(
+                hotp(RFC_SECRET, counter as u64),
+                value,
+                "counter {}",
+                counter
+            );
+        }
+    }
+
+    #[test]
+    fn verify_totp_matches_rfc_6238_test_vectors() {
+        // RFC 6238, Appendix B (SHA-1), truncated to the last 6 digits.
+        let secret = base32_encode(RFC_SECRET);
+        let vectors = [
+            (59, "287082"),
+            (1111111109, "081804"),
+            (1111111111, "050471"),
+            (1234567890, "005924"),
+            (2000000000, "279037"),
+            (20000000000, "353130"),
+        ];
+        for (unix_time, code) in vectors {
+            assert_eq!(
+                verify_totp(&secret, code, unix_time),
+                Some(unix_time / TOTP_STEP),
+                "time {}",
+                unix_time
+            );
+        }
+    }
+
+    #[test]
+    fn verify_totp_allows_only_limited_drift() {
+        let secret = base32_encode(RFC_SECRET);
+        let step = 1234567890 / TOTP_STEP;
+        let code = format!("{:06}", hotp(RFC_SECRET, step as u64));
+
+        for drift in -TOTP_ALLOWED_DRIFT..=TOTP_ALLOWED_DRIFT {
+            let unix_time = (step + drift) * TOTP_STEP;
+            assert_eq!(verify_totp(&secret, &code, unix_time), Some(step));
+        }
+        let too_late = (step + TOTP_ALLOWED_DRIFT + 1) * TOTP_STEP;
+        assert_eq!(verify_totp(&secret, &code, too_late), None);
+        assert_eq!(verify_totp(&secret, "abcdef", step * TOTP_STEP), None);
+        assert_eq!(verify_totp("not base32!", &code, step * TOTP_STEP), None);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn base32_matches_rfc_4648_test_vectors() {
+        let vectors = [
+            ("", ""),
+            ("f", "MY"),
+            ("fo", "MZXQ"),
+            ("foo", "MZXW6"),
+            ("foob", "MZXW6YQ"),
+            ("fooba", "MZXW6YTB"),
+            ("foobar", "MZXW6YTBOI"),
+        ];
+        for (decoded, encoded) in vectors {
+            assert_eq!(base32_encode(decoded.as_bytes()), encoded);
+            assert_eq!(base32_decode(encoded).unwrap(), decoded.as_bytes());
+        }
+        // Padding and lowercase letters are accepted, as some apps produce them.
+        assert_eq!(base32_decode("mzxw6ytboi======").unwrap(), b"foobar");
+        assert_eq!(base32_decode("MZXW6YTB01"), None);
+    }
+
+    #[test]
+    fn base32_round_trips_totp_secrets() {
+        for _ in 0..10 {
+            let secret = generate_totp_secret();
+            let key = base32_decode(&secret).unwrap();
+            assert_eq!(key.len(), TOTP_SECRET_LENGTH);
+            assert_eq!(base32_encode(&key), secret);
+        }
+    }
+
+    #[test]
+    fn recovery_codes_are_random_enough() {
+        let codes = generate_recovery_codes();
+        assert_eq!(codes.len(), RECOVERY_CODES_COUNT);
+        for code in &codes {
+            assert_eq!(base32_decode(code).unwrap().len(), RECOVERY_CODE_BYTES);
+        }
+        let mut unique = codes.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), codes.len());
+    }
+}
@@ -0,0 +1,77 @@
+use std::env;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+const DEFAULT_MAX_ACTIVE_RENTALS: u64 = 3;
+const DEFAULT_MAX_RENTAL_DAYS: i64 = 14;
+const DEFAULT_JOBS_INTERVAL_HOURS: u64 = 24;
+const DEFAULT_UNCONFIRMED_ACCOUNT_DAYS: i64 = 7;
+const DEFAULT_MAX_FAILED_LOGINS: u32 = 5;
+const DEFAULT_LOCKOUT_MINUTES: i64 = 15;
+
+/// Optional settings read from the environment, falling back to defaults when not set.
+#[derive(Debug, Clone)]
+pub(crate) struct Config {
+    /// Maximum number of rentals (including future bookings) a user can have at once.
+    pub(crate) max_active_rentals: u64,
+    /// Maximum number of days a single rental can last, extensions included.
+    pub(crate) max_rental_days: i64,
+    /// Number of hours between consecutive runs of the background jobs.
+    pub(crate) jobs_interval_hours: u64,
+    /// Number of days after the last confirmation email after which unconfirmed accounts are deleted.
+    pub(crate) unconfirmed_account_days: i64,
+    /// Whether staff (users whose role has any permissions) have to turn on
+    /// two-factor authentication to use them.
+    pub(crate) require_admin_2fa: bool,
+    /// Number of failed logins in a row after which an account is temporarily locked.
+    pub(crate) max_failed_logins: u32,
+    /// Number of minutes an account stays locked after too many failed logins.
+    pub(crate) lockout_minutes: i64,
+    /// Addresses of the reverse proxies whose `Forwarded` and `X-Forwarded-For` headers are trusted.
+    /// When empty, clients are told apart only by the address they connect from.
+    pub(crate) trusted_proxies: Vec<IpAddr>,
+}
+
+impl Config {
+    /// Reads the settings from the environment.
+    /// Panics if a variable is set but cannot be parsed.
+    pub(crate) fn from_env() -> Self {
+        Self {
+            max_active_rentals: parse_var("MAX_ACTIVE_RENTALS", DEFAULT_MAX_ACTIVE_RENTALS),
+            max_rental_days: parse_var("MAX_RENTAL_DAYS", DEFAULT_MAX_RENTAL_DAYS),
+            jobs_interval_hours: parse_var("JOBS_INTERVAL_HOURS", DEFAULT_JOBS_INTERVAL_HOURS),
+            unconfirmed_account_days: parse_var(
+                "UNCONFIRMED_ACCOUNT_DAYS",
+                DEFAULT_UNCONFIRMED_ACCOUNT_DAYS,
+            ),
+            require_admin_2fa: parse_var("REQUIRE_ADMIN_2FA", false),
+            max_failed_logins: parse_var("MAX_FAILED_LOGINS", DEFAULT_MAX_FAILED_LOGINS),
+            lockout_minutes: parse_var("LOCKOUT_MINUTES", DEFAULT_LOCKOUT_MINUTES),
+            trusted_proxies: parse_list_var("TRUSTED_PROXIES"),
+        }
+    }
+}
+
+fn parse_var<T: FromStr>(name: &str, default: T) -> T {
+    match env::var(name) {
+        Ok(value) => match value.parse() {
+            Ok(value) => value,
+            Err(_) => panic!("{} has an invalid value", name),
+        },
+        Err(_) => default,
+    }
+}
+
+/// Parses a comma-separated list, which is empty when the variable is not set.
+fn parse_list_var<T: FromStr>(name: &str) -> Vec<T> {
+    env::var(name)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(|value| match value.parse() {
+            Ok(value) => value,
+            Err(_) => panic!("{} has an invalid value", name),
+        })
+        .collect()
+}
@@ -1,21 +1,47 @@
+use chrono::{Duration, Local, TimeZone, Utc};
 use entity::board_game::{ActiveModel as BoardGameActiveModel, Model as BoardGameModel};
+use entity::board_game_category::ActiveModel as BoardGameCategoryActiveModel;
+use entity::board_game_tag::ActiveModel as BoardGameTagActiveModel;
+use entity::category::{ActiveModel as CategoryActiveModel, Model as CategoryModel};
 use entity::favourite::ActiveModel as FavouriteActiveModel;
-use entity::prelude::{BoardGame, Favourite, Rental, RentalHistory, User};
+use entity::game_copy::{ActiveModel as GameCopyActiveModel, Model as GameCopyModel};
+use entity::password_reset_token::ActiveModel as PasswordResetTokenActiveModel;
+use entity::penalty_event::{ActiveModel as PenaltyEventActiveModel, Model as PenaltyEventModel};
+use entity::prelude::{
+    BoardGame, BoardGameCategory, BoardGameTag, Category, Favourite, GameCopy, PasswordResetToken,
+    PenaltyEvent, RecoveryCode, Rental, RentalHistory, Role, RolePermission, Session, Tag, User,
+    Waitlist,
+};
+use entity::recovery_code::ActiveModel as RecoveryCodeActiveModel;
 use entity::rental::{ActiveModel as RentalActiveModel, Model as RentalModel};
 use entity::rental_history::ActiveModel as RentalHistoryActiveModel;
+use entity::role::Model as RoleModel;
+use entity::sea_orm_active_enums::Permission;
+use entity::session::{ActiveModel as SessionActiveModel, Model as SessionModel};
+use entity::tag::{ActiveModel as TagActiveModel, Model as TagModel};
 use entity::user::{ActiveModel as UserActiveModel, Model as UserModel};
-use entity::{board_game, favourite, rental, rental_history, user};
-use migration::{Expr, JoinType, Migrator, MigratorTrait};
-use sea_orm::prelude::Date;
+use entity::waitlist::{ActiveModel as WaitlistActiveModel, Model as WaitlistModel};
+use entity::{
+    board_game, board_game_category, board_game_tag, category, favourite, game_copy,
+    password_reset_token, penalty_event, recovery_code, rental, rental_history, role,
+    role_permission, session, tag, user, waitlist,
+};
+use migration::{Expr, Func, JoinType, LikeExpr, Migrator, MigratorTrait, Order};
+use sea_orm::prelude::{Date, DateTime};
 use sea_orm::sea_query::IntoCondition;
 use sea_orm::{
-    ActiveModelTrait, ActiveValue, ColumnTrait, Database, DatabaseConnection, DbErr, EntityTrait,
-    FromQueryResult, Iterable, QueryFilter, QueryOrder, QuerySelect, RelationTrait,
-    TransactionTrait,
+    ActiveModelTrait, ActiveValue, ColumnTrait, Condition, ConnectionTrait, Database,
+    DatabaseConnection, DatabaseTransaction, DbErr, EntityTrait, FromQueryResult,
+    ItemsAndPagesNumber, Iterable, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect,
+    QueryTrait, RelationTrait, SelectorTrait, TransactionTrait,
 };
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 
-const PENALTY_THRESHOLD: u8 = 2;
+pub(crate) const PENALTY_THRESHOLD: i64 = 2;
+const WAITLIST_HOLD_HOURS: i64 = 48;
+const DEFAULT_PAGE_SIZE: u64 = 20;
+const MAX_PAGE_SIZE: u64 = 100;
 
 #[derive(Debug, Clone)]
 pub struct DatabaseManager {
@@ -31,10 +57,9 @@ impl DatabaseManager {
         Ok(Self { db })
     }
 
-    /// Inserts a new user into the database.
-    pub(crate) async fn insert_user(&self, user: UserActiveModel) -> Result<(), DbErr> {
-        user.insert(&self.db).await?;
-        Ok(())
+    /// Inserts a new user into the database and returns it.
+    pub(crate) async fn insert_user(&self, user: UserActiveModel) -> Result<UserModel, DbErr> {
+        user.insert(&self.db).await
     }
 
     /// Retrieves a user of the given ID from the database.
@@ -43,20 +68,409 @@ impl DatabaseManager {
         Ok(user)
     }
 
-    /// Retrieves all users from the database.
-    pub(crate) async fn get_users(&self) -> Result<Vec<UserModel>, DbErr> {
-        let users = User::find()
+    /// Retrieves a user of the given email from the database.
+    pub(crate) async fn get_user_by_email(&self, email: &str) -> Result<Option<UserModel>, DbErr> {
+        let user = User::find()
+            .filter(user::Column::Email.eq(email))
+            .one(&self.db)
+            .await?;
+        Ok(user)
+    }
+
+    /// Checks whether any user has the given email, ignoring letter case.
+    pub(crate) async fn email_exists(&self, email: &str) -> Result<bool, DbErr> {
+        let count = User::find()
+            .filter(
+                Expr::expr(Func::lower(Expr::col(user::Column::Email))).eq(email.to_lowercase()),
+            )
+            .count(&self.db)
+            .await?;
+        Ok(count > 0)
+    }
+
+    /// Deletes users who haven't confirmed their email, if the last confirmation email
+    /// was sent before the given time. Returns the number of deleted users.
+    pub(crate) async fn delete_unconfirmed_users(&self, before: DateTime) -> Result<u64, DbErr> {
+        let result = User::delete_many()
+            .filter(user::Column::ConfirmationToken.is_not_null())
+            .filter(user::Column::ConfirmationSentAt.lt(before))
+            .exec(&self.db)
+            .await?;
+        Ok(result.rows_affected)
+    }
+
+    /// Saves a new password reset token, replacing the unused tokens of the same user.
+    pub(crate) async fn save_password_reset_token(
+        &self,
+        token: PasswordResetTokenActiveModel,
+    ) -> Result<(), DbErr> {
+        let txn = self.db.begin().await?;
+        PasswordResetToken::delete_many()
+            .filter(password_reset_token::Column::UserId.eq(token.user_id.clone().unwrap()))
+            .filter(password_reset_token::Column::UsedAt.is_null())
+            .exec(&txn)
+            .await?;
+        token.insert(&txn).await?;
+        txn.commit().await?;
+        Ok(())
+    }
+
+    /// Sets a new password for the owner of the password reset token with the given hash,
+    /// provided that the token hasn't expired or been used yet, and marks the token as used.
+    /// The user's token version is bumped and their sessions are revoked,
+    /// so that all their existing tokens become invalid.
+    /// Returns whether the token was valid.
+    pub(crate) async fn reset_password(
+        &self,
+        token_hash: &str,
+        password_hash: String,
+        now: DateTime,
+    ) -> Result<bool, DbErr> {
+        let token = PasswordResetToken::find()
+            .filter(password_reset_token::Column::TokenHash.eq(token_hash))
+            .filter(password_reset_token::Column::UsedAt.is_null())
+            .filter(password_reset_token::Column::ExpiresAt.gt(now))
+            .one(&self.db)
+            .await?;
+        let token = match token {
+            Some(token) => token,
+            None => return Ok(false),
+        };
+
+        let txn = self.db.begin().await?;
+        // The token is claimed conditionally, so it can't be used twice by concurrent requests.
+        let claimed = PasswordResetToken::update_many()
+            .col_expr(password_reset_token::Column::UsedAt, Expr::value(now))
+            .filter(password_reset_token::Column::Id.eq(token.id))
+            .filter(password_reset_token::Column::UsedAt.is_null())
+            .exec(&txn)
+            .await?;
+        if claimed.rows_affected == 0 {
+            return Ok(false);
+        }
+        User::update_many()
+            .col_expr(user::Column::PasswordHash, Expr::value(password_hash))
+            .col_expr(
+                user::Column::TokenVersion,
+                Expr::col(user::Column::TokenVersion).add(1),
+            )
+            .filter(user::Column::Id.eq(token.user_id))
+            .exec(&txn)
+            .await?;
+        revoke_user_sessions(&txn, token.user_id, now).await?;
+        txn.commit().await?;
+        Ok(true)
+    }
+
+    /// Inserts a new session into the database and returns it.
+    pub(crate) async fn insert_session(
+        &self,
+        session: SessionActiveModel,
+    ) -> Result<SessionModel, DbErr> {
+        session.insert(&self.db).await
+    }
+
+    /// Retrieves a session of the given ID from the database.
+    pub(crate) async fn get_session(&self, id: i32) -> Result<Option<SessionModel>, DbErr> {
+        let session = Session::find_by_id(id).one(&self.db).await?;
+        Ok(session)
+    }
+
+    /// Retrieves a session of the given ID from the database, along with its user.
+    pub(crate) async fn get_session_user(
+        &self,
+        id: i32,
+    ) -> Result<Option<(SessionModel, UserModel)>, DbErr> {
+        let session = Session::find_by_id(id)
+            .find_also_related(User)
+            .one(&self.db)
+            .await?;
+        Ok(session.and_then(|(session, user)| Some((session, user?))))
+    }
+
+    /// Replaces the refresh token of a session and extends it until the given time.
+    /// The token is replaced only if the session still has the expected one and hasn't been
+    /// revoked, so a token can't be rotated twice by concurrent requests.
+    /// Returns whether the token has been replaced.
+    pub(crate) async fn rotate_session(
+        &self,
+        id: i32,
+        old_token_hash: &str,
+        new_token_hash: String,
+        now: DateTime,
+        expires_at: DateTime,
+    ) -> Result<bool, DbErr> {
+        let result = Session::update_many()
+            .col_expr(session::Column::TokenHash, Expr::value(new_token_hash))
+            .col_expr(session::Column::LastUsedAt, Expr::value(now))
+            .col_expr(session::Column::ExpiresAt, Expr::value(expires_at))
+            .filter(session::Column::Id.eq(id))
+            .filter(session::Column::TokenHash.eq(old_token_hash))
+            .filter(session::Column::RevokedAt.is_null())
+            .exec(&self.db)
+            .await?;
+        Ok(result.rows_affected > 0)
+    }
+
+    /// Revokes a session of the given ID, so that its refresh token can't be used anymore.
+    pub(crate) async fn revoke_session(&self, id: i32, now: DateTime) -> Result<(), DbErr> {
+        Session::update_many()
+            .col_expr(session::Column::RevokedAt, Expr::value(now))
+            .filter(session::Column::Id.eq(id))
+            .filter(session::Column::RevokedAt.is_null())
+            .exec(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    /// Makes all tokens of the given user invalid, by bumping their token version
+    /// and revoking their sessions.
+    pub(crate) async fn revoke_user_tokens(
+        &self,
+        user_id: i32,
+        now: DateTime,
+    ) -> Result<(), DbErr> {
+        let txn = self.db.begin().await?;
+        revoke_user_tokens(&txn, user_id, now).await?;
+        txn.commit().await?;
+        Ok(())
+    }
+
+    /// Deletes sessions that expired before the given time.
+    /// Revoked sessions are kept until they expire, so that reuse of their tokens is still detected.
+    pub(crate) async fn delete_expired_sessions(&self, before: DateTime) -> Result<u64, DbErr> {
+        let result = Session::delete_many()
+            .filter(session::Column::ExpiresAt.lt(before))
+            .exec(&self.db)
+            .await?;
+        Ok(result.rows_affected)
+    }
+
+    /// Turns on two-factor authentication for the given user, whose TOTP secret is already saved.
+    /// The time step of the code used to confirm it can't be used again.
+    pub(crate) async fn enable_two_factor(
+        &self,
+        user_id: i32,
+        totp_step: i64,
+        recovery_code_hashes: Vec<String>,
+    ) -> Result<(), DbErr> {
+        let txn = self.db.begin().await?;
+        let user = UserActiveModel {
+            id: ActiveValue::Set(user_id),
+            totp_enabled: ActiveValue::Set(true),
+            totp_last_step: ActiveValue::Set(Some(totp_step)),
+            ..Default::default()
+        };
+        user.update(&txn).await?;
+        replace_recovery_codes(&txn, user_id, recovery_code_hashes).await?;
+        txn.commit().await?;
+        Ok(())
+    }
+
+    /// Turns off two-factor authentication for the given user, removing their secret
+    /// and recovery codes.
+    pub(crate) async fn disable_two_factor(&self, user_id: i32) -> Result<(), DbErr> {
+        let txn = self.db.begin().await?;
+        let user = UserActiveModel {
+            id: ActiveValue::Set(user_id),
+            totp_secret: ActiveValue::Set(None),
+            totp_enabled: ActiveValue::Set(false),
+            totp_last_step: ActiveValue::Set(None),
+            ..Default::default()
+        };
+        user.update(&txn).await?;
+        replace_recovery_codes(&txn, user_id, Vec::new()).await?;
+        txn.commit().await?;
+        Ok(())
+    }
+
+    /// Replaces all recovery codes of the given user with new ones.
+    pub(crate) async fn save_recovery_codes(
+        &self,
+        user_id: i32,
+        recovery_code_hashes: Vec<String>,
+    ) -> Result<(), DbErr> {
+        let txn = self.db.begin().await?;
+        replace_recovery_codes(&txn, user_id, recovery_code_hashes).await?;
+        txn.commit().await?;
+        Ok(())
+    }
+
+    /// Records that a TOTP code of the given time step has been used by the user.
+    /// Returns false if a code of this or a later step has already been used,
+    /// so that every code is accepted only once.
+    pub(crate) async fn claim_totp_step(&self, user_id: i32, step: i64) -> Result<bool, DbErr> {
+        let result = User::update_many()
+            .col_expr(user::Column::TotpLastStep, Expr::value(step))
+            .filter(user::Column::Id.eq(user_id))
+            .filter(
+                Condition::any()
+                    .add(user::Column::TotpLastStep.is_null())
+                    .add(user::Column::TotpLastStep.lt(step)),
+            )
+            .exec(&self.db)
+            .await?;
+        Ok(result.rows_affected > 0)
+    }
+
+    /// Marks a recovery code of the given user as used.
+    /// Returns false if there is no such unused code.
+    pub(crate) async fn use_recovery_code(
+        &self,
+        user_id: i32,
+        code_hash: &str,
+        now: DateTime,
+    ) -> Result<bool, DbErr> {
+        let result = RecoveryCode::update_many()
+            .col_expr(recovery_code::Column::UsedAt, Expr::value(now))
+            .filter(recovery_code::Column::UserId.eq(user_id))
+            .filter(recovery_code::Column::CodeHash.eq(code_hash))
+            .filter(recovery_code::Column::UsedAt.is_null())
+            .exec(&self.db)
+            .await?;
+        Ok(result.rows_affected > 0)
+    }
+
+    /// Retrieves a page of users from the database, along with their current penalty points.
+    pub(crate) async fn get_users(
+        &self,
+        page: PageParams,
+    ) -> Result<Page<GetUsersQueryResult>, DbErr> {
+        let now = Utc::now().naive_utc();
+        let query = User::find()
+            .select_only()
+            .columns([
+                user::Column::Id,
+                user::Column::Name,
+                user::Column::Surname,
+                user::Column::Email,
+            ])
+            .column_as(role::Column::Name, "role")
+            .expr_as(
+                Func::greatest([
+                    Func::coalesce([
+                        Func::sum(Expr::col((
+                            penalty_event::Entity,
+                            penalty_event::Column::Points,
+                        )))
+                        .into(),
+                        Expr::val(0).into(),
+                    ])
+                    .into(),
+                    Expr::val(0).into(),
+                ]),
+                "penalty_points",
+            )
+            .join(
+                JoinType::LeftJoin,
+                user::Relation::PenaltyEvent
+                    .def()
+                    .on_condition(move |_left, right| {
+                        Condition::any()
+                            .add(
+                                Expr::col((right.clone(), penalty_event::Column::ExpiresAt))
+                                    .is_null(),
+                            )
+                            .add(Expr::col((right, penalty_event::Column::ExpiresAt)).gt(now))
+                    }),
+            )
+            .join(JoinType::InnerJoin, user::Relation::Role.def())
+            .group_by(user::Column::Id)
             .order_by_asc(user::Column::Surname)
+            .order_by_asc(user::Column::Id)
+            .into_model::<GetUsersQueryResult>();
+        fetch_page(&self.db, query, page).await
+    }
+
+    /// Retrieves a role of the given ID from the database.
+    pub(crate) async fn get_role(&self, id: i32) -> Result<Option<RoleModel>, DbErr> {
+        let role = Role::find_by_id(id).one(&self.db).await?;
+        Ok(role)
+    }
+
+    /// Retrieves all roles along with their permissions from the database.
+    pub(crate) async fn get_roles(&self) -> Result<Vec<GetRolesQueryResult>, DbErr> {
+        let roles = Role::find()
+            .find_with_related(RolePermission)
+            .order_by_asc(role::Column::Id)
+            .all(&self.db)
+            .await?;
+        Ok(roles
+            .into_iter()
+            .map(|(role, permissions)| GetRolesQueryResult {
+                id: role.id,
+                name: role.name,
+                permissions: permissions.into_iter().map(|p| p.permission).collect(),
+            })
+            .collect())
+    }
+
+    /// Retrieves the permissions of the given role from the database.
+    pub(crate) async fn get_role_permissions(
+        &self,
+        role_id: i32,
+    ) -> Result<Vec<Permission>, DbErr> {
+        let permissions = RolePermission::find()
+            .filter(role_permission::Column::RoleId.eq(role_id))
             .all(&self.db)
             .await?;
-        Ok(users)
+        Ok(permissions.into_iter().map(|p| p.permission).collect())
+    }
+
+    /// Sums up the penalty points of a user from the events that haven't expired yet.
+    /// Manual corrections can be negative, but the total never drops below zero.
+    pub(crate) async fn get_penalty_points(&self, user_id: i32) -> Result<i64, DbErr> {
+        let points = PenaltyEvent::find()
+            .select_only()
+            .column_as(penalty_event::Column::Points.sum(), "points")
+            .filter(penalty_event::Column::UserId.eq(user_id))
+            .filter(active_penalty_events())
+            .into_tuple::<Option<i64>>()
+            .one(&self.db)
+            .await?;
+        Ok(points.flatten().unwrap_or(0).max(0))
     }
 
     /// Checks whether a user is penalized based on their penalty points.
     /// A user is penalized if their penalty points exceed `PENALTY_THRESHOLD` const.
     pub(crate) async fn is_user_penalized(&self, id: i32) -> Result<bool, DbErr> {
-        let user = User::find_by_id(id).one(&self.db).await?;
-        Ok(user.map_or(false, |u| u.penalty_points > PENALTY_THRESHOLD))
+        let points = self.get_penalty_points(id).await?;
+        Ok(points > PENALTY_THRESHOLD)
+    }
+
+    /// Retrieves the current penalty points of a user, along with a page of their penalty events,
+    /// newest first.
+    pub(crate) async fn get_user_penalties(
+        &self,
+        user_id: i32,
+        page: PageParams,
+    ) -> Result<GetPenaltiesQueryResult, DbErr> {
+        let points = self.get_penalty_points(user_id).await?;
+        let query = PenaltyEvent::find()
+            .filter(penalty_event::Column::UserId.eq(user_id))
+            .order_by_desc(penalty_event::Column::CreatedAt)
+            .order_by_desc(penalty_event::Column::Id);
+        let events = fetch_page(&self.db, query, page).await?;
+        Ok(GetPenaltiesQueryResult {
+            points,
+            is_penalized: points > PENALTY_THRESHOLD,
+            events,
+        })
+    }
+
+    /// Saves a penalty event into the ledger.
+    pub(crate) async fn insert_penalty_event(
+        &self,
+        penalty_event: PenaltyEventActiveModel,
+    ) -> Result<(), DbErr> {
+        penalty_event.insert(&self.db).await?;
+        Ok(())
+    }
+
+    /// Deletes a penalty event of the given ID from the ledger.
+    pub(crate) async fn delete_penalty_event(&self, id: i32) -> Result<(), DbErr> {
+        PenaltyEvent::delete_by_id(id).exec(&self.db).await?;
+        Ok(())
     }
 
     /// Updates existing user in the database.
@@ -65,6 +479,21 @@ impl DatabaseManager {
         Ok(())
     }
 
+    /// Updates existing user in the database, including their password.
+    /// All their existing tokens are made invalid in the same transaction,
+    /// so that none of them outlives the old password.
+    pub(crate) async fn update_user_password(
+        &self,
+        user: UserActiveModel,
+        now: DateTime,
+    ) -> Result<(), DbErr> {
+        let txn = self.db.begin().await?;
+        let user = user.update(&txn).await?;
+        revoke_user_tokens(&txn, user.id, now).await?;
+        txn.commit().await?;
+        Ok(())
+    }
+
     /// Deletes a user of the given ID from the database.
     pub(crate) async fn delete_user(&self, id: i32) -> Result<(), DbErr> {
         User::delete_by_id(id).exec(&self.db).await?;
@@ -72,11 +501,24 @@ impl DatabaseManager {
     }
 
     /// Saves a board game to the database. Handles both insertions and updates.
+    /// A newly inserted board game gets a single copy, so that it can be rented right away.
     pub(crate) async fn save_board_game(
         &self,
         board_game: BoardGameActiveModel,
     ) -> Result<(), DbErr> {
-        board_game.save(&self.db).await?;
+        let is_insert = board_game.id.is_not_set();
+        let txn = self.db.begin().await?;
+
+        let board_game = board_game.save(&txn).await?;
+        if is_insert {
+            let game_copy = GameCopyActiveModel {
+                game_id: ActiveValue::Set(board_game.id.unwrap()),
+                ..Default::default()
+            };
+            game_copy.insert(&txn).await?;
+        }
+
+        txn.commit().await?;
         Ok(())
     }
 
@@ -86,13 +528,73 @@ impl DatabaseManager {
         Ok(board_game)
     }
 
+    /// Checks whether any board game uses the photo of the given file name.
+    pub(crate) async fn is_photo_used(&self, file_name: &str) -> Result<bool, DbErr> {
+        let count = BoardGame::find()
+            .filter(board_game::Column::PhotoFilename.eq(file_name))
+            .count(&self.db)
+            .await?;
+        Ok(count > 0)
+    }
+
+    /// Retrieves the distinct file names of all board game photos from the database.
+    pub(crate) async fn get_photo_filenames(&self) -> Result<Vec<String>, DbErr> {
+        let file_names = BoardGame::find()
+            .select_only()
+            .column(board_game::Column::PhotoFilename)
+            .distinct()
+            .into_tuple()
+            .all(&self.db)
+            .await?;
+        Ok(file_names)
+    }
+
+    /// Retrieves the board game of the given copy ID from the database.
+    pub(crate) async fn get_copy_board_game(
+        &self,
+        copy_id: i32,
+    ) -> Result<Option<BoardGameModel>, DbErr> {
+        let board_game = GameCopy::find_by_id(copy_id)
+            .find_also_related(BoardGame)
+            .one(&self.db)
+            .await?;
+        Ok(board_game.and_then(|(_, board_game)| board_game))
+    }
+
     /// Retrieves all board games from the database, along with the information
-    /// about the rental status and whether they are in the user's favourites.
+    /// about the number of available copies, the earliest return date of the rented ones
+    /// and whether they are in the user's favourites. Copies held for other users
+    /// from the waitlist are not counted as available.
     pub(crate) async fn get_board_games(
         &self,
         user_id: i32,
-    ) -> Result<Vec<GetBoardGamesQueryResult>, DbErr> {
-        let board_games = BoardGame::find()
+        page: PageParams,
+    ) -> Result<Page<GetBoardGamesQueryResult>, DbErr> {
+        self.search_board_games(user_id, &BoardGameFilter::default(), page)
+            .await
+    }
+
+    /// Retrieves board games matching the given filter, with the same info as `get_board_games`.
+    /// All filtering and sorting is done by the database.
+    pub(crate) async fn search_board_games(
+        &self,
+        user_id: i32,
+        filter: &BoardGameFilter,
+        page: PageParams,
+    ) -> Result<Page<GetBoardGamesQueryResult>, DbErr> {
+        let today = today();
+        let now = Utc::now().naive_utc();
+        let total_copies =
+            Func::count_distinct(Expr::col((game_copy::Entity, game_copy::Column::Id)));
+        let available_copies =
+            Expr::expr(total_copies.clone()).sub(Func::count_distinct(Expr::case(
+                Expr::col((rental::Entity, rental::Column::CopyId))
+                    .is_not_null()
+                    .or(Expr::col((waitlist::Entity, waitlist::Column::Id)).is_not_null()),
+                Expr::col((game_copy::Entity, game_copy::Column::Id)),
+            )));
+
+        let mut query = BoardGame::find()
             .select_only()
             .columns(board_game::Column::iter().filter(|c| {
                 !matches!(
@@ -100,7 +602,12 @@ impl DatabaseManager {
                     board_game::Column::Weight | board_game::Column::AdditionalInfo
                 )
             }))
-            .column(rental::Column::ReturnDate)
+            .expr_as(total_copies, "total_copies")
+            .expr_as(available_copies.clone(), "available_copies")
+            .expr_as(
+                Func::min(Expr::col((rental::Entity, rental::Column::ReturnDate))),
+                "return_date",
+            )
             .expr_as(
                 Expr::case(
                     Expr::col((favourite::Entity, favourite::Column::UserId)).is_not_null(),
@@ -119,25 +626,128 @@ impl DatabaseManager {
                             .into_condition()
                     }),
             )
-            .left_join(Rental)
+            .left_join(GameCopy)
+            .join(
+                JoinType::LeftJoin,
+                game_copy::Relation::Rental
+                    .def()
+                    .on_condition(move |_left, right| {
+                        Expr::col((right, rental::Column::RentalDate))
+                            .lte(today)
+                            .into_condition()
+                    }),
+            )
+            .join(
+                JoinType::LeftJoin,
+                game_copy::Relation::Waitlist
+                    .def()
+                    .on_condition(move |_left, right| {
+                        Expr::col((right.clone(), waitlist::Column::HoldExpiresAt))
+                            .gt(now)
+                            .and(Expr::col((right, waitlist::Column::UserId)).ne(user_id))
+                            .into_condition()
+                    }),
+            )
+            .filter(filter.condition())
+            .group_by(board_game::Column::Id)
+            .group_by(Expr::col((favourite::Entity, favourite::Column::UserId)));
+
+        if filter.favourites_only.unwrap_or(false) {
+            query = query
+                .filter(Expr::col((favourite::Entity, favourite::Column::UserId)).is_not_null());
+        }
+        if filter.available_now.unwrap_or(false) {
+            query = query.having(Expr::expr(available_copies.clone()).gt(0));
+        }
+
+        query = match filter.sort.unwrap_or_default() {
+            BoardGameSort::Title => query,
+            BoardGameSort::TitleDesc => query.order_by_desc(board_game::Column::Title),
+            BoardGameSort::Weight => query.order_by_asc(board_game::Column::Weight),
+            BoardGameSort::WeightDesc => query.order_by_desc(board_game::Column::Weight),
+            BoardGameSort::Playtime => query.order_by_asc(board_game::Column::MinPlaytime),
+            BoardGameSort::PlaytimeDesc => query.order_by_desc(board_game::Column::MaxPlaytime),
+            BoardGameSort::Available => query.order_by(available_copies, Order::Desc),
+        };
+        // Games that are equal in the chosen order are always sorted by title.
+        let query = query
             .order_by_asc(board_game::Column::Title)
-            .into_model::<GetBoardGamesQueryResult>()
+            .order_by_asc(board_game::Column::Id)
+            .into_model::<GetBoardGamesQueryResult>();
+        let mut board_games = fetch_page(&self.db, query, page).await?;
+
+        let game_ids: Vec<i32> = board_games.items.iter().map(|b| b.id).collect();
+        let categories: Vec<(i32, String)> = BoardGameCategory::find()
+            .select_only()
+            .column(board_game_category::Column::GameId)
+            .column(category::Column::Name)
+            .inner_join(Category)
+            .filter(board_game_category::Column::GameId.is_in(game_ids.clone()))
+            .order_by_asc(category::Column::Name)
+            .into_tuple()
             .all(&self.db)
             .await?;
+        let tags: Vec<(i32, String)> = BoardGameTag::find()
+            .select_only()
+            .column(board_game_tag::Column::GameId)
+            .column(tag::Column::Name)
+            .inner_join(Tag)
+            .filter(board_game_tag::Column::GameId.is_in(game_ids))
+            .order_by_asc(tag::Column::Name)
+            .into_tuple()
+            .all(&self.db)
+            .await?;
+
+        let names_of = |labels: &[(i32, String)], game_id: i32| {
+            labels
+                .iter()
+                .filter(|(id, _)| *id == game_id)
+                .map(|(_, name)| name.clone())
+                .collect()
+        };
+        for board_game in &mut board_games.items {
+            board_game.categories = names_of(&categories, board_game.id);
+            board_game.tags = names_of(&tags, board_game.id);
+        }
         Ok(board_games)
     }
 
-    /// Retrieves all board games from the database, along with their rental status.
+    /// Retrieves a page of board games from the database, along with their copies and rentals.
     /// Should be used by admin users only, as it provides additional info about rental.
     pub(crate) async fn get_board_games_admin(
         &self,
-    ) -> Result<Vec<(BoardGameModel, Option<RentalModel>)>, DbErr> {
-        let board_games = BoardGame::find()
+        page: PageParams,
+    ) -> Result<Page<(BoardGameModel, Vec<GameCopyModel>, Vec<RentalModel>)>, DbErr> {
+        let query = BoardGame::find()
             .order_by_asc(board_game::Column::Title)
-            .find_also_related(Rental)
+            .order_by_asc(board_game::Column::Id);
+        let board_games = fetch_page(&self.db, query, page).await?;
+
+        let game_ids: Vec<i32> = board_games.items.iter().map(|b| b.id).collect();
+        let copies = GameCopy::find()
+            .filter(game_copy::Column::GameId.is_in(game_ids))
+            .order_by_asc(game_copy::Column::Id)
             .all(&self.db)
             .await?;
-        Ok(board_games)
+        let rentals = Rental::find()
+            .filter(rental::Column::CopyId.is_in(copies.iter().map(|c| c.id)))
+            .order_by_asc(rental::Column::RentalDate)
+            .all(&self.db)
+            .await?;
+
+        Ok(board_games.map(|board_game| {
+            let game_copies: Vec<GameCopyModel> = copies
+                .iter()
+                .filter(|c| c.game_id == board_game.id)
+                .cloned()
+                .collect();
+            let game_rentals = rentals
+                .iter()
+                .filter(|r| game_copies.iter().any(|c| c.id == r.copy_id))
+                .cloned()
+                .collect();
+            (board_game, game_copies, game_rentals)
+        }))
     }
 
     /// Deletes a board game of the given ID from the database.
@@ -146,6 +756,249 @@ impl DatabaseManager {
         Ok(())
     }
 
+    /// Saves a copy of a board game to the database. Handles both insertions and updates.
+    pub(crate) async fn save_game_copy(&self, game_copy: GameCopyActiveModel) -> Result<(), DbErr> {
+        game_copy.save(&self.db).await?;
+        Ok(())
+    }
+
+    /// Retrieves a page of the copies of the board game of the given ID from the database.
+    pub(crate) async fn get_game_copies(
+        &self,
+        game_id: i32,
+        page: PageParams,
+    ) -> Result<Page<GameCopyModel>, DbErr> {
+        let query = GameCopy::find()
+            .filter(game_copy::Column::GameId.eq(game_id))
+            .order_by_asc(game_copy::Column::Id);
+        fetch_page(&self.db, query, page).await
+    }
+
+    /// Retrieves a copy of the board game of the given ID that has no rentals in the given
+    /// period and isn't held from the waitlist for another user when the period starts.
+    pub(crate) async fn get_free_game_copy(
+        &self,
+        game_id: i32,
+        from: Date,
+        to: Date,
+        user_id: i32,
+    ) -> Result<Option<GameCopyModel>, DbErr> {
+        find_free_game_copy(&self.db, game_id, from, to, user_id).await
+    }
+
+    /// Checks whether a copy of the given ID has no rentals in the given period.
+    /// A rental of the given ID is not taken into account.
+    pub(crate) async fn is_game_copy_free(
+        &self,
+        copy_id: i32,
+        from: Date,
+        to: Date,
+        excluded_rental_id: Option<i32>,
+    ) -> Result<bool, DbErr> {
+        is_copy_free(&self.db, copy_id, from, to, excluded_rental_id).await
+    }
+
+    /// Rents a free copy of the board game of the given ID to the user in the given period,
+    /// see `get_free_game_copy`. The copy is looked up and booked in one transaction holding
+    /// the write lock, so concurrent bookings can't get the same copy.
+    /// Returns `None` if no copy is free.
+    pub(crate) async fn book_free_game_copy(
+        &self,
+        game_id: i32,
+        user_id: i32,
+        from: Date,
+        to: Date,
+    ) -> Result<Option<RentalModel>, DbErr> {
+        let txn = self.db.begin().await?;
+        lock_for_writing(&txn).await?;
+
+        let Some(game_copy) = find_free_game_copy(&txn, game_id, from, to, user_id).await? else {
+            return Ok(None);
+        };
+        let rental = RentalActiveModel {
+            copy_id: ActiveValue::Set(game_copy.id),
+            user_id: ActiveValue::Set(user_id),
+            rental_date: ActiveValue::Set(from),
+            return_date: ActiveValue::Set(to),
+            extension_date: ActiveValue::Set(None),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+
+        txn.commit().await?;
+        Ok(Some(rental))
+    }
+
+    /// Moves the given rental to another period, if its copy has no other rentals
+    /// then. The check and the update are done in one transaction holding the write lock,
+    /// see `book_free_game_copy`. Returns whether the rental was moved.
+    pub(crate) async fn reschedule_rental(
+        &self,
+        rental: &RentalModel,
+        from: Date,
+        to: Date,
+    ) -> Result<bool, DbErr> {
+        let txn = self.db.begin().await?;
+        lock_for_writing(&txn).await?;
+
+        if !is_copy_free(&txn, rental.copy_id, from, to, Some(rental.id)).await? {
+            return Ok(false);
+        }
+        RentalActiveModel {
+            id: ActiveValue::Set(rental.id),
+            rental_date: ActiveValue::Set(from),
+            return_date: ActiveValue::Set(to),
+            extension_date: ActiveValue::Set(None),
+            reminder_sent_at: ActiveValue::Set(None),
+            ..Default::default()
+        }
+        .update(&txn)
+        .await?;
+
+        txn.commit().await?;
+        Ok(true)
+    }
+
+    /// Retrieves the availability of the board game of the given ID in the given period,
+    /// as a list of consecutive intervals with the same number of free copies.
+    pub(crate) async fn get_board_game_availability(
+        &self,
+        game_id: i32,
+        from: Date,
+        to: Date,
+    ) -> Result<Vec<AvailabilityInterval>, DbErr> {
+        let copies = GameCopy::find()
+            .filter(game_copy::Column::GameId.eq(game_id))
+            .all(&self.db)
+            .await?;
+        let rentals = Rental::find()
+            .inner_join(GameCopy)
+            .filter(game_copy::Column::GameId.eq(game_id))
+            .filter(overlapping_rentals(from, to))
+            .all(&self.db)
+            .await?;
+
+        let today = today();
+        let mut intervals: Vec<AvailabilityInterval> = Vec::new();
+        for day in from.iter_days().take_while(|day| *day <= to) {
+            let busy_copies = copies
+                .iter()
+                .filter(|copy| {
+                    rentals.iter().any(|r| {
+                        r.copy_id == copy.id
+                            && r.rental_date <= day
+                            && (r.return_date >= day || day <= today)
+                    })
+                })
+                .count() as i64;
+            let free_copies = copies.len() as i64 - busy_copies;
+
+            match intervals.last_mut() {
+                Some(last) if last.free_copies == free_copies => last.to = day,
+                _ => intervals.push(AvailabilityInterval {
+                    from: day,
+                    to: day,
+                    free_copies,
+                    busy_copies,
+                }),
+            }
+        }
+        Ok(intervals)
+    }
+
+    /// Deletes a copy of a board game of the given ID from the database.
+    pub(crate) async fn delete_game_copy(&self, id: i32) -> Result<(), DbErr> {
+        GameCopy::delete_by_id(id).exec(&self.db).await?;
+        Ok(())
+    }
+
+    /// Retrieves a page of the board game categories from the database, ordered by name.
+    pub(crate) async fn get_categories(
+        &self,
+        page: PageParams,
+    ) -> Result<Page<CategoryModel>, DbErr> {
+        let query = Category::find().order_by_asc(category::Column::Name);
+        fetch_page(&self.db, query, page).await
+    }
+
+    /// Saves a board game category to the database. Handles both insertions and updates.
+    pub(crate) async fn save_category(&self, category: CategoryActiveModel) -> Result<(), DbErr> {
+        category.save(&self.db).await?;
+        Ok(())
+    }
+
+    /// Deletes a board game category of the given ID from the database.
+    pub(crate) async fn delete_category(&self, id: i32) -> Result<(), DbErr> {
+        Category::delete_by_id(id).exec(&self.db).await?;
+        Ok(())
+    }
+
+    /// Replaces the categories of the board game of the given ID.
+    pub(crate) async fn set_board_game_categories(
+        &self,
+        game_id: i32,
+        category_ids: Vec<i32>,
+    ) -> Result<(), DbErr> {
+        let txn = self.db.begin().await?;
+        BoardGameCategory::delete_many()
+            .filter(board_game_category::Column::GameId.eq(game_id))
+            .exec(&txn)
+            .await?;
+        if !category_ids.is_empty() {
+            let categories =
+                category_ids
+                    .into_iter()
+                    .map(|category_id| BoardGameCategoryActiveModel {
+                        game_id: ActiveValue::Set(game_id),
+                        category_id: ActiveValue::Set(category_id),
+                    });
+            BoardGameCategory::insert_many(categories)
+                .exec(&txn)
+                .await?;
+        }
+        txn.commit().await
+    }
+
+    /// Retrieves a page of the board game tags from the database, ordered by name.
+    pub(crate) async fn get_tags(&self, page: PageParams) -> Result<Page<TagModel>, DbErr> {
+        let query = Tag::find().order_by_asc(tag::Column::Name);
+        fetch_page(&self.db, query, page).await
+    }
+
+    /// Saves a board game tag to the database. Handles both insertions and updates.
+    pub(crate) async fn save_tag(&self, tag: TagActiveModel) -> Result<(), DbErr> {
+        tag.save(&self.db).await?;
+        Ok(())
+    }
+
+    /// Deletes a board game tag of the given ID from the database.
+    pub(crate) async fn delete_tag(&self, id: i32) -> Result<(), DbErr> {
+        Tag::delete_by_id(id).exec(&self.db).await?;
+        Ok(())
+    }
+
+    /// Replaces the tags of the board game of the given ID.
+    pub(crate) async fn set_board_game_tags(
+        &self,
+        game_id: i32,
+        tag_ids: Vec<i32>,
+    ) -> Result<(), DbErr> {
+        let txn = self.db.begin().await?;
+        BoardGameTag::delete_many()
+            .filter(board_game_tag::Column::GameId.eq(game_id))
+            .exec(&txn)
+            .await?;
+        if !tag_ids.is_empty() {
+            let tags = tag_ids.into_iter().map(|tag_id| BoardGameTagActiveModel {
+                game_id: ActiveValue::Set(game_id),
+                tag_id: ActiveValue::Set(tag_id),
+            });
+            BoardGameTag::insert_many(tags).exec(&txn).await?;
+        }
+        txn.commit().await
+    }
+
     /// Saves a rental to the database. Handles both insertions and updates.
     pub(crate) async fn save_rental(&self, rental: RentalActiveModel) -> Result<(), DbErr> {
         rental.save(&self.db).await?;
@@ -158,31 +1011,126 @@ impl DatabaseManager {
         Ok(rental)
     }
 
-    /// Retrieves all rentals from the database, along with
-    /// the information about associated board games and users.
-    pub(crate) async fn get_rentals(&self) -> Result<Vec<GetRentalsQueryResult>, DbErr> {
+    /// Counts the rentals of the given user, including the ones that haven't started yet.
+    pub(crate) async fn count_user_rentals(&self, user_id: i32) -> Result<u64, DbErr> {
+        let count = Rental::find()
+            .filter(rental::Column::UserId.eq(user_id))
+            .count(&self.db)
+            .await?;
+        Ok(count)
+    }
+
+    /// Retrieves all rentals that haven't been picked up, even though they started before the given date.
+    pub(crate) async fn get_unclaimed_rentals(
+        &self,
+        before: Date,
+    ) -> Result<Vec<RentalModel>, DbErr> {
+        let rentals = Rental::find()
+            .filter(rental::Column::PickedUp.eq(false))
+            .filter(rental::Column::RentalDate.lt(before))
+            .order_by_asc(rental::Column::RentalDate)
+            .all(&self.db)
+            .await?;
+        Ok(rentals)
+    }
+
+    /// Retrieves all picked up rentals that should have been returned before the given date,
+    /// but haven't been flagged as overdue yet.
+    pub(crate) async fn get_unflagged_overdue_rentals(
+        &self,
+        today: Date,
+    ) -> Result<Vec<RentalModel>, DbErr> {
+        let rentals = Rental::find()
+            .filter(rental::Column::PickedUp.eq(true))
+            .filter(rental::Column::ReturnDate.lt(today))
+            .filter(rental::Column::OverdueSince.is_null())
+            .order_by_asc(rental::Column::ReturnDate)
+            .all(&self.db)
+            .await?;
+        Ok(rentals)
+    }
+
+    /// Marks a rental of the given ID as overdue since the given date.
+    pub(crate) async fn flag_overdue_rental(&self, id: i32, since: Date) -> Result<(), DbErr> {
+        let rental = RentalActiveModel {
+            id: ActiveValue::Set(id),
+            overdue_since: ActiveValue::Set(Some(since)),
+            ..Default::default()
+        };
+        rental.update(&self.db).await?;
+        Ok(())
+    }
+
+    /// Retrieves all picked up rentals that have to be returned on the given date,
+    /// but haven't been reminded about yet.
+    pub(crate) async fn get_unreminded_rentals_due_on(
+        &self,
+        date: Date,
+    ) -> Result<Vec<RentalModel>, DbErr> {
+        let rentals = Rental::find()
+            .filter(rental::Column::PickedUp.eq(true))
+            .filter(rental::Column::ReturnDate.eq(date))
+            .filter(rental::Column::ReminderSentAt.is_null())
+            .all(&self.db)
+            .await?;
+        Ok(rentals)
+    }
+
+    /// Marks that the user of a rental of the given ID has been reminded about its return date.
+    pub(crate) async fn mark_reminder_sent(&self, id: i32, at: DateTime) -> Result<(), DbErr> {
+        let rental = RentalActiveModel {
+            id: ActiveValue::Set(id),
+            reminder_sent_at: ActiveValue::Set(Some(at)),
+            ..Default::default()
+        };
+        rental.update(&self.db).await?;
+        Ok(())
+    }
+
+    /// Retrieves all rentals with an extension request still pending,
+    /// even though their return date is already before the given date.
+    pub(crate) async fn get_expired_extension_requests(
+        &self,
+        today: Date,
+    ) -> Result<Vec<RentalModel>, DbErr> {
         let rentals = Rental::find()
+            .filter(rental::Column::ExtensionDate.is_not_null())
+            .filter(rental::Column::ReturnDate.lt(today))
+            .all(&self.db)
+            .await?;
+        Ok(rentals)
+    }
+
+    /// Retrieves a page of rentals from the database, along with
+    /// the information about associated board games and users.
+    pub(crate) async fn get_rentals(
+        &self,
+        page: PageParams,
+    ) -> Result<Page<GetRentalsQueryResult>, DbErr> {
+        let query = Rental::find()
+            .column(game_copy::Column::GameId)
             .columns([board_game::Column::Title, board_game::Column::PhotoFilename])
             .columns([user::Column::Name, user::Column::Surname])
             .inner_join(BoardGame)
             .inner_join(User)
             .order_by_asc(rental::Column::RentalDate)
-            .into_model::<GetRentalsQueryResult>()
-            .all(&self.db)
-            .await?;
-        Ok(rentals)
+            .order_by_asc(rental::Column::Id)
+            .into_model::<GetRentalsQueryResult>();
+        fetch_page(&self.db, query, page).await
     }
 
-    /// Retrieves all rentals from the database for the given user ID,
+    /// Retrieves a page of rentals from the database for the given user ID,
     /// along with the information about associated board games
     /// and whether they are in the user's favourites.
     pub(crate) async fn get_user_rentals(
         &self,
         user_id: i32,
-    ) -> Result<Vec<GetUserRentalsQueryResult>, DbErr> {
-        let user_rentals = Rental::find()
+        page: PageParams,
+    ) -> Result<Page<GetUserRentalsQueryResult>, DbErr> {
+        let query = Rental::find()
             .select_only()
             .columns(rental::Column::iter().filter(|c| !matches!(c, rental::Column::UserId)))
+            .column(game_copy::Column::GameId)
             .columns([board_game::Column::Title, board_game::Column::PhotoFilename])
             .expr_as(
                 Expr::case(
@@ -205,84 +1153,122 @@ impl DatabaseManager {
             )
             .filter(rental::Column::UserId.eq(user_id))
             .order_by_asc(rental::Column::RentalDate)
-            .into_model::<GetUserRentalsQueryResult>()
-            .all(&self.db)
-            .await?;
-        Ok(user_rentals)
+            .order_by_asc(rental::Column::Id)
+            .into_model::<GetUserRentalsQueryResult>();
+        fetch_page(&self.db, query, page).await
     }
 
-    /// Retrieves all rentals from the database for the given user ID,
+    /// Retrieves a page of rentals from the database for the given user ID,
     /// along with the information about associated board games.
     /// Should be used by admin as it doesn't contain information about user favourites.
     pub(crate) async fn get_user_rentals_admin(
         &self,
         user_id: i32,
-    ) -> Result<Vec<GetUserRentalsAdminQueryResult>, DbErr> {
-        let user_rentals = Rental::find()
+        page: PageParams,
+    ) -> Result<Page<GetUserRentalsAdminQueryResult>, DbErr> {
+        let query = Rental::find()
             .select_only()
             .columns(rental::Column::iter().filter(|c| !matches!(c, rental::Column::UserId)))
+            .column(game_copy::Column::GameId)
             .columns([board_game::Column::Title, board_game::Column::PhotoFilename])
             .inner_join(BoardGame)
             .filter(rental::Column::UserId.eq(user_id))
             .order_by_asc(rental::Column::RentalDate)
-            .into_model::<GetUserRentalsAdminQueryResult>()
-            .all(&self.db)
-            .await?;
-        Ok(user_rentals)
+            .order_by_asc(rental::Column::Id)
+            .into_model::<GetUserRentalsAdminQueryResult>();
+        fetch_page(&self.db, query, page).await
     }
 
     /// Deletes a rental of the given ID from the database.
+    #[allow(dead_code)]
     pub(crate) async fn delete_rental(&self, id: i32) -> Result<(), DbErr> {
         Rental::delete_by_id(id).exec(&self.db).await?;
         Ok(())
     }
 
+    /// Marks a rental of the given ID as picked up by the user right now,
+    /// handed over by the staff member of the given ID.
+    pub(crate) async fn pick_up_rental(&self, id: i32, staff_id: i32) -> Result<(), DbErr> {
+        let rental = RentalActiveModel {
+            id: ActiveValue::Set(id),
+            picked_up: ActiveValue::Set(true),
+            picked_up_at: ActiveValue::Set(Some(Utc::now().naive_utc())),
+            picked_up_by: ActiveValue::Set(Some(staff_id)),
+            ..Default::default()
+        };
+        rental.update(&self.db).await?;
+        Ok(())
+    }
+
     /// Archives a rental of the given ID by moving it to the rental history table.
-    pub(crate) async fn archive_rental(&self, id: i32) -> Result<(), DbErr> {
-        let rental = Rental::find_by_id(id).one(&self.db).await?;
-        if let Some(rental) = rental {
+    /// If the ID of the staff member who took the copy back is given, the rental is recorded
+    /// as returned right now. Actual pickup and return dates replace the planned ones.
+    /// The given penalty event, e.g. for a late return, is saved in the same transaction.
+    pub(crate) async fn archive_rental(
+        &self,
+        id: i32,
+        returned_by: Option<i32>,
+        penalty: Option<PenaltyEventActiveModel>,
+    ) -> Result<(), DbErr> {
+        let rental = Rental::find_by_id(id)
+            .find_also_related(GameCopy)
+            .one(&self.db)
+            .await?;
+        if let Some((rental, Some(game_copy))) = rental {
             let txn = self.db.begin().await?;
 
+            let returned_at = returned_by.map(|_| Utc::now().naive_utc());
+            let rental_date = rental.picked_up_at.map_or(rental.rental_date, local_date);
+            let return_date = returned_at.map_or(rental.return_date, local_date);
             let rental_history = RentalHistoryActiveModel {
                 id: ActiveValue::Set(rental.id),
-                game_id: ActiveValue::Set(rental.game_id),
+                game_id: ActiveValue::Set(game_copy.game_id),
                 user_id: ActiveValue::Set(rental.user_id),
-                rental_date: ActiveValue::Set(rental.rental_date),
-                return_date: ActiveValue::Set(rental.return_date),
+                rental_date: ActiveValue::Set(rental_date),
+                return_date: ActiveValue::Set(return_date.max(rental_date)),
                 picked_up: ActiveValue::Set(rental.picked_up),
+                picked_up_at: ActiveValue::Set(rental.picked_up_at),
+                picked_up_by: ActiveValue::Set(rental.picked_up_by),
+                returned_at: ActiveValue::Set(returned_at),
+                returned_by: ActiveValue::Set(returned_by),
             };
             rental_history.insert(&txn).await?;
             Rental::delete_by_id(id).exec(&txn).await?;
+            grant_waitlist_hold(&txn, game_copy.game_id, game_copy.id).await?;
+            if let Some(penalty) = penalty {
+                penalty.insert(&txn).await?;
+            }
 
             txn.commit().await?;
         }
         Ok(())
     }
 
-    /// Retrieves all rental history entries from the database,
+    /// Retrieves a page of rental history entries from the database,
     /// along with the information about associated board games and users.
     pub(crate) async fn get_rental_history(
         &self,
-    ) -> Result<Vec<GetRentalHistoryQueryResult>, DbErr> {
-        let rental_history = RentalHistory::find()
+        page: PageParams,
+    ) -> Result<Page<GetRentalHistoryQueryResult>, DbErr> {
+        let query = RentalHistory::find()
             .columns([board_game::Column::Title, board_game::Column::PhotoFilename])
             .columns([user::Column::Name, user::Column::Surname])
             .inner_join(BoardGame)
             .inner_join(User)
             .order_by_desc(rental_history::Column::ReturnDate)
-            .into_model::<GetRentalHistoryQueryResult>()
-            .all(&self.db)
-            .await?;
-        Ok(rental_history)
+            .order_by_desc(rental_history::Column::Id)
+            .into_model::<GetRentalHistoryQueryResult>();
+        fetch_page(&self.db, query, page).await
     }
 
-    /// Retrieves all rental history entries from the database for the given user ID, along with
-    /// the information about associated board games and whether they are in the user's favourites.
+    /// Retrieves a page of rental history entries from the database for the given user ID, along
+    /// with the information about associated board games and whether they are in the user's favourites.
     pub(crate) async fn get_user_rental_history(
         &self,
         user_id: i32,
-    ) -> Result<Vec<GetUserRentalHistoryQueryResult>, DbErr> {
-        let user_rental_history = RentalHistory::find()
+        page: PageParams,
+    ) -> Result<Page<GetUserRentalHistoryQueryResult>, DbErr> {
+        let query = RentalHistory::find()
             .select_only()
             .columns(
                 rental_history::Column::iter()
@@ -310,20 +1296,20 @@ impl DatabaseManager {
             )
             .filter(rental_history::Column::UserId.eq(user_id))
             .order_by_desc(rental_history::Column::ReturnDate)
-            .into_model::<GetUserRentalHistoryQueryResult>()
-            .all(&self.db)
-            .await?;
-        Ok(user_rental_history)
+            .order_by_desc(rental_history::Column::Id)
+            .into_model::<GetUserRentalHistoryQueryResult>();
+        fetch_page(&self.db, query, page).await
     }
 
-    /// Retrieves all rental history entries from the database for the given user ID,
+    /// Retrieves a page of rental history entries from the database for the given user ID,
     /// along with the information about associated board games.
     /// Should be used by admin users only, as it doesn't contain information about user favourites.
     pub(crate) async fn get_user_rental_history_admin(
         &self,
         user_id: i32,
-    ) -> Result<Vec<GetUserRentalHistoryAdminQueryResult>, DbErr> {
-        let user_rental_history = Rental::find()
+        page: PageParams,
+    ) -> Result<Page<GetUserRentalHistoryAdminQueryResult>, DbErr> {
+        let query = RentalHistory::find()
             .select_only()
             .columns(
                 rental_history::Column::iter()
@@ -333,10 +1319,9 @@ impl DatabaseManager {
             .inner_join(BoardGame)
             .filter(rental_history::Column::UserId.eq(user_id))
             .order_by_desc(rental_history::Column::ReturnDate)
-            .into_model::<GetUserRentalHistoryAdminQueryResult>()
-            .all(&self.db)
-            .await?;
-        Ok(user_rental_history)
+            .order_by_desc(rental_history::Column::Id)
+            .into_model::<GetUserRentalHistoryAdminQueryResult>();
+        fetch_page(&self.db, query, page).await
     }
 
     /// Deletes a rental history entry of the given ID from the database.
@@ -344,7 +1329,119 @@ impl DatabaseManager {
         RentalHistory::delete_by_id(id).exec(&self.db).await?;
         Ok(())
     }
-    
+
+    /// Adds a user to the end of the waitlist of the given board game.
+    pub(crate) async fn join_waitlist(&self, user_id: i32, game_id: i32) -> Result<(), DbErr> {
+        let waitlist = WaitlistActiveModel {
+            game_id: ActiveValue::Set(game_id),
+            user_id: ActiveValue::Set(user_id),
+            created_at: ActiveValue::Set(Utc::now().naive_utc()),
+            ..Default::default()
+        };
+        waitlist.insert(&self.db).await?;
+        Ok(())
+    }
+
+    /// Removes a user from the waitlist of the given board game.
+    /// If the user was holding a copy, it is passed on to the next person in line.
+    pub(crate) async fn leave_waitlist(&self, user_id: i32, game_id: i32) -> Result<(), DbErr> {
+        let entry = Waitlist::find()
+            .filter(waitlist::Column::UserId.eq(user_id))
+            .filter(waitlist::Column::GameId.eq(game_id))
+            .one(&self.db)
+            .await?;
+        if let Some(entry) = entry {
+            let txn = self.db.begin().await?;
+            remove_waitlist_entry(&txn, entry).await?;
+            txn.commit().await?;
+        }
+        Ok(())
+    }
+
+    /// Retrieves the waitlist entry of the given user for the given board game.
+    pub(crate) async fn get_waitlist_entry(
+        &self,
+        user_id: i32,
+        game_id: i32,
+    ) -> Result<Option<WaitlistModel>, DbErr> {
+        let entry = Waitlist::find()
+            .filter(waitlist::Column::UserId.eq(user_id))
+            .filter(waitlist::Column::GameId.eq(game_id))
+            .one(&self.db)
+            .await?;
+        Ok(entry)
+    }
+
+    /// Retrieves a page of the waitlist entries of the given user, along with
+    /// the information about associated board games and positions in their queues.
+    pub(crate) async fn get_user_waitlist(
+        &self,
+        user_id: i32,
+        page: PageParams,
+    ) -> Result<Page<GetWaitlistQueryResult>, DbErr> {
+        let query = Waitlist::find()
+            .find_also_related(BoardGame)
+            .filter(waitlist::Column::UserId.eq(user_id))
+            .order_by_asc(waitlist::Column::CreatedAt)
+            .order_by_asc(waitlist::Column::Id);
+        let entries = fetch_page(&self.db, query, page).await?;
+
+        let mut user_waitlist = Vec::with_capacity(entries.items.len());
+        for (entry, board_game) in entries.items {
+            user_waitlist.push(waitlist_position(&self.db, entry, board_game).await?);
+        }
+        Ok(Page {
+            items: user_waitlist,
+            page: entries.page,
+            limit: entries.limit,
+            total_items: entries.total_items,
+            total_pages: entries.total_pages,
+        })
+    }
+
+    /// Retrieves the waitlist entry of the given user for the given board game,
+    /// along with the information about the game and the position in its queue.
+    pub(crate) async fn get_user_waitlist_position(
+        &self,
+        user_id: i32,
+        game_id: i32,
+    ) -> Result<Option<GetWaitlistQueryResult>, DbErr> {
+        let entry = Waitlist::find()
+            .find_also_related(BoardGame)
+            .filter(waitlist::Column::UserId.eq(user_id))
+            .filter(waitlist::Column::GameId.eq(game_id))
+            .one(&self.db)
+            .await?;
+        match entry {
+            Some((entry, board_game)) => {
+                Ok(Some(waitlist_position(&self.db, entry, board_game).await?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Removes waitlist entries whose holds have expired by the given time
+    /// and passes the held copies on to the next people in line.
+    /// Returns the number of expired holds.
+    pub(crate) async fn expire_waitlist_holds(&self, now: DateTime) -> Result<usize, DbErr> {
+        let expired = Waitlist::find()
+            .filter(waitlist::Column::HoldExpiresAt.lte(now))
+            .order_by_asc(waitlist::Column::HoldExpiresAt)
+            .all(&self.db)
+            .await?;
+        if expired.is_empty() {
+            return Ok(0);
+        }
+
+        let count = expired.len();
+        let txn = self.db.begin().await?;
+        for entry in expired {
+            remove_waitlist_entry(&txn, entry).await?;
+        }
+        txn.commit().await?;
+        Ok(count)
+    }
+
     /// Checks if a given game is given user's favourite.
     pub(crate) async fn is_favourite(&self, user_id: i32, game_id: i32) -> Result<bool, DbErr> {
         let favourite = Favourite::find()
@@ -373,64 +1470,591 @@ impl DatabaseManager {
     }
 }
 
-#[derive(Debug, Eq, PartialEq, FromQueryResult, Serialize, Deserialize)]
+/// Finds the position of the waitlist entry in the queue of its board game.
+async fn waitlist_position<C>(
+    db: &C,
+    entry: WaitlistModel,
+    board_game: Option<BoardGameModel>,
+) -> Result<GetWaitlistQueryResult, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let queue = Waitlist::find().filter(waitlist::Column::GameId.eq(entry.game_id));
+    let position = queue
+        .clone()
+        .filter(
+            Condition::any()
+                .add(waitlist::Column::CreatedAt.lt(entry.created_at))
+                .add(
+                    waitlist::Column::CreatedAt
+                        .eq(entry.created_at)
+                        .and(waitlist::Column::Id.lt(entry.id)),
+                ),
+        )
+        .count(db)
+        .await?;
+    let queue_length = queue.count(db).await?;
+
+    Ok(GetWaitlistQueryResult {
+        game_id: entry.game_id,
+        title: board_game.map(|b| b.title).unwrap_or_default(),
+        position: position + 1,
+        queue_length,
+        joined_at: entry.created_at,
+        hold_copy_id: entry.hold_copy_id,
+        hold_expires_at: entry.hold_expires_at,
+    })
+}
+
+/// Deletes a waitlist entry, passing its hold (if any) on to the next person in line.
+async fn remove_waitlist_entry<C>(db: &C, entry: WaitlistModel) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    Waitlist::delete_by_id(entry.id).exec(db).await?;
+    if let Some(copy_id) = entry.hold_copy_id {
+        grant_waitlist_hold(db, entry.game_id, copy_id).await?;
+    }
+    Ok(())
+}
+
+/// Gives a time-limited hold on a freed copy to the first person in the waitlist
+/// of the given board game who doesn't hold a copy yet. Does nothing if the copy
+/// is already held or is rented at the moment.
+async fn grant_waitlist_hold<C>(db: &C, game_id: i32, copy_id: i32) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    let now = Utc::now().naive_utc();
+    let is_held = Waitlist::find()
+        .filter(waitlist::Column::HoldCopyId.eq(copy_id))
+        .filter(waitlist::Column::HoldExpiresAt.gt(now))
+        .count(db)
+        .await?
+        > 0;
+    let is_rented = Rental::find()
+        .filter(rental::Column::CopyId.eq(copy_id))
+        .filter(rental::Column::RentalDate.lte(today()))
+        .count(db)
+        .await?
+        > 0;
+    if is_held || is_rented {
+        return Ok(());
+    }
+
+    let next = Waitlist::find()
+        .filter(waitlist::Column::GameId.eq(game_id))
+        .filter(waitlist::Column::HoldCopyId.is_null())
+        .order_by_asc(waitlist::Column::CreatedAt)
+        .order_by_asc(waitlist::Column::Id)
+        .one(db)
+        .await?;
+    if let Some(next) = next {
+        let hold_expires_at: DateTime = now + Duration::hours(WAITLIST_HOLD_HOURS);
+        let waitlist = WaitlistActiveModel {
+            id: ActiveValue::Set(next.id),
+            hold_copy_id: ActiveValue::Set(Some(copy_id)),
+            hold_expires_at: ActiveValue::Set(Some(hold_expires_at)),
+            ..Default::default()
+        };
+        waitlist.update(db).await?;
+    }
+    Ok(())
+}
+
+/// Makes all tokens of the given user invalid, see `DatabaseManager::revoke_user_tokens`.
+async fn revoke_user_tokens<C>(db: &C, user_id: i32, now: DateTime) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    User::update_many()
+        .col_expr(
+            user::Column::TokenVersion,
+            Expr::col(user::Column::TokenVersion).add(1),
+        )
+        .filter(user::Column::Id.eq(user_id))
+        .exec(db)
+        .await?;
+    revoke_user_sessions(db, user_id, now).await
+}
+
+/// Revokes all active sessions of the given user.
+async fn revoke_user_sessions<C>(db: &C, user_id: i32, now: DateTime) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    Session::update_many()
+        .col_expr(session::Column::RevokedAt, Expr::value(now))
+        .filter(session::Column::UserId.eq(user_id))
+        .filter(session::Column::RevokedAt.is_null())
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// Retrieves a free copy of a board game, see `DatabaseManager::get_free_game_copy`.
+async fn find_free_game_copy<C>(
+    db: &C,
+    game_id: i32,
+    from: Date,
+    to: Date,
+    user_id: i32,
+) -> Result<Option<GameCopyModel>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let busy_copies = Rental::find()
+        .select_only()
+        .column(rental::Column::CopyId)
+        .filter(overlapping_rentals(from, to))
+        .into_query();
+    let held_copies = Waitlist::find()
+        .select_only()
+        .column(waitlist::Column::HoldCopyId)
+        .filter(waitlist::Column::HoldCopyId.is_not_null())
+        .filter(waitlist::Column::HoldExpiresAt.gt(Utc::now().naive_utc()))
+        .filter(waitlist::Column::HoldExpiresAt.gte(from.and_time(Default::default())))
+        .filter(waitlist::Column::UserId.ne(user_id))
+        .into_query();
+    GameCopy::find()
+        .filter(game_copy::Column::GameId.eq(game_id))
+        .filter(game_copy::Column::Id.not_in_subquery(busy_copies))
+        .filter(game_copy::Column::Id.not_in_subquery(held_copies))
+        .order_by_asc(game_copy::Column::Id)
+        .one(db)
+        .await
+}
+
+/// Checks whether a copy has no rentals in a period, see `DatabaseManager::is_game_copy_free`.
+async fn is_copy_free<C>(
+    db: &C,
+    copy_id: i32,
+    from: Date,
+    to: Date,
+    excluded_rental_id: Option<i32>,
+) -> Result<bool, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let overlapping = Rental::find()
+        .filter(rental::Column::CopyId.eq(copy_id))
+        .filter(overlapping_rentals(from, to))
+        .apply_if(excluded_rental_id, |query, id| {
+            query.filter(rental::Column::Id.ne(id))
+        })
+        .count(db)
+        .await?;
+    Ok(overlapping == 0)
+}
+
+/// Takes the write lock of the database for the given transaction at once, like
+/// `BEGIN IMMEDIATE` in SQLite. Other transactions doing the same wait until it ends,
+/// so nothing it has read can be changed by them before it writes.
+async fn lock_for_writing(txn: &DatabaseTransaction) -> Result<(), DbErr> {
+    // SQLite takes the lock with the first write, even one that doesn't change any row.
+    txn.execute_unprepared("UPDATE rental SET id = id WHERE 0")
+        .await?;
+    Ok(())
+}
+
+/// Deletes all recovery codes of the given user and saves the new ones.
+async fn replace_recovery_codes<C>(
+    db: &C,
+    user_id: i32,
+    recovery_code_hashes: Vec<String>,
+) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    RecoveryCode::delete_many()
+        .filter(recovery_code::Column::UserId.eq(user_id))
+        .exec(db)
+        .await?;
+    if recovery_code_hashes.is_empty() {
+        return Ok(());
+    }
+
+    let codes = recovery_code_hashes
+        .into_iter()
+        .map(|code_hash| RecoveryCodeActiveModel {
+            user_id: ActiveValue::Set(user_id),
+            code_hash: ActiveValue::Set(code_hash),
+            ..Default::default()
+        });
+    RecoveryCode::insert_many(codes).exec(db).await?;
+    Ok(())
+}
+
+/// Builds a condition matching penalty events that haven't expired yet.
+fn active_penalty_events() -> Condition {
+    Condition::any()
+        .add(penalty_event::Column::ExpiresAt.is_null())
+        .add(penalty_event::Column::ExpiresAt.gt(Utc::now().naive_utc()))
+}
+
+/// Returns the current local date.
+pub(crate) fn today() -> Date {
+    Local::now().date_naive()
+}
+
+/// Converts a UTC timestamp, as stored in the database, into a local date.
+fn local_date(date_time: DateTime) -> Date {
+    Local.from_utc_datetime(&date_time).date_naive()
+}
+
+/// Builds a condition matching rentals that keep a copy busy at any day of the given period.
+/// Rentals which haven't been archived yet are treated as lasting at least until today,
+/// because an overdue copy is still not back on the shelf.
+fn overlapping_rentals(from: Date, to: Date) -> Condition {
+    let condition = Condition::all().add(rental::Column::RentalDate.lte(to));
+    if from > today() {
+        condition.add(rental::Column::ReturnDate.gte(from))
+    } else {
+        condition
+    }
+}
+
+/// Filters for searching the board game catalogue. Unset fields don't filter anything.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct BoardGameFilter {
+    /// Words which all have to appear in the title or the additional info.
+    pub query: Option<String>,
+    /// Number of players the game has to support.
+    pub players: Option<u8>,
+    /// Playtime window the whole playtime of the game has to fit in.
+    pub min_playtime: Option<u16>,
+    pub max_playtime: Option<u16>,
+    pub min_weight: Option<u16>,
+    pub max_weight: Option<u16>,
+    /// Only games with a copy that can be rented right now.
+    pub available_now: Option<bool>,
+    /// Only games in the user's favourites.
+    pub favourites_only: Option<bool>,
+    /// Comma-separated names of categories the game has to be in, all of them.
+    pub categories: Option<String>,
+    /// Comma-separated names of tags the game has to have, all of them.
+    pub tags: Option<String>,
+    pub sort: Option<BoardGameSort>,
+}
+
+impl BoardGameFilter {
+    /// Builds a condition on the `board_game` columns.
+    fn condition(&self) -> Condition {
+        let mut condition = Condition::all();
+        let words = self.query.iter().flat_map(|query| query.split_whitespace());
+        for word in words {
+            let pattern = LikeExpr::new(format!("%{}%", escape_like(word))).escape('\\');
+            condition = condition.add(
+                Condition::any()
+                    .add(
+                        Expr::col((board_game::Entity, board_game::Column::Title))
+                            .like(pattern.clone()),
+                    )
+                    .add(
+                        Expr::col((board_game::Entity, board_game::Column::AdditionalInfo))
+                            .like(pattern),
+                    ),
+            );
+        }
+        if let Some(players) = self.players {
+            condition = condition
+                .add(board_game::Column::MinPlayers.lte(players))
+                .add(board_game::Column::MaxPlayers.gte(players));
+        }
+        if let Some(min_playtime) = self.min_playtime {
+            condition = condition.add(board_game::Column::MinPlaytime.gte(min_playtime));
+        }
+        if let Some(max_playtime) = self.max_playtime {
+            condition = condition.add(board_game::Column::MaxPlaytime.lte(max_playtime));
+        }
+        if let Some(min_weight) = self.min_weight {
+            condition = condition.add(board_game::Column::Weight.gte(min_weight));
+        }
+        if let Some(max_weight) = self.max_weight {
+            condition = condition.add(board_game::Column::Weight.lte(max_weight));
+        }
+        for name in split_names(&self.categories) {
+            let games = BoardGameCategory::find()
+                .select_only()
+                .column(board_game_category::Column::GameId)
+                .inner_join(Category)
+                .filter(category::Column::Name.eq(name))
+                .into_query();
+            condition = condition.add(board_game::Column::Id.in_subquery(games));
+        }
+        for name in split_names(&self.tags) {
+            let games = BoardGameTag::find()
+                .select_only()
+                .column(board_game_tag::Column::GameId)
+                .inner_join(Tag)
+                .filter(tag::Column::Name.eq(name))
+                .into_query();
+            condition = condition.add(board_game::Column::Id.in_subquery(games));
+        }
+        condition
+    }
+}
+
+/// Splits a comma-separated list of names, skipping empty ones.
+fn split_names(names: &Option<String>) -> impl Iterator<Item = String> + '_ {
+    names
+        .iter()
+        .flat_map(|names| names.split(','))
+        .map(|name| name.trim().to_lowercase())
+        .filter(|name| !name.is_empty())
+}
+
+/// Order of the board game search results.
+#[derive(Debug, Default, Clone, Copy, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BoardGameSort {
+    #[default]
+    Title,
+    TitleDesc,
+    Weight,
+    WeightDesc,
+    /// Shortest games first.
+    Playtime,
+    /// Longest games first.
+    PlaytimeDesc,
+    /// Games with the most available copies first.
+    Available,
+}
+
+/// Escapes the wildcards of `LIKE` patterns, so that the text is matched literally.
+fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// Fetches the requested page of the query results along with the total number of results.
+async fn fetch_page<'db, Q>(
+    db: &'db DatabaseConnection,
+    query: Q,
+    params: PageParams,
+) -> Result<Page<<Q::Selector as SelectorTrait>::Item>, DbErr>
+where
+    Q: PaginatorTrait<'db, DatabaseConnection>,
+{
+    let limit = params.limit();
+    let paginator = query.paginate(db, limit);
+    let ItemsAndPagesNumber {
+        number_of_items,
+        number_of_pages,
+    } = paginator.num_items_and_pages().await?;
+    let page = params.page();
+    let items = paginator.fetch_page(page - 1).await?;
+    Ok(Page {
+        items,
+        page,
+        limit,
+        total_items: number_of_items,
+        total_pages: number_of_pages,
+    })
+}
+
+/// Query parameters selecting a page of a list. Pages are numbered from 1.
+#[derive(Debug, Default, Clone, Copy, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PageParams {
+    pub page: Option<u64>,
+    pub limit: Option<u64>,
+}
+
+impl PageParams {
+    fn page(&self) -> u64 {
+        self.page.unwrap_or(1).max(1)
+    }
+
+    fn limit(&self) -> u64 {
+        self.limit
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE)
+    }
+}
+
+/// Page of a list, as returned by all list endpoints:
+/// `{ "items": [...], "page": 1, "limit": 20, "totalItems": 42, "totalPages": 3 }`.
+/// Asking for a page past the last one returns no items.
+#[derive(Debug, Eq, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub page: u64,
+    pub limit: u64,
+    pub total_items: u64,
+    pub total_pages: u64,
+}
+
+impl<T> Page<T> {
+    /// Converts the items, keeping the page info.
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            page: self.page,
+            limit: self.limit,
+            total_items: self.total_items,
+            total_pages: self.total_pages,
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, FromQueryResult, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GetUsersQueryResult {
+    id: i32,
+    name: String,
+    surname: String,
+    email: String,
+    role: String,
+    penalty_points: i64,
+}
+
+#[derive(Debug, Eq, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GetRolesQueryResult {
+    id: i32,
+    name: String,
+    permissions: Vec<Permission>,
+}
+
+#[derive(Debug, Eq, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GetPenaltiesQueryResult {
+    points: i64,
+    is_penalized: bool,
+    events: Page<PenaltyEventModel>,
+}
+
+/// Query result including the photo of a board game, which is sent to the client as `ImageUrls`.
+pub(crate) trait HasPhoto {
+    fn photo_filename(&self) -> &str;
+}
+
+macro_rules! impl_has_photo {
+    ($($result:ty),* $(,)?) => {
+        $(
+            impl HasPhoto for $result {
+                fn photo_filename(&self) -> &str {
+                    &self.photo_filename
+                }
+            }
+        )*
+    };
+}
+
+impl_has_photo!(
+    GetBoardGamesQueryResult,
+    GetRentalsQueryResult,
+    GetUserRentalsQueryResult,
+    GetUserRentalsAdminQueryResult,
+    GetRentalHistoryQueryResult,
+    GetUserRentalHistoryQueryResult,
+    GetUserRentalHistoryAdminQueryResult,
+);
+
+#[derive(Debug, Eq, PartialEq, FromQueryResult, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct GetBoardGamesQueryResult {
     id: i32,
     title: String,
+    #[serde(skip)]
     photo_filename: String,
     min_players: u8,
     max_players: u8,
     min_playtime: u16,
     max_playtime: u16,
+    total_copies: i64,
+    available_copies: i64,
     return_date: Option<Date>,
     is_favourite: bool,
+    #[sea_orm(skip)]
+    categories: Vec<String>,
+    #[sea_orm(skip)]
+    tags: Vec<String>,
+}
+
+#[derive(Debug, Eq, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AvailabilityInterval {
+    from: Date,
+    to: Date,
+    free_copies: i64,
+    busy_copies: i64,
+}
+
+#[derive(Debug, Eq, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GetWaitlistQueryResult {
+    pub(crate) game_id: i32,
+    title: String,
+    position: u64,
+    queue_length: u64,
+    joined_at: DateTime,
+    hold_copy_id: Option<i32>,
+    hold_expires_at: Option<DateTime>,
 }
 
-#[derive(Debug, Eq, PartialEq, FromQueryResult, Serialize, Deserialize)]
+#[derive(Debug, Eq, PartialEq, FromQueryResult, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct GetRentalsQueryResult {
     id: i32,
+    copy_id: i32,
     game_id: i32,
     user_id: i32,
     rental_date: Date,
     return_date: Date,
     extension_date: Option<Date>,
     picked_up: bool,
+    picked_up_at: Option<DateTime>,
+    picked_up_by: Option<i32>,
+    overdue_since: Option<Date>,
     title: String,
+    #[serde(skip)]
     photo_filename: String,
     name: String,
     surname: String,
 }
 
-#[derive(Debug, Eq, PartialEq, FromQueryResult, Serialize, Deserialize)]
+#[derive(Debug, Eq, PartialEq, FromQueryResult, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct GetUserRentalsQueryResult {
     id: i32,
+    copy_id: i32,
     game_id: i32,
     rental_date: Date,
     return_date: Date,
     extension_date: Option<Date>,
     picked_up: bool,
+    picked_up_at: Option<DateTime>,
+    overdue_since: Option<Date>,
     title: String,
+    #[serde(skip)]
     photo_filename: String,
     is_favourite: bool,
 }
 
-#[derive(Debug, Eq, PartialEq, FromQueryResult, Serialize, Deserialize)]
+#[derive(Debug, Eq, PartialEq, FromQueryResult, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct GetUserRentalsAdminQueryResult {
     id: i32,
+    copy_id: i32,
     game_id: i32,
     rental_date: Date,
     return_date: Date,
     extension_date: Option<Date>,
     picked_up: bool,
+    picked_up_at: Option<DateTime>,
+    picked_up_by: Option<i32>,
+    overdue_since: Option<Date>,
     title: String,
+    #[serde(skip)]
     photo_filename: String,
 }
 
-#[derive(Debug, Eq, PartialEq, FromQueryResult, Serialize, Deserialize)]
+#[derive(Debug, Eq, PartialEq, FromQueryResult, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct GetRentalHistoryQueryResult {
     id: i32,
@@ -439,13 +2063,18 @@ pub struct GetRentalHistoryQueryResult {
     rental_date: Date,
     return_date: Date,
     picked_up: bool,
+    picked_up_at: Option<DateTime>,
+    picked_up_by: Option<i32>,
+    returned_at: Option<DateTime>,
+    returned_by: Option<i32>,
     title: String,
+    #[serde(skip)]
     photo_filename: String,
     name: String,
     surname: String,
 }
 
-#[derive(Debug, Eq, PartialEq, FromQueryResult, Serialize, Deserialize)]
+#[derive(Debug, Eq, PartialEq, FromQueryResult, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct GetUserRentalHistoryQueryResult {
     id: i32,
@@ -453,12 +2082,15 @@ pub struct GetUserRentalHistoryQueryResult {
     rental_date: Date,
     return_date: Date,
     picked_up: bool,
+    picked_up_at: Option<DateTime>,
+    returned_at: Option<DateTime>,
     title: String,
+    #[serde(skip)]
     photo_filename: String,
     is_favourite: bool,
 }
 
-#[derive(Debug, Eq, PartialEq, FromQueryResult, Serialize, Deserialize)]
+#[derive(Debug, Eq, PartialEq, FromQueryResult, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct GetUserRentalHistoryAdminQueryResult {
     id: i32,
@@ -466,6 +2098,11 @@ pub struct GetUserRentalHistoryAdminQueryResult {
     rental_date: Date,
     return_date: Date,
     picked_up: bool,
+    picked_up_at: Option<DateTime>,
+    picked_up_by: Option<i32>,
+    returned_at: Option<DateTime>,
+    returned_by: Option<i32>,
     title: String,
+    #[serde(skip)]
     photo_filename: String,
 }
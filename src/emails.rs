@@ -0,0 +1,168 @@
+use crate::auth::{CONFIRMATION_EXPIRY_TIME, PASSWORD_RESET_EXPIRY_TIME};
+use crate::db_manager::DatabaseManager;
+use crate::mailer::{deliver, Email, Mailer};
+use entity::rental::Model as RentalModel;
+use entity::user::Model as UserModel;
+use std::env;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Builds an email about a rental, given its user and the title of the rented game.
+pub(crate) type RentalTemplate = fn(&UserModel, &str, &RentalModel) -> Email;
+
+/// The link leads to a frontend page, which confirms the account with `POST /api/v2/users/{id}/confirmation`.
+pub(crate) fn confirmation_email(user: &UserModel, token: Uuid) -> Email {
+    let confirmation_link = format!(
+        "{}/confirm_account?id={}&token={}",
+        env::var("FRONTEND_URL").unwrap(),
+        user.id,
+        token
+    );
+    email(
+        user,
+        "Potwierdź swój email",
+        format!(
+            "Aby aktywować konto, kliknij w link:\n{}\n\
+             Link jest ważny przez {} godzin.",
+            confirmation_link, CONFIRMATION_EXPIRY_TIME
+        ),
+    )
+}
+
+pub(crate) fn password_reset_email(user: &UserModel, token: &str) -> Email {
+    let reset_link = format!(
+        "{}/reset_password?token={}",
+        env::var("FRONTEND_URL").unwrap(),
+        token
+    );
+    email(
+        user,
+        "Resetowanie hasła",
+        format!(
+            "Aby ustawić nowe hasło, kliknij w link:\n{}\n\
+             Link jest ważny przez {} minut. Jeśli prośba o zmianę hasła \
+             nie pochodzi od Ciebie, zignoruj tę wiadomość.",
+            reset_link, PASSWORD_RESET_EXPIRY_TIME
+        ),
+    )
+}
+
+pub(crate) fn due_reminder_email(user: &UserModel, title: &str, rental: &RentalModel) -> Email {
+    email(
+        user,
+        "Przypomnienie o zwrocie gry",
+        format!(
+            "Przypominamy, że termin zwrotu gry \"{}\" mija jutro ({}).",
+            title, rental.return_date
+        ),
+    )
+}
+
+pub(crate) fn overdue_email(user: &UserModel, title: &str, rental: &RentalModel) -> Email {
+    email(
+        user,
+        "Przekroczony termin zwrotu gry",
+        format!(
+            "Termin zwrotu gry \"{}\" minął {}. Prosimy o jak najszybszy zwrot. \
+             Za opóźnienie naliczane są punkty karne.",
+            title, rental.return_date
+        ),
+    )
+}
+
+pub(crate) fn no_show_email(user: &UserModel, title: &str, rental: &RentalModel) -> Email {
+    email(
+        user,
+        "Anulowana rezerwacja",
+        format!(
+            "Rezerwacja gry \"{}\" od dnia {} została anulowana, ponieważ gra nie została odebrana. \
+             Za nieodebraną rezerwację naliczono punkty karne.",
+            title, rental.rental_date
+        ),
+    )
+}
+
+pub(crate) fn extension_accepted_email(
+    user: &UserModel,
+    title: &str,
+    rental: &RentalModel,
+) -> Email {
+    email(
+        user,
+        "Przedłużenie wypożyczenia zaakceptowane",
+        format!(
+            "Prośba o przedłużenie wypożyczenia gry \"{}\" została zaakceptowana. \
+             Nowy termin zwrotu: {}.",
+            title, rental.return_date
+        ),
+    )
+}
+
+pub(crate) fn extension_rejected_email(
+    user: &UserModel,
+    title: &str,
+    rental: &RentalModel,
+) -> Email {
+    email(
+        user,
+        "Przedłużenie wypożyczenia odrzucone",
+        format!(
+            "Prośba o przedłużenie wypożyczenia gry \"{}\" została odrzucona. \
+             Termin zwrotu pozostaje bez zmian: {}.",
+            title, rental.return_date
+        ),
+    )
+}
+
+pub(crate) fn extension_expired_email(
+    user: &UserModel,
+    title: &str,
+    rental: &RentalModel,
+) -> Email {
+    email(
+        user,
+        "Prośba o przedłużenie wygasła",
+        format!(
+            "Prośba o przedłużenie wypożyczenia gry \"{}\" nie została rozpatrzona \
+             przed terminem zwrotu ({}) i wygasła.",
+            title, rental.return_date
+        ),
+    )
+}
+
+/// Sends an email about a rental to its user and returns whether it was delivered.
+/// Failures are only logged, as a missing notification shouldn't undo the action it is about.
+pub(crate) async fn send_rental_email(
+    db: &DatabaseManager,
+    mailer: &Arc<dyn Mailer>,
+    rental: &RentalModel,
+    template: RentalTemplate,
+) -> bool {
+    let (user, board_game) = match (
+        db.get_user(rental.user_id).await,
+        db.get_copy_board_game(rental.copy_id).await,
+    ) {
+        (Ok(Some(user)), Ok(Some(board_game))) => (user, board_game),
+        _ => {
+            eprintln!("Failed to get email data for rental {}", rental.id);
+            return false;
+        }
+    };
+
+    let email = template(&user, &board_game.title, rental);
+    match deliver(mailer.clone(), email).await {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("Failed to send email about rental {}: {}", rental.id, e);
+            false
+        }
+    }
+}
+
+fn email(user: &UserModel, subject: &str, content: String) -> Email {
+    Email {
+        to: user.email.clone(),
+        subject: subject.into(),
+        body: format!("Cześć {},\n\n{}\n", user.name, content),
+    }
+}
@@ -0,0 +1,397 @@
+use crate::api_error::{internal_error, ApiError};
+use crate::db_manager::DatabaseManager;
+use crate::media_store::{media_url, MediaStore};
+use actix_multipart::form::tempfile::TempFile;
+use actix_web::web;
+use image::codecs::png::{CompressionType, FilterType as PngFilter, PngEncoder};
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageError, ImageReader, Limits};
+use sea_orm::DbErr;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::io::{Cursor, ErrorKind};
+use std::path::Path;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+/// Largest accepted image, in bytes.
+pub(crate) const MAX_IMAGE_SIZE: usize = 5 * 1024 * 1024;
+/// Largest accepted width and height of an image, in pixels. A small, well compressed file
+/// can still declare huge dimensions, so they are checked before the pixels are decoded.
+const MAX_IMAGE_DIMENSION: u32 = 6000;
+/// Most memory the decoder can allocate for a single image, in bytes.
+const MAX_DECODER_ALLOC: u64 = 256 * 1024 * 1024;
+
+/// Image formats accepted for board game photos.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ImageFormat {
+    Png,
+    Jpeg,
+    Webp,
+}
+
+impl ImageFormat {
+    /// Recognizes the format by the magic bytes at the start of the file.
+    fn detect(header: &[u8]) -> Option<Self> {
+        if header.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(ImageFormat::Png)
+        } else if header.starts_with(&[0xff, 0xd8, 0xff]) {
+            Some(ImageFormat::Jpeg)
+        } else if header.len() >= 12 && &header[..4] == b"RIFF" && &header[8..12] == b"WEBP" {
+            Some(ImageFormat::Webp)
+        } else {
+            None
+        }
+    }
+
+    fn mime_type(&self) -> &'static str {
+        match self {
+            ImageFormat::Png => "image/png",
+            ImageFormat::Jpeg => "image/jpeg",
+            ImageFormat::Webp => "image/webp",
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg => "jpg",
+            ImageFormat::Webp => "webp",
+        }
+    }
+}
+
+/// Resized copy of an image, stored in its own subdirectory under the name of the original image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ImageVariant {
+    Thumbnail,
+    Medium,
+}
+
+impl ImageVariant {
+    const ALL: [ImageVariant; 2] = [ImageVariant::Thumbnail, ImageVariant::Medium];
+
+    fn directory(&self) -> &'static str {
+        match self {
+            ImageVariant::Thumbnail => "thumbnail",
+            ImageVariant::Medium => "medium",
+        }
+    }
+
+    /// Longest side of the image in pixels. Smaller images are not enlarged.
+    fn max_size(&self) -> u32 {
+        match self {
+            ImageVariant::Thumbnail => 200,
+            ImageVariant::Medium => 600,
+        }
+    }
+}
+
+/// Every variant is stored in each of these formats, WebP for browsers supporting it and PNG otherwise.
+const VARIANT_FORMATS: [image::ImageFormat; 2] =
+    [image::ImageFormat::WebP, image::ImageFormat::Png];
+
+/// Quality of the lossy WebP variants, from 0 to 100.
+const WEBP_QUALITY: f32 = 80.0;
+
+/// Name of the variant file in the given format.
+fn variant_name(file_name: &str, variant: ImageVariant, format: image::ImageFormat) -> String {
+    let stem = Path::new(file_name)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(file_name);
+    let extension = format.extensions_str()[0];
+    format!("{}/{}.{}", variant.directory(), stem, extension)
+}
+
+fn variant_names(file_name: &str) -> impl Iterator<Item = String> + '_ {
+    ImageVariant::ALL.into_iter().flat_map(move |variant| {
+        VARIANT_FORMATS
+            .into_iter()
+            .map(move |format| variant_name(file_name, variant, format))
+    })
+}
+
+/// Encoded variant of an image, ready to be stored.
+struct VariantFile {
+    name: String,
+    data: Vec<u8>,
+    content_type: &'static str,
+}
+
+/// Decodes an image, failing with `ImageError::Limits` if it is too large.
+fn decode_image(data: &[u8]) -> Result<DynamicImage, ImageError> {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_IMAGE_DIMENSION);
+    limits.max_image_height = Some(MAX_IMAGE_DIMENSION);
+    limits.max_alloc = Some(MAX_DECODER_ALLOC);
+
+    let mut reader = ImageReader::new(Cursor::new(data)).with_guessed_format()?;
+    reader.limits(limits);
+    reader.decode()
+}
+
+/// Encodes all variants of an image.
+fn generate_variants(file_name: &str, data: &[u8]) -> Result<Vec<VariantFile>, ImageError> {
+    let original = decode_image(data)?;
+    let mut files = Vec::new();
+    for variant in ImageVariant::ALL {
+        let size = variant.max_size();
+        let needs_resize = original.width() > size || original.height() > size;
+        let resized = if needs_resize {
+            original.resize(size, size, FilterType::Lanczos3)
+        } else {
+            original.clone()
+        };
+
+        // WebP only supports 8-bit color, the alpha channel is kept only if the image has one.
+        let webp = if resized.color().has_alpha() {
+            let pixels = resized.to_rgba8();
+            webp::Encoder::from_rgba(&pixels, pixels.width(), pixels.height()).encode(WEBP_QUALITY)
+        } else {
+            let pixels = resized.to_rgb8();
+            webp::Encoder::from_rgb(&pixels, pixels.width(), pixels.height()).encode(WEBP_QUALITY)
+        };
+        files.push(VariantFile {
+            name: variant_name(file_name, variant, image::ImageFormat::WebP),
+            data: webp.to_vec(),
+            content_type: "image/webp",
+        });
+
+        // Encoding a small PNG again would only make it larger.
+        let png = if !needs_resize && ImageFormat::detect(data) == Some(ImageFormat::Png) {
+            data.to_vec()
+        } else {
+            let mut png = Vec::new();
+            resized.write_with_encoder(PngEncoder::new_with_quality(
+                &mut png,
+                CompressionType::Best,
+                PngFilter::Adaptive,
+            ))?;
+            png
+        };
+        files.push(VariantFile {
+            name: variant_name(file_name, variant, image::ImageFormat::Png),
+            data: png,
+            content_type: "image/png",
+        });
+    }
+    Ok(files)
+}
+
+/// Stores an image together with its variants. The original is stored last,
+/// so that its presence means the variants are complete.
+fn put_image(
+    store: &dyn MediaStore,
+    file_name: &str,
+    data: &[u8],
+    format: ImageFormat,
+) -> Result<(), ApiError> {
+    let variants = generate_variants(file_name, data).map_err(|e| match e {
+        // The header looked right, but the rest of the file is not a valid image.
+        ImageError::Decoding(_) | ImageError::Unsupported(_) => ApiError::UnsupportedImageType,
+        ImageError::IoError(e) if e.kind() == ErrorKind::UnexpectedEof => {
+            ApiError::UnsupportedImageType
+        }
+        ImageError::Limits(_) => ApiError::ImageTooLarge,
+        e => internal_error("failed to generate image variants", e),
+    })?;
+    for variant in variants {
+        store.put(&variant.name, &variant.data, variant.content_type)?;
+    }
+    store.put(file_name, data, format.mime_type())?;
+    Ok(())
+}
+
+/// Validates an uploaded image and stores it together with its variants. Returns the name of the image,
+/// which is derived from its content. The name sent by the client is never used, so uploads can't
+/// overwrite images of other games, and the same image uploaded again is kept as a single file.
+pub(crate) async fn store_image(
+    store: Arc<dyn MediaStore>,
+    image: TempFile,
+) -> Result<String, ApiError> {
+    if image.size > MAX_IMAGE_SIZE {
+        return Err(ApiError::ImageTooLarge);
+    }
+
+    // Reading, resizing and uploading takes a while, so it is done outside of the async runtime.
+    web::block(move || {
+        let data = std::fs::read(image.file.path())
+            .map_err(|e| internal_error("failed to read uploaded file", e))?;
+        let format = ImageFormat::detect(&data).ok_or(ApiError::UnsupportedImageType)?;
+
+        // The declared type has to agree with the content.
+        let content_type = image.content_type.as_ref().map(|mime| mime.essence_str());
+        if content_type != Some(format.mime_type()) {
+            return Err(ApiError::UnsupportedImageType);
+        }
+
+        // The image is written even if it is already stored, as a game which used it might be
+        // removing it at the same time. Having the same name, it can only be replaced with itself.
+        let file_name = format!("{:x}.{}", Sha256::digest(&data), format.extension());
+        put_image(store.as_ref(), &file_name, &data, format)?;
+        Ok(file_name)
+    })
+    .await
+    .map_err(|e| internal_error("failed to store image", e))?
+}
+
+/// Deletes a stored image together with its variants. Names which aren't plain file names are ignored.
+pub(crate) async fn remove_image(store: Arc<dyn MediaStore>, file_name: String) {
+    let path = Path::new(&file_name);
+    if file_name.is_empty() || path.file_name() != Some(path.as_os_str()) {
+        return;
+    }
+
+    let result = web::block(move || {
+        // The original goes first, so that a partially removed image is not taken as complete.
+        for name in std::iter::once(file_name.clone()).chain(variant_names(&file_name)) {
+            if let Err(e) = store.delete(&name) {
+                eprintln!("Failed to remove image {}: {}", name, e);
+            }
+        }
+    })
+    .await;
+    if let Err(e) = result {
+        eprintln!("Failed to remove image: {}", e);
+    }
+}
+
+/// Deletes a stored image, unless some board game still uses it.
+pub(crate) async fn remove_unused_image(
+    db: &DatabaseManager,
+    store: Arc<dyn MediaStore>,
+    file_name: &str,
+) -> Result<(), DbErr> {
+    if !db.is_photo_used(file_name).await? {
+        remove_image(store, file_name.to_string()).await;
+    }
+    Ok(())
+}
+
+/// Generates the variants of the given image if any of them is missing.
+/// Returns whether anything was generated.
+fn generate_missing(store: &dyn MediaStore, file_name: &str) -> Result<bool, String> {
+    let mut missing = false;
+    for name in variant_names(file_name) {
+        missing |= !store.exists(&name).map_err(|e| e.to_string())?;
+    }
+    if !missing {
+        return Ok(false);
+    }
+
+    let data = store
+        .get(file_name)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "the image doesn't exist".to_string())?;
+    let variants = generate_variants(file_name, &data).map_err(|e| e.to_string())?;
+    for variant in variants {
+        store
+            .put(&variant.name, &variant.data, variant.content_type)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(true)
+}
+
+/// Generates the variants which are missing for any of the images used by board games.
+/// Meant to be run once for the images uploaded before the variants were introduced.
+pub(crate) async fn generate_missing_variants(
+    db: &DatabaseManager,
+    store: Arc<dyn MediaStore>,
+) -> Result<(), DbErr> {
+    for file_name in db.get_photo_filenames().await? {
+        let store = store.clone();
+        let name = file_name.clone();
+        let result = web::block(move || generate_missing(store.as_ref(), &name))
+            .await
+            .unwrap_or_else(|e| Err(e.to_string()));
+
+        match result {
+            Ok(true) => println!("Generated variants of {}", file_name),
+            Ok(false) => {}
+            Err(e) => eprintln!("Failed to generate variants of {}: {}", file_name, e),
+        }
+    }
+    Ok(())
+}
+
+/// URLs of an image and all of its variants, as sent to the client.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub(crate) struct ImageUrls {
+    original: String,
+    thumbnail: VariantUrls,
+    medium: VariantUrls,
+}
+
+/// URLs of a variant in each of the formats.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub(crate) struct VariantUrls {
+    webp: String,
+    png: String,
+}
+
+impl ImageUrls {
+    pub(crate) fn new(file_name: &str) -> Self {
+        let variant_urls = |variant| VariantUrls {
+            webp: media_url(&variant_name(file_name, variant, image::ImageFormat::WebP)),
+            png: media_url(&variant_name(file_name, variant, image::ImageFormat::Png)),
+        };
+        ImageUrls {
+            original: media_url(file_name),
+            thumbnail: variant_urls(ImageVariant::Thumbnail),
+            medium: variant_urls(ImageVariant::Medium),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::media_store::LocalMediaStore;
+    use image::RgbImage;
+    use uuid::Uuid;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut data = Vec::new();
+        RgbImage::new(width, height)
+            .write_to(&mut Cursor::new(&mut data), image::ImageFormat::Png)
+            .unwrap();
+        data
+    }
+
+    fn temp_store() -> LocalMediaStore {
+        let dir = std::env::temp_dir().join(format!("images-test-{}", Uuid::new_v4()));
+        LocalMediaStore::new(dir.to_str().unwrap())
+    }
+
+    #[test]
+    fn put_image_stores_all_variants() {
+        let store = temp_store();
+        let data = png(800, 400);
+        put_image(&store, "game.png", &data, ImageFormat::Png).unwrap();
+
+        assert_eq!(store.get("game.png").unwrap(), Some(data));
+        for name in variant_names("game.png") {
+            let variant = store.get(&name).unwrap().expect(&name);
+            let size = decode_image(&variant).unwrap();
+            assert!(size.width() <= 600 && size.height() <= 600, "{}", name);
+        }
+    }
+
+    #[test]
+    fn put_image_rejects_too_large_dimensions() {
+        // A few kilobytes of PNG can declare more pixels than the server should decode.
+        let data = png(MAX_IMAGE_DIMENSION + 1, 1);
+        assert!(data.len() < MAX_IMAGE_SIZE);
+        let result = put_image(&temp_store(), "wide.png", &data, ImageFormat::Png);
+        assert!(matches!(result, Err(ApiError::ImageTooLarge)));
+    }
+
+    #[test]
+    fn put_image_rejects_broken_image() {
+        let data = png(100, 100);
+        let truncated = &data[..data.len() / 2];
+        let result = put_image(&temp_store(), "broken.png", truncated, ImageFormat::Png);
+        assert!(matches!(result, Err(ApiError::UnsupportedImageType)));
+    }
+}
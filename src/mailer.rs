@@ -0,0 +1,184 @@
+use lettre::address::AddressError;
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::transport::file::Error as FileError;
+use lettre::transport::smtp::{authentication::Credentials, Error as SmtpError};
+use lettre::{FileTransport, Message, SmtpTransport, Transport};
+use std::env;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+const DEFAULT_MAILER_DIR: &str = "./emails";
+
+/// An email ready to be sent to a single recipient.
+#[derive(Debug, Clone)]
+pub(crate) struct Email {
+    pub(crate) to: String,
+    pub(crate) subject: String,
+    pub(crate) body: String,
+}
+
+#[derive(Debug)]
+pub(crate) enum MailerError {
+    InvalidAddress(AddressError),
+    Message(lettre::error::Error),
+    Smtp(SmtpError),
+    File(FileError),
+    Io(std::io::Error),
+    Canceled,
+}
+
+impl fmt::Display for MailerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MailerError::InvalidAddress(e) => write!(f, "invalid email address: {}", e),
+            MailerError::Message(e) => write!(f, "failed to build email: {}", e),
+            MailerError::Smtp(e) => write!(f, "failed to send email: {}", e),
+            MailerError::File(e) => write!(f, "failed to save email: {}", e),
+            MailerError::Io(e) => write!(f, "failed to prepare email directory: {}", e),
+            MailerError::Canceled => write!(f, "sending email was canceled"),
+        }
+    }
+}
+
+/// Transport used to deliver emails. Sending blocks the current thread,
+/// so in async code emails should be sent with [`deliver`].
+pub(crate) trait Mailer: Send + Sync + fmt::Debug {
+    fn send(&self, email: &Email) -> Result<(), MailerError>;
+}
+
+/// Delivers emails through an SMTP server.
+#[derive(Debug)]
+pub(crate) struct SmtpMailer {
+    transport: SmtpTransport,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    /// Creates a mailer using the `MAILER_*` settings from the environment.
+    pub(crate) fn from_env() -> Result<Self, MailerError> {
+        let credentials = Credentials::new(
+            env::var("MAILER_USERNAME").unwrap(),
+            env::var("MAILER_PASSWORD").unwrap(),
+        );
+        let port = match env::var("MAILER_PORT").unwrap().parse::<u16>() {
+            Ok(port) => port,
+            Err(_) => panic!("MAILER_PORT has an invalid value"),
+        };
+        let transport = SmtpTransport::starttls_relay(&env::var("MAILER_HOST").unwrap())
+            .map_err(MailerError::Smtp)?
+            .port(port)
+            .credentials(credentials)
+            .build();
+
+        Ok(Self {
+            transport,
+            from: sender()?,
+        })
+    }
+}
+
+impl Mailer for SmtpMailer {
+    fn send(&self, email: &Email) -> Result<(), MailerError> {
+        let message = build_message(&self.from, email)?;
+        self.transport.send(&message).map_err(MailerError::Smtp)?;
+        Ok(())
+    }
+}
+
+/// Saves emails as `.eml` files in a directory instead of sending them.
+#[derive(Debug)]
+pub(crate) struct FileMailer {
+    transport: FileTransport,
+    from: Mailbox,
+}
+
+impl FileMailer {
+    /// Creates a mailer saving emails into the given directory, creating it if needed.
+    pub(crate) fn new(dir: &str) -> Result<Self, MailerError> {
+        std::fs::create_dir_all(dir).map_err(MailerError::Io)?;
+        Ok(Self {
+            transport: FileTransport::new(dir),
+            from: sender()?,
+        })
+    }
+}
+
+impl Mailer for FileMailer {
+    fn send(&self, email: &Email) -> Result<(), MailerError> {
+        let message = build_message(&self.from, email)?;
+        self.transport.send(&message).map_err(MailerError::File)?;
+        Ok(())
+    }
+}
+
+/// Keeps the emails in memory instead of sending them.
+#[derive(Debug, Default)]
+pub(crate) struct MemoryMailer {
+    sent: Mutex<Vec<Email>>,
+}
+
+impl MemoryMailer {
+    /// Returns all emails sent so far.
+    #[cfg(test)]
+    pub(crate) fn sent(&self) -> Vec<Email> {
+        self.sent.lock().unwrap().clone()
+    }
+}
+
+impl Mailer for MemoryMailer {
+    fn send(&self, email: &Email) -> Result<(), MailerError> {
+        email
+            .to
+            .parse::<Mailbox>()
+            .map_err(MailerError::InvalidAddress)?;
+        self.sent.lock().unwrap().push(email.clone());
+        Ok(())
+    }
+}
+
+/// Creates the mailer selected with `MAILER_TRANSPORT` (`smtp`, `file` or `memory`).
+/// SMTP is used when the variable is not set. Panics if the mailer cannot be created.
+pub(crate) fn mailer_from_env() -> Arc<dyn Mailer> {
+    let transport = env::var("MAILER_TRANSPORT").unwrap_or_else(|_| "smtp".into());
+    let mailer: Result<Arc<dyn Mailer>, MailerError> = match transport.as_str() {
+        "smtp" => SmtpMailer::from_env().map(|m| Arc::new(m) as Arc<dyn Mailer>),
+        "file" => {
+            let dir = env::var("MAILER_DIR").unwrap_or_else(|_| DEFAULT_MAILER_DIR.into());
+            FileMailer::new(&dir).map(|m| Arc::new(m) as Arc<dyn Mailer>)
+        }
+        "memory" => Ok(Arc::new(MemoryMailer::default())),
+        _ => panic!("MAILER_TRANSPORT has an invalid value"),
+    };
+    match mailer {
+        Ok(mailer) => mailer,
+        Err(e) => panic!("Failed to initialize mailer: {}", e),
+    }
+}
+
+/// Sends an email on a thread pool, so that slow transports don't block the server.
+pub(crate) async fn deliver(mailer: Arc<dyn Mailer>, email: Email) -> Result<(), MailerError> {
+    match actix_web::rt::task::spawn_blocking(move || mailer.send(&email)).await {
+        Ok(result) => result,
+        Err(_) => Err(MailerError::Canceled),
+    }
+}
+
+/// Reads the sender address from `MAILER_EMAIL`.
+fn sender() -> Result<Mailbox, MailerError> {
+    env::var("MAILER_EMAIL")
+        .unwrap()
+        .parse()
+        .map_err(MailerError::InvalidAddress)
+}
+
+fn build_message(from: &Mailbox, email: &Email) -> Result<Message, MailerError> {
+    let to: Mailbox = email.to.parse().map_err(MailerError::InvalidAddress)?;
+    Message::builder()
+        .from(from.clone())
+        .to(to)
+        .subject(email.subject.clone())
+        .header(ContentType::TEXT_PLAIN)
+        .body(email.body.clone())
+        .map_err(MailerError::Message)
+}
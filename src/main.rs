@@ -1,35 +1,91 @@
+mod api_error;
+mod api_v1;
 mod auth;
+mod config;
 mod db_manager;
-
+mod emails;
+mod images;
+mod mailer;
+mod media_store;
+mod openapi;
+mod penalties;
+mod rate_limit;
+mod rental_policy;
+mod scheduler;
+mod validation;
+
+use crate::api_error::{localize_error, ApiError, ErrorResponse};
 use crate::auth::{
-    generate_jwt, hash_password, send_confirmation_email, verify_jwt, verify_password, Claims,
+    build_refresh_token, generate_challenge_token, generate_jwt, generate_recovery_codes,
+    generate_token, generate_totp_secret, hash_password, hash_token, parse_refresh_token, totp_uri,
+    verify_challenge_token, verify_jwt, verify_password, verify_totp, Claims,
+    CONFIRMATION_EXPIRY_TIME, PASSWORD_RESET_EXPIRY_TIME, REMEMBER_ME_SESSION_EXPIRY_TIME,
+    SESSION_EXPIRY_TIME,
+};
+use crate::config::Config;
+use crate::db_manager::{
+    today, AvailabilityInterval, BoardGameFilter, DatabaseManager, GetBoardGamesQueryResult,
+    GetPenaltiesQueryResult, GetRentalHistoryQueryResult, GetRentalsQueryResult,
+    GetRolesQueryResult, GetUserRentalHistoryAdminQueryResult, GetUserRentalHistoryQueryResult,
+    GetUserRentalsAdminQueryResult, GetUserRentalsQueryResult, GetUsersQueryResult,
+    GetWaitlistQueryResult, HasPhoto, Page, PageParams,
+};
+use crate::emails::{
+    confirmation_email, extension_accepted_email, extension_rejected_email, password_reset_email,
+    send_rental_email,
+};
+use crate::images::{generate_missing_variants, remove_unused_image, store_image, ImageUrls};
+use crate::mailer::{deliver, mailer_from_env, Mailer};
+use crate::media_store::{media_store_from_env, MediaStore, LOCAL_MEDIA_URL};
+use crate::openapi::SecurityAddon;
+use crate::penalties::{
+    late_return_penalty, penalize_no_shows, penalty_event, PENALTY_EXPIRY_DAYS,
+};
+use crate::rate_limit::{LimitedAction, MemoryRateLimitStore, RateLimiter};
+use crate::rental_policy::{check_extension, check_new_rental, check_rental_update};
+use crate::scheduler::{Scheduler, SystemClock};
+use crate::validation::{
+    check_registration_taken, validate_label, validate_password, validate_registration,
+    validate_user_update, FieldError, FieldIssue,
 };
-use crate::db_manager::DatabaseManager;
 use actix_cors::Cors;
 use actix_files::{Files, NamedFile};
 use actix_multipart::form::tempfile::TempFile;
 use actix_multipart::form::text::Text;
-use actix_multipart::form::MultipartForm;
+use actix_multipart::form::{MultipartForm, MultipartFormConfig};
+use actix_multipart::MultipartError;
 use actix_web::dev::Payload;
-use actix_web::http::{header, StatusCode};
-use actix_web::web::{Data, Form};
-use actix_web::{get, post, web, App, FromRequest, HttpRequest, HttpResponse, HttpServer};
-use chrono::NaiveDate as Date;
+use actix_web::error::PayloadError;
+use actix_web::http::header;
+use actix_web::middleware::ErrorHandlers;
+use actix_web::web::{Data, FormConfig, Json, JsonConfig, PathConfig, QueryConfig};
+use actix_web::{get, web, App, FromRequest, HttpRequest, HttpResponse, HttpServer};
+use chrono::{Days, Duration, NaiveDate as Date, Utc};
 use dotenv::dotenv;
-use entity::board_game::ActiveModel as BoardGameActiveModel;
+use entity::board_game::{ActiveModel as BoardGameActiveModel, Model as BoardGameModel};
+use entity::category::{ActiveModel as CategoryActiveModel, Model as CategoryModel};
 use entity::favourite::ActiveModel as FavouriteActiveModel;
-use entity::rental::ActiveModel as RentalActiveModel;
-use entity::user::ActiveModel as UserActiveModel;
-use futures::future::{ready, Ready};
+use entity::game_copy::{ActiveModel as GameCopyActiveModel, Model as GameCopyModel};
+use entity::password_reset_token::ActiveModel as PasswordResetTokenActiveModel;
+use entity::rental::{ActiveModel as RentalActiveModel, Model as RentalModel};
+use entity::sea_orm_active_enums::{PenaltyReason, Permission};
+use entity::session::ActiveModel as SessionActiveModel;
+use entity::tag::{ActiveModel as TagActiveModel, Model as TagModel};
+use entity::user::{ActiveModel as UserActiveModel, Model as UserModel};
+use futures::future::LocalBoxFuture;
 use sea_orm::ActiveValue::Set;
-use sea_orm::NotSet;
+use sea_orm::{DbErr, NotSet};
 use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+use std::net::IpAddr;
 use std::path::PathBuf;
+use std::sync::Arc;
+use utoipa::{IntoParams, OpenApi, ToSchema};
+use utoipa_swagger_ui::{SwaggerUi, Url};
 use uuid::Uuid;
 
-const SELF_REQUEST_USER_ID: i32 = 0;
-const HAS_TOKEN: bool = false;
-const HAS_ADMIN_TOKEN: bool = true;
+const AVAILABILITY_DEFAULT_DAYS: u64 = 30;
+const AVAILABILITY_MAX_DAYS: i64 = 366;
 const REQUIRED_ENV_VARS: [&str; 7] = [
     "JWT_SECRET",
     "MAILER_HOST",
@@ -43,59 +99,150 @@ const REQUIRED_ENV_VARS: [&str; 7] = [
 #[derive(Debug, Clone)]
 struct AppState {
     db: DatabaseManager,
+    mailer: Arc<dyn Mailer>,
+    media: Arc<dyn MediaStore>,
+    rate_limiter: RateLimiter,
+    config: Config,
+}
+
+/// Permission required by `Auth`, given as one of the marker types declared with `permissions!`.
+/// Every marker has to name its permission, so that a new one can't end up allowing everyone.
+trait RequiredPermission {
+    /// `None` means that any logged in user is allowed.
+    const PERMISSION: Option<Permission>;
+}
+
+macro_rules! permissions {
+    ($($marker:ident => $permission:expr),* $(,)?) => {
+        $(
+            enum $marker {}
+
+            impl RequiredPermission for $marker {
+                const PERMISSION: Option<Permission> = $permission;
+            }
+        )*
+    };
 }
 
-// Struct for authenticating clients' requests.
-struct Auth<const IS_ADMIN: bool>(Claims);
+permissions! {
+    HasToken => None,
+    ViewUsers => Some(Permission::ViewUsers),
+    ManageUsers => Some(Permission::ManageUsers),
+    ManageRoles => Some(Permission::ManageRoles),
+    ManageCatalog => Some(Permission::ManageCatalog),
+    ManageRentals => Some(Permission::ManageRentals),
+}
+
+// Struct for authenticating clients' requests and checking their permissions.
+struct Auth<P: RequiredPermission>(Claims, PhantomData<P>);
 
-impl<const IS_ADMIN: bool> FromRequest for Auth<IS_ADMIN> {
-    type Error = actix_web::Error;
-    type Future = Ready<Result<Self, Self::Error>>;
+impl<P: RequiredPermission> FromRequest for Auth<P> {
+    type Error = ApiError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
 
     fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
         let token = req
             .headers()
             .get(header::AUTHORIZATION)
             .and_then(|value| value.to_str().ok())
-            .and_then(|str| str.split(" ").nth(1)); // Token is the second word.
-
-        match token {
-            Some(token) => match verify_jwt(token) {
-                Ok(claims) => {
-                    if IS_ADMIN && !claims.is_admin {
-                        return ready(Err(actix_web::error::ErrorForbidden(
-                            "Insufficient privileges",
-                        )));
-                    }
-                    ready(Ok(Self(claims)))
+            .and_then(|str| str.split(" ").nth(1)) // Token is the second word.
+            .map(str::to_owned);
+        let data = req.app_data::<Data<AppState>>().cloned();
+
+        Box::pin(async move {
+            let token = token.ok_or(ApiError::MissingToken)?;
+            let mut claims = verify_jwt(&token).map_err(|_| ApiError::InvalidToken)?;
+
+            // Tokens of revoked sessions, deleted users and tokens issued before e.g.
+            // a password change (which have an outdated version) are rejected.
+            let data = data.expect("AppState is not registered");
+            let user = match data.db.get_session_user(claims.sid).await? {
+                Some((session, user))
+                    if session.revoked_at.is_none()
+                        && user.id == claims.sub
+                        && user.token_version == claims.ver =>
+                {
+                    user
                 }
-                Err(e) => ready(Err(actix_web::error::ErrorUnauthorized(format!(
-                    "Invalid token: {}",
-                    e
-                )))),
-            },
-            None => ready(Err(actix_web::error::ErrorUnauthorized(
-                "No token provided",
-            ))),
-        }
+                _ => return Err(ApiError::TokenRevoked),
+            };
+
+            // Permissions are taken from the database, so that role changes take effect at once.
+            // If required, staff without 2FA are treated as regular users.
+            let permissions = data.db.get_role_permissions(user.role_id).await?;
+            let two_factor_missing = data.config.require_admin_2fa && !user.totp_enabled;
+            if let Some(permission) = P::PERMISSION {
+                if !permissions.contains(&permission) {
+                    return Err(ApiError::Forbidden);
+                }
+                if two_factor_missing {
+                    return Err(ApiError::TwoFactorRequired);
+                }
+            }
+            if !two_factor_missing {
+                claims.permissions = permissions;
+            }
+            Ok(Self(claims, PhantomData))
+        })
     }
 }
 
-fn is_self_request(user: &Claims, id: i32) -> Result<(), HttpResponse> {
-    if !user.is_admin && user.sub != id {
-        Err(HttpResponse::Forbidden().body("Insufficient privileges"))
+/// Checks whether the request concerns the user themselves,
+/// or they have the permission to act on behalf of others.
+fn is_self_request(user: &Claims, id: i32, permission: Permission) -> Result<(), ApiError> {
+    if !user.has_permission(permission) && user.sub != id {
+        Err(ApiError::Forbidden)
     } else {
         Ok(())
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 struct LoginFormData {
     id: i32,
     password: String,
+    #[serde(default)]
+    remember_me: bool,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct TwoFactorLoginFormData {
+    challenge_token: String,
+    code: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct TwoFactorCodeFormData {
+    code: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct RefreshTokenFormData {
+    refresh_token: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct ConfirmationFormData {
+    token: Uuid,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct ResendConfirmationFormData {
+    email: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct ForgotPasswordFormData {
+    email: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct ResetPasswordFormData {
+    token: String,
+    password: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 struct RegisterFormData {
     name: String,
     surname: String,
@@ -104,67 +251,285 @@ struct RegisterFormData {
     password: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 struct ChangePasswordFormData {
     password: String,
 }
 
-#[derive(Debug, Deserialize)]
+/// Changes of a user's data. Fields left out are not changed.
+#[derive(Debug, Deserialize, ToSchema)]
 struct UpdateUserFormData {
-    name: String,
-    surname: String,
-    email: String,
-    password: String,
-    penalty_points: u8,
-    is_admin: bool,
+    name: Option<String>,
+    surname: Option<String>,
+    email: Option<String>,
+    password: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct SetRoleFormData {
+    role_id: i32,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct PenaltyFormData {
+    points: i32,
+    description: String,
+    expiry_days: Option<i64>,
 }
 
-#[derive(Debug, MultipartForm)]
+#[derive(Debug, MultipartForm, ToSchema)]
 struct BoardGameFormData {
+    #[schema(value_type = String)]
     title: Text<String>,
+    #[schema(value_type = u16)]
     weight: Text<u16>,
-    image: TempFile,
+    #[multipart(limit = "5MiB")]
+    #[schema(value_type = Option<String>, format = Binary)]
+    image: Option<TempFile>,
+    #[schema(value_type = u8)]
     min_players: Text<u8>,
+    #[schema(value_type = u8)]
     max_players: Text<u8>,
+    #[schema(value_type = u16)]
     min_playtime: Text<u16>,
+    #[schema(value_type = u16)]
     max_playtime: Text<u16>,
+    #[schema(value_type = String)]
     additional_info: Text<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
+struct GameCopyFormData {
+    game_id: i32,
+    label: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct LabelFormData {
+    name: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct BoardGameCategoriesFormData {
+    category_ids: Vec<i32>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct BoardGameTagsFormData {
+    tag_ids: Vec<i32>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
 struct RentalFormData {
     game_id: i32,
-    rental_date: String,
-    return_date: String,
+    rental_date: Date,
+    return_date: Date,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct RentalDatesFormData {
+    rental_date: Date,
+    return_date: Date,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct AvailabilityQuery {
+    from: Option<String>,
+    to: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 struct ExtensionRequestFormData {
-    extension_date: String,
+    extension_date: Date,
 }
 
-fn build_error_response(status: StatusCode, error: &str) -> HttpResponse {
-    HttpResponse::build(status).json(ErrorResponse {
-        error: error.to_string(),
-    })
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct TwoFactorChallengeResponse {
+    two_factor_required: bool,
+    challenge_token: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct TwoFactorSetupResponse {
+    secret: String,
+    otpauth_uri: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct RecoveryCodesResponse {
+    recovery_codes: Vec<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
-struct ErrorResponse {
-    error: String,
+struct BoardGameResponse {
+    #[serde(flatten)]
+    board_game: BoardGameModel,
+    photo: ImageUrls,
+}
+
+/// Board game with all its copies and rentals, serialized as a `[game, copies, rentals]` array.
+#[derive(Debug, Serialize, ToSchema)]
+struct BoardGameAdminResponse(BoardGameResponse, Vec<GameCopyModel>, Vec<RentalModel>);
+
+impl From<BoardGameModel> for BoardGameResponse {
+    fn from(board_game: BoardGameModel) -> Self {
+        let photo = ImageUrls::new(&board_game.photo_filename);
+        BoardGameResponse { board_game, photo }
+    }
+}
+
+/// Query result with the URLs of its board game photo and the photo's variants.
+#[derive(Debug, Serialize, ToSchema)]
+struct WithPhotoUrls<T> {
+    #[serde(flatten)]
+    item: T,
+    photo: ImageUrls,
+}
+
+impl<T: HasPhoto> From<T> for WithPhotoUrls<T> {
+    fn from(item: T) -> Self {
+        let photo = ImageUrls::new(item.photo_filename());
+        WithPhotoUrls { item, photo }
+    }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 struct LoginResponse {
     jwt_token: String,
+    refresh_token: String,
+}
+
+/// Declares the routes of the `/api/v2` scope, registering them in `configure_api`
+/// and documenting their handlers in `ApiDoc`, so that no route can be left out of the specification.
+macro_rules! api_routes {
+    ($($method:ident $path:literal => $handler:ident),* $(,)?) => {
+        fn configure_api(cfg: &mut web::ServiceConfig) {
+            $(cfg.route($path, web::$method().to($handler));)*
+        }
+
+        /// OpenAPI specification of the `/api/v2` scope, served at `/api/v2/openapi.json`.
+        #[derive(OpenApi)]
+        #[openapi(
+            info(title = "Board game rental system API"),
+            servers((url = "/api/v2")),
+            paths($($handler),*),
+            components(schemas(TwoFactorChallengeResponse, ErrorResponse)),
+            modifiers(&SecurityAddon),
+            tags(
+                (name = "auth", description = "Registration, logging in and sessions"),
+                (name = "users", description = "User accounts"),
+                (name = "roles", description = "Roles and their permissions"),
+                (name = "penalties", description = "Penalty points"),
+                (name = "board_games", description = "Board games and their copies"),
+                (name = "labels", description = "Categories and tags of board games"),
+                (name = "rentals", description = "Rentals"),
+                (name = "history", description = "Finished rentals"),
+                (name = "extensions", description = "Rental extension requests"),
+                (name = "favourites", description = "Favourite board games"),
+                (name = "waitlist", description = "Waitlists of rented out board games"),
+            )
+        )]
+        struct ApiDoc;
+    };
 }
 
+api_routes!(
+    post "/sessions" => login,
+    post "/sessions/refresh" => refresh,
+    delete "/sessions" => logout,
+    post "/sessions/2fa" => login_two_factor,
+    post "/me/2fa/setup" => setup_two_factor,
+    post "/me/2fa" => enable_two_factor,
+    delete "/me/2fa" => disable_two_factor,
+    post "/me/2fa/recovery_codes" => regenerate_recovery_codes,
+    delete "/users/{id}/2fa" => reset_two_factor,
+    delete "/users/{id}/lock" => unlock_user,
+    post "/users" => register,
+    post "/users/{id}/confirmation" => confirm_user,
+    post "/confirmation_emails" => resend_confirmation,
+    get "/users/{id}" => get_user,
+    get "/users" => get_users,
+    get "/users/{id}/penalized" => is_penalized,
+    get "/me/penalties" => get_my_penalties,
+    get "/users/{id}/penalties" => get_user_penalties,
+    post "/users/{id}/penalties" => add_penalty,
+    delete "/penalties/{id}" => delete_penalty,
+    post "/penalties/no_shows" => process_no_shows,
+    put "/users/{id}/password" => change_password,
+    delete "/users/{id}/sessions" => logout_all,
+    post "/password_reset_emails" => forgot_password,
+    post "/password_resets" => reset_password,
+    patch "/users/{id}" => update_user,
+    put "/users/{id}/role" => set_user_role,
+    get "/roles" => get_roles,
+    delete "/users/{id}" => delete_user,
+    post "/board_games" => create_board_game,
+    put "/board_games/{id}" => update_board_game,
+    get "/board_games/{id}" => get_board_game,
+    get "/board_games" => search_board_games,
+    get "/inventory" => get_board_games_admin,
+    get "/board_games/{id}/availability" => get_board_game_availability,
+    delete "/board_games/{id}" => delete_board_game,
+    post "/copies" => create_game_copy,
+    put "/copies/{id}" => update_game_copy,
+    get "/board_games/{id}/copies" => get_game_copies,
+    delete "/copies/{id}" => delete_game_copy,
+    get "/categories" => get_categories,
+    post "/categories" => create_category,
+    put "/categories/{id}" => update_category,
+    delete "/categories/{id}" => delete_category,
+    put "/board_games/{id}/categories" => set_board_game_categories,
+    get "/tags" => get_tags,
+    post "/tags" => create_tag,
+    put "/tags/{id}" => update_tag,
+    delete "/tags/{id}" => delete_tag,
+    put "/board_games/{id}/tags" => set_board_game_tags,
+    post "/rentals" => create_rental,
+    put "/rentals/{id}" => update_rental,
+    get "/rentals" => get_rentals,
+    get "/me/rentals" => get_my_rentals,
+    get "/users/{id}/rentals" => get_user_rentals,
+    delete "/rentals/{id}" => archive_rental,
+    post "/rentals/{id}/pick_up" => pick_up_rental,
+    post "/rentals/{id}/return" => return_rental,
+    get "/history" => get_rental_history,
+    get "/me/history" => get_my_rental_history,
+    get "/users/{id}/history" => get_user_rental_history,
+    delete "/history/{id}" => delete_rental_history,
+    put "/rentals/{id}/extension" => save_extension_request,
+    post "/rentals/{id}/extension/accept" => accept_extension_request,
+    delete "/rentals/{id}/extension" => delete_extension_request,
+    put "/me/favourites/{id}" => add_favourite,
+    delete "/me/favourites/{id}" => remove_favourite,
+    post "/me/waitlist/{id}" => join_waitlist,
+    delete "/me/waitlist/{id}" => leave_waitlist,
+    get "/me/waitlist" => get_my_waitlist,
+    get "/me/waitlist/{id}" => get_waitlist_position,
+);
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     // Load all the necessary resources.
     dotenv().ok();
+
+    // One-off command creating the resized variants of images uploaded before they were introduced.
+    if std::env::args().nth(1).as_deref() == Some("generate-thumbnails") {
+        let db =
+            DatabaseManager::new(&std::env::var("DATABASE_URL").expect("DATABASE_URL is not set"))
+                .await
+                .expect("Failed to initialize database");
+        generate_missing_variants(&db, media_store_from_env())
+            .await
+            .expect("Failed to read board games from the database");
+        return Ok(());
+    }
+
     for var in REQUIRED_ENV_VARS.iter() {
         if std::env::var(var).is_err() {
             panic!("{} is not set", var);
@@ -174,7 +539,32 @@ async fn main() -> std::io::Result<()> {
         .await
         .expect("Failed to initialize database");
 
-    let state = AppState { db };
+    let config = Config::from_env();
+    let mailer = mailer_from_env();
+    let media = media_store_from_env();
+
+    Scheduler::new(db.clone(), mailer.clone(), Arc::new(SystemClock), &config).start();
+
+    let rate_limiter = RateLimiter::new(Arc::new(MemoryRateLimitStore::default()), &config);
+
+    let state = AppState {
+        db,
+        mailer,
+        media,
+        rate_limiter,
+        config,
+    };
+
+    let openapi = vec![
+        (
+            Url::with_primary("v2", "/api/v2/openapi.json", true),
+            ApiDoc::openapi(),
+        ),
+        (
+            Url::new("v1 (deprecated)", "/api/openapi.json"),
+            api_v1::ApiDoc::openapi(),
+        ),
+    ];
 
     HttpServer::new(move || {
         let cors = Cors::default() // TODO: make it more secure
@@ -184,42 +574,49 @@ async fn main() -> std::io::Result<()> {
             .max_age(3600);
 
         App::new()
+            .wrap(ErrorHandlers::new().default_handler(localize_error))
             .wrap(cors)
             .app_data(Data::new(state.clone()))
-            .service(Files::new("/static", "./static/img"))
+            .app_data(
+                FormConfig::default()
+                    .error_handler(|err, _| ApiError::InvalidRequest(err.to_string()).into()),
+            )
+            .app_data(
+                JsonConfig::default()
+                    .error_handler(|err, _| ApiError::InvalidRequest(err.to_string()).into()),
+            )
+            .app_data(
+                PathConfig::default()
+                    .error_handler(|err, _| ApiError::InvalidRequest(err.to_string()).into()),
+            )
+            .app_data(
+                QueryConfig::default()
+                    .error_handler(|err, _| ApiError::InvalidRequest(err.to_string()).into()),
+            )
+            .app_data(
+                MultipartFormConfig::default().error_handler(|err, _| match err {
+                    MultipartError::Payload(PayloadError::Overflow) => {
+                        ApiError::ImageTooLarge.into()
+                    }
+                    err => ApiError::InvalidRequest(err.to_string()).into(),
+                }),
+            )
+            .configure(|cfg| {
+                // Files kept elsewhere are served by the storage itself.
+                if let Some(dir) = state.media.local_dir() {
+                    cfg.service(Files::new(LOCAL_MEDIA_URL, dir));
+                }
+            })
             .service(index_test)
             .service(index_login)
             .service(index_register)
             .service(index_board_game)
+            .service(SwaggerUi::new("/api/docs/{_:.*}").urls(openapi.clone()))
+            .service(web::scope("/api/v2").configure(configure_api))
             .service(
                 web::scope("/api")
-                    .service(login)
-                    .service(register)
-                    .service(confirm_user)
-                    .service(get_user)
-                    .service(get_users)
-                    .service(is_penalized)
-                    .service(change_password)
-                    .service(update_user)
-                    .service(delete_user)
-                    .service(save_board_game)
-                    .service(get_board_game)
-                    .service(get_board_games)
-                    .service(get_board_games_admin)
-                    .service(delete_board_game)
-                    .service(save_rental)
-                    .service(get_rentals)
-                    .service(get_my_rentals)
-                    .service(get_user_rentals)
-                    .service(archive_rental)
-                    .service(get_rental_history)
-                    .service(get_my_rental_history)
-                    .service(get_user_rental_history)
-                    .service(delete_rental_history)
-                    .service(save_extension_request)
-                    .service(accept_extension_request)
-                    .service(delete_extension_request)
-                    .service(change_favourite),
+                    .wrap(api_v1::deprecation_headers())
+                    .configure(api_v1::configure),
             )
     })
     .bind(("127.0.0.1", 8080))?
@@ -255,611 +652,3120 @@ async fn index_board_game(_req: HttpRequest) -> actix_web::Result<NamedFile> {
     Ok(NamedFile::open(path)?)
 }
 
-#[post("/user/login")]
-async fn login(form: Form<LoginFormData>, data: Data<AppState>) -> HttpResponse {
-    match data.db.get_user(form.id).await {
-        Ok(Some(user)) => {
-            if user.confirmation_token.is_some() {
-                build_error_response(
-                    StatusCode::UNAUTHORIZED,
-                    "Konto nie zostało jeszcze aktywowane",
-                )
-            } else {
-                match verify_password(form.password.clone(), user.password_hash) {
-                    Ok(true) => match generate_jwt(user.id, user.is_admin) {
-                        Ok(token) => HttpResponse::Ok().json(LoginResponse { jwt_token: token }),
-                        Err(_) => HttpResponse::InternalServerError().finish(), // Failed to generate JWT token
-                    },
-                    Ok(false) => build_error_response(
-                        StatusCode::UNAUTHORIZED,
-                        "Nieprawidłowe dane logowania", // Invalid password
-                    ),
-                    Err(_) => HttpResponse::InternalServerError().finish(), // Failed to verify password
-                }
-            }
+#[utoipa::path(
+    post,
+    path = "/sessions",
+    tag = "auth",
+    responses(
+        (status = 200, description = "Session tokens, or a `TwoFactorChallengeResponse` if the user has 2FA on", body = LoginResponse),
+        (status = 401, description = "Invalid credentials or unconfirmed account", body = ErrorResponse),
+        (status = 429, description = "Too many attempts or locked account", body = ErrorResponse),
+    )
+)]
+async fn login(
+    req: HttpRequest,
+    form: Json<LoginFormData>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let ip = client_ip(&req, &data.config.trusted_proxies);
+    check_login_limits(&data, form.id, &ip)?;
+
+    let user = match data.db.get_user(form.id).await? {
+        Some(user) => user,
+        None => {
+            // User doesn't exist
+            record_failed_login(&data, form.id, &ip);
+            return Err(ApiError::InvalidCredentials);
         }
-        Ok(None) => build_error_response(
-            StatusCode::UNAUTHORIZED,
-            "Nieprawidłowe dane logowania", // User doesn't exist
-        ),
-        Err(_) => HttpResponse::InternalServerError().finish(), // Failed to get user data from the database
+    };
+    if user.confirmation_token.is_some() {
+        return Err(ApiError::AccountNotConfirmed);
     }
+    if !verify_password(form.password.clone(), user.password_hash.clone())? {
+        record_failed_login(&data, user.id, &ip);
+        return Err(ApiError::InvalidCredentials);
+    }
+
+    // With 2FA on, the session is started only after the code is given.
+    if user.totp_enabled {
+        return Ok(HttpResponse::Ok().json(TwoFactorChallengeResponse {
+            two_factor_required: true,
+            challenge_token: generate_challenge_token(user.id, form.remember_me)?,
+        }));
+    }
+    data.rate_limiter.record_successful_login(user.id);
+    start_session(&data.db, &user, form.remember_me).await
 }
 
-#[post("/user/register")]
-async fn register(form: Form<RegisterFormData>, data: Data<AppState>) -> HttpResponse {
-    let password_hash = match hash_password(form.password.clone()) {
-        Ok(hash) => hash,
-        Err(_) => return HttpResponse::InternalServerError().finish(), // Failed to hash password
+/// Returns the IP address of the client, used for rate limiting.
+/// Requests from trusted proxies are attributed to the last address they were forwarded for
+/// that isn't a trusted proxy itself, as anything before it could have been made up by the client.
+fn client_ip(req: &HttpRequest, trusted_proxies: &[IpAddr]) -> String {
+    let peer = match req.peer_addr() {
+        Some(addr) => addr.ip(),
+        None => return String::new(),
     };
+    if !trusted_proxies.contains(&peer) {
+        return peer.to_string();
+    }
 
-    let uuid = Uuid::new_v4();
-    let user = UserActiveModel {
-        id: Set(form.id),
-        name: Set(form.name.clone()),
-        surname: Set(form.surname.clone()),
-        email: Set(form.email.clone()),
-        password_hash: Set(password_hash),
-        // TODO: fix emails
-        // confirmation_token: Set(Some(uuid)),
-        confirmation_token: Set(None),
-        ..Default::default()
-    };
+    forwarded_addresses(req)
+        .into_iter()
+        .rev()
+        .find(|addr| {
+            !addr
+                .parse::<IpAddr>()
+                .is_ok_and(|ip| trusted_proxies.contains(&ip))
+        })
+        .unwrap_or_else(|| peer.to_string())
+}
 
-    match data.db.insert_user(user).await {
-        // TODO: fix emails
-        // Ok(_) => match send_confirmation_email(form.id, form.email.clone(), uuid) {
-        //     Ok(_) => HttpResponse::Ok().body("User registered"),
-        //     Err(e) => {
-        //         HttpResponse::InternalServerError().body(format!("Failed to send email: {}", e))
-        //     }
-        // },
-        Ok(_) => HttpResponse::Ok().finish(),
-        // TODO: Err(_) might mean that user already exists
-        Err(_) => HttpResponse::InternalServerError().finish(), // Failed to save user data into the database
-    }
-}
-
-// TODO: change server responses here
-#[get("/user/confirm/{id}/{token}")]
-async fn confirm_user(path: web::Path<(i32, Uuid)>, data: Data<AppState>) -> HttpResponse {
-    let (id, token) = path.into_inner();
-    let user = match data.db.get_user(id).await {
-        Ok(Some(user)) => user,
-        Ok(None) => return HttpResponse::NotFound().body("User not found"),
-        Err(_) => {
-            return HttpResponse::InternalServerError()
-                .body("Failed to get user data from database")
-        }
+/// Lists the addresses the request was forwarded for, from the client to the last proxy.
+/// The standard `Forwarded` header is used if present, `X-Forwarded-For` otherwise.
+fn forwarded_addresses(req: &HttpRequest) -> Vec<String> {
+    let header_values = |name| {
+        req.headers()
+            .get_all(name)
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .collect::<Vec<_>>()
     };
 
-    if user.confirmation_token != Some(token) {
-        return HttpResponse::Unauthorized().body("Invalid token");
+    let forwarded: Vec<String> = header_values(header::FORWARDED)
+        .into_iter()
+        .filter_map(|element| {
+            element.split(';').find_map(|pair| {
+                let (name, value) = pair.trim().split_once('=')?;
+                name.eq_ignore_ascii_case("for")
+                    .then(|| strip_port(value.trim_matches('"')))
+            })
+        })
+        .collect();
+    if !forwarded.is_empty() {
+        return forwarded;
     }
+    header_values(header::X_FORWARDED_FOR)
+        .into_iter()
+        .map(strip_port)
+        .collect()
+}
 
-    let user = UserActiveModel {
-        id: Set(id),
-        confirmation_token: Set(None),
-        ..Default::default()
-    };
-
-    match data.db.update_user(user).await {
-        Ok(_) => HttpResponse::Ok().body("User confirmed"),
-        Err(_) => {
-            HttpResponse::InternalServerError().body("Failed to save user data into database")
-        }
+/// Removes the port from an address like `192.0.2.1:4711` or `[2001:db8::1]:4711`.
+fn strip_port(addr: &str) -> String {
+    let addr = addr.trim();
+    if let Some(ipv6) = addr.strip_prefix('[') {
+        return ipv6.split(']').next().unwrap_or_default().to_string();
+    }
+    match addr.split_once(':') {
+        // More colons mean an IPv6 address without a port.
+        Some((ipv4, port)) if !port.contains(':') => ipv4.to_string(),
+        _ => addr.to_string(),
     }
 }
 
-#[get("/user/get/{id}")]
-async fn get_user(
-    id: web::Path<i32>,
-    Auth(user): Auth<HAS_TOKEN>,
-    data: Data<AppState>,
-) -> HttpResponse {
-    // Non-admin user can only check info about themselves.
-    let id = id.into_inner();
-    if let Err(response) = is_self_request(&user, id) {
-        return response;
+/// Checks whether the client can try to log in to the given account,
+/// counting the attempt towards the limit of its IP address.
+fn check_login_limits(data: &AppState, user_id: i32, ip: &str) -> Result<(), ApiError> {
+    let now = Utc::now();
+    if let Err(retry_at) = data.rate_limiter.check_ip(LimitedAction::Login, ip, now) {
+        eprintln!("Too many login attempts from {}", ip);
+        return Err(ApiError::TooManyLoginAttempts { retry_at });
+    }
+    if let Some(retry_at) = data.rate_limiter.locked_until(user_id, now) {
+        eprintln!("Login attempt to locked account {} from {}", user_id, ip);
+        return Err(ApiError::AccountLocked { retry_at });
     }
+    Ok(())
+}
 
-    match data.db.get_user(id).await {
-        Ok(Some(user)) => HttpResponse::Ok().json(user),
-        Ok(None) => HttpResponse::NotFound().body("User not found"),
-        Err(_) => HttpResponse::InternalServerError().body("Failed to get user data from database"),
+/// Counts and logs a failed login to the given account, locking it after too many.
+fn record_failed_login(data: &AppState, user_id: i32, ip: &str) {
+    eprintln!("Failed login to account {} from {}", user_id, ip);
+    if data.rate_limiter.record_failed_login(user_id, Utc::now()) {
+        eprintln!("Account {} locked after too many failed logins", user_id);
     }
 }
 
-#[get("/user/get_all")]
-async fn get_users(Auth(_user): Auth<HAS_ADMIN_TOKEN>, data: Data<AppState>) -> HttpResponse {
-    match data.db.get_users().await {
-        Ok(users) => HttpResponse::Ok().json(users),
-        Err(_) => {
-            HttpResponse::InternalServerError().body("Failed to get users data from database")
-        }
+/// Starts a new session for the user and responds with its tokens.
+async fn start_session(
+    db: &DatabaseManager,
+    user: &UserModel,
+    remember_me: bool,
+) -> Result<HttpResponse, ApiError> {
+    let secret = generate_token();
+    let now = Utc::now().naive_utc();
+    let session = SessionActiveModel {
+        user_id: Set(user.id),
+        token_hash: Set(hash_token(&secret)),
+        remember_me: Set(remember_me),
+        created_at: Set(now),
+        last_used_at: Set(now),
+        expires_at: Set(now + session_duration(remember_me)),
+        ..Default::default()
+    };
+
+    let session = db.insert_session(session).await?;
+    build_login_response(user, session.id, &secret)
+}
+
+fn session_duration(remember_me: bool) -> Duration {
+    if remember_me {
+        Duration::days(REMEMBER_ME_SESSION_EXPIRY_TIME)
+    } else {
+        Duration::days(SESSION_EXPIRY_TIME)
     }
 }
 
-#[get("/user/is_penalized/{id}")]
-async fn is_penalized(
-    id: web::Path<i32>,
-    Auth(user): Auth<HAS_TOKEN>,
+fn build_login_response(
+    user: &UserModel,
+    session_id: i32,
+    secret: &str,
+) -> Result<HttpResponse, ApiError> {
+    Ok(HttpResponse::Ok().json(LoginResponse {
+        jwt_token: generate_jwt(user.id, user.token_version, session_id)?,
+        refresh_token: build_refresh_token(session_id, secret),
+    }))
+}
+
+/// Exchanges a refresh token for a new access token and a new refresh token.
+/// Every refresh token can be used only once. Using it again means that it has leaked,
+/// so the whole session is revoked.
+#[utoipa::path(
+    post,
+    path = "/sessions/refresh",
+    tag = "auth",
+    responses(
+        (status = 200, description = "New session tokens", body = LoginResponse),
+        (status = 401, description = "Expired, revoked or reused refresh token", body = ErrorResponse),
+    )
+)]
+async fn refresh(
+    form: Json<RefreshTokenFormData>,
     data: Data<AppState>,
-) -> HttpResponse {
-    // Non-admin user can only check info about themselves.
-    let id = id.into_inner();
-    if let Err(response) = is_self_request(&user, id) {
-        return response;
+) -> Result<HttpResponse, ApiError> {
+    let (session_id, secret) =
+        parse_refresh_token(&form.refresh_token).ok_or(ApiError::SessionExpired)?;
+    let session = data
+        .db
+        .get_session(session_id)
+        .await?
+        .ok_or(ApiError::SessionExpired)?;
+
+    let now = Utc::now().naive_utc();
+    if session.revoked_at.is_some() || session.expires_at <= now {
+        return Err(ApiError::SessionExpired);
+    }
+    let token_hash = hash_token(secret);
+    if session.token_hash != token_hash {
+        // An already rotated token is being reused.
+        data.db.revoke_session(session.id, now).await?;
+        return Err(ApiError::SessionExpired);
     }
 
-    match data.db.is_user_penalized(id).await {
-        Ok(res) => HttpResponse::Ok().json(res),
-        Err(_) => HttpResponse::InternalServerError().body("Failed to get user data from database"),
+    let user = data
+        .db
+        .get_user(session.user_id)
+        .await?
+        .ok_or(ApiError::SessionExpired)?;
+
+    let new_secret = generate_token();
+    let expires_at = now + session_duration(session.remember_me);
+    let rotated = data
+        .db
+        .rotate_session(
+            session.id,
+            &token_hash,
+            hash_token(&new_secret),
+            now,
+            expires_at,
+        )
+        .await?;
+    if !rotated {
+        return Err(ApiError::SessionExpired); // The token has been rotated in the meantime
     }
+    build_login_response(&user, session.id, &new_secret)
 }
 
-#[post("/user/change_password/{id}")]
-async fn change_password(
-    id: web::Path<i32>,
-    form: Form<ChangePasswordFormData>,
-    Auth(user): Auth<HAS_TOKEN>,
+/// Ends the session of the given refresh token.
+#[utoipa::path(
+    delete,
+    path = "/sessions",
+    tag = "auth",
+    responses(
+        (status = 200, description = "Session ended"),
+    )
+)]
+async fn logout(
+    form: Json<RefreshTokenFormData>,
     data: Data<AppState>,
-) -> HttpResponse {
-    // Non-admin user can only change their own password.
-    let id = id.into_inner();
-    if let Err(response) = is_self_request(&user, id) {
-        return response;
-    }
-
-    let password_hash = match hash_password(form.password.clone()) {
-        Ok(hash) => hash,
-        Err(_) => return HttpResponse::InternalServerError().body("Failed to hash password"),
+) -> Result<HttpResponse, ApiError> {
+    let (session_id, secret) = match parse_refresh_token(&form.refresh_token) {
+        Some(token) => token,
+        None => return Ok(HttpResponse::Ok().finish()),
     };
-
-    let user = UserActiveModel {
-        id: Set(id),
-        password_hash: Set(password_hash),
-        ..Default::default()
+    let session = match data.db.get_session(session_id).await? {
+        Some(session) if session.token_hash == hash_token(secret) => session,
+        _ => return Ok(HttpResponse::Ok().finish()),
     };
 
-    match data.db.update_user(user).await {
-        Ok(_) => HttpResponse::Ok().finish(),
-        Err(_) => {
-            HttpResponse::InternalServerError().body("Failed to save user data into database")
-        }
-    }
+    data.db
+        .revoke_session(session.id, Utc::now().naive_utc())
+        .await?;
+    Ok(HttpResponse::Ok().finish())
 }
 
-#[post("/user/update/{id}")]
-async fn update_user(
-    id: web::Path<i32>,
-    form: Form<UpdateUserFormData>,
-    Auth(_user): Auth<HAS_ADMIN_TOKEN>,
+/// Finishes logging in with 2FA on, exchanging the challenge token from `login`
+/// and a code from the authenticator (or a recovery code) for a session.
+#[utoipa::path(
+    post,
+    path = "/sessions/2fa",
+    tag = "auth",
+    responses(
+        (status = 200, description = "Session tokens", body = LoginResponse),
+        (status = 401, description = "Expired challenge or invalid code", body = ErrorResponse),
+        (status = 429, description = "Too many attempts or locked account", body = ErrorResponse),
+    )
+)]
+async fn login_two_factor(
+    req: HttpRequest,
+    form: Json<TwoFactorLoginFormData>,
     data: Data<AppState>,
-) -> HttpResponse {
-    let id = id.into_inner();
-
-    let password_hash = if !form.password.is_empty() {
-        NotSet
-    } else if let Ok(hash) = hash_password(form.password.clone()) {
-        Set(hash)
-    } else {
-        return HttpResponse::InternalServerError().body("Failed to hash password");
+) -> Result<HttpResponse, ApiError> {
+    let claims = verify_challenge_token(&form.challenge_token)
+        .map_err(|_| ApiError::LoginChallengeExpired)?;
+    let user = match data.db.get_user(claims.sub).await? {
+        Some(user) if user.totp_enabled => user,
+        _ => return Err(ApiError::LoginChallengeExpired),
     };
 
-    let user = UserActiveModel {
-        id: Set(id),
-        name: Set(form.name.clone()),
-        surname: Set(form.surname.clone()),
-        email: Set(form.email.clone()),
-        password_hash,
-        penalty_points: Set(form.penalty_points),
-        is_admin: Set(form.is_admin),
-        ..Default::default()
-    };
+    let ip = client_ip(&req, &data.config.trusted_proxies);
+    check_login_limits(&data, user.id, &ip)?;
 
-    match data.db.update_user(user).await {
-        Ok(_) => HttpResponse::Ok().finish(),
-        Err(_) => {
-            HttpResponse::InternalServerError().body("Failed to save user data into database")
-        }
+    if !verify_two_factor_code(&data.db, &user, &form.code).await? {
+        record_failed_login(&data, user.id, &ip);
+        return Err(ApiError::InvalidTwoFactorCode);
     }
+    data.rate_limiter.record_successful_login(user.id);
+    start_session(&data.db, &user, claims.remember_me).await
 }
 
-#[get("/user/delete/{id}")]
-async fn delete_user(
-    id: web::Path<i32>,
-    Auth(_user): Auth<HAS_ADMIN_TOKEN>,
-    data: Data<AppState>,
-) -> HttpResponse {
-    let id = id.into_inner();
-    match data.db.delete_user(id).await {
-        Ok(_) => HttpResponse::Ok().finish(),
-        Err(_) => HttpResponse::InternalServerError().body("Failed to delete user from database"),
+/// Checks a 2FA code of the user, either from their authenticator or one of their recovery codes.
+/// Every code can be used only once.
+async fn verify_two_factor_code(
+    db: &DatabaseManager,
+    user: &UserModel,
+    code: &str,
+) -> Result<bool, DbErr> {
+    let secret = match &user.totp_secret {
+        Some(secret) => secret,
+        None => return Ok(false),
+    };
+    if let Some(step) = verify_totp(secret, code, Utc::now().timestamp()) {
+        return db.claim_totp_step(user.id, step).await;
     }
+    db.use_recovery_code(user.id, &hash_token(code.trim()), Utc::now().naive_utc())
+        .await
 }
 
-/// id = 0 ==> insert a new board game
-#[post("/board_game/save/{id}")]
-async fn save_board_game(
-    id: web::Path<i32>,
-    MultipartForm(form): MultipartForm<BoardGameFormData>,
-    // TODO: uncomment this
-    // Auth(_user): Auth<HAS_ADMIN_TOKEN>,
+/// Generates a new TOTP secret for the user, to be added to their authenticator app.
+/// 2FA is turned on only after the first code is confirmed with `enable_two_factor`.
+#[utoipa::path(
+    post,
+    path = "/me/2fa/setup",
+    tag = "auth",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "New TOTP secret", body = TwoFactorSetupResponse),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 409, description = "2FA is already on", body = ErrorResponse),
+    )
+)]
+async fn setup_two_factor(
+    Auth(user, _): Auth<HasToken>,
     data: Data<AppState>,
-) -> HttpResponse {
-    let file_name = match form.image.file_name {
-        Some(name) => name,
-        None => return HttpResponse::BadRequest().body("File name is missing"),
-    };
-
-    // Save the image on the server.
-    let path = format!("./static/img/{}", file_name);
-    if form.image.file.persist(path).is_err() {
-        return HttpResponse::InternalServerError().body("Failed to save file");
+) -> Result<HttpResponse, ApiError> {
+    let current_user = data
+        .db
+        .get_user(user.sub)
+        .await?
+        .ok_or(ApiError::UserNotFound)?;
+    if current_user.totp_enabled {
+        return Err(ApiError::TwoFactorAlreadyEnabled);
     }
 
-    let id = id.into_inner();
-    let additional_info = form.additional_info.into_inner();
-    let additional_info = if additional_info.is_empty() {
-        None
-    } else {
-        Some(additional_info)
-    };
-    let board_game = BoardGameActiveModel {
-        id: if id == 0 { NotSet } else { Set(id) },
-        title: Set(form.title.into_inner()),
-        weight: Set(form.weight.into_inner()),
-        photo_filename: Set(file_name),
-        min_players: Set(form.min_players.into_inner()),
-        max_players: Set(form.max_players.into_inner()),
-        min_playtime: Set(form.min_playtime.into_inner()),
-        max_playtime: Set(form.max_playtime.into_inner()),
-        additional_info: Set(additional_info),
+    let secret = generate_totp_secret();
+    let updated_user = UserActiveModel {
+        id: Set(user.sub),
+        totp_secret: Set(Some(secret.clone())),
+        ..Default::default()
     };
-
-    match data.db.save_board_game(board_game).await {
-        Ok(_) => HttpResponse::Ok().finish(),
-        Err(_) => {
-            HttpResponse::InternalServerError().body("Failed to save board game into database")
-        }
-    }
+    data.db.update_user(updated_user).await?;
+    Ok(HttpResponse::Ok().json(TwoFactorSetupResponse {
+        otpauth_uri: totp_uri(&secret, user.sub),
+        secret,
+    }))
 }
 
-#[get("/board_game/get/{id}")]
-async fn get_board_game(
-    id: web::Path<i32>,
-    Auth(_user): Auth<HAS_TOKEN>,
+/// Turns on 2FA after confirming a code generated from the secret from `setup_two_factor`.
+/// Responds with recovery codes, which are shown to the user only once.
+#[utoipa::path(
+    post,
+    path = "/me/2fa",
+    tag = "auth",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Recovery codes", body = RecoveryCodesResponse),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 401, description = "Invalid token or code", body = ErrorResponse),
+        (status = 409, description = "2FA is already on", body = ErrorResponse),
+    )
+)]
+async fn enable_two_factor(
+    form: Json<TwoFactorCodeFormData>,
+    Auth(user, _): Auth<HasToken>,
     data: Data<AppState>,
-) -> HttpResponse {
-    let id = id.into_inner();
-    match data.db.get_board_game(id).await {
-        Ok(Some(board_game)) => HttpResponse::Ok().json(board_game),
-        Ok(None) => HttpResponse::NotFound().body("Board game not found"),
-        Err(_) => {
-            HttpResponse::InternalServerError().body("Failed to get board game data from database")
-        }
+) -> Result<HttpResponse, ApiError> {
+    let user = data
+        .db
+        .get_user(user.sub)
+        .await?
+        .ok_or(ApiError::UserNotFound)?;
+    if user.totp_enabled {
+        return Err(ApiError::TwoFactorAlreadyEnabled);
     }
+    let secret = user
+        .totp_secret
+        .as_ref()
+        .ok_or(ApiError::TwoFactorNotSetUp)?;
+    let step = verify_totp(secret, &form.code, Utc::now().timestamp())
+        .ok_or(ApiError::InvalidTwoFactorCode)?;
+
+    let recovery_codes = generate_recovery_codes();
+    let recovery_code_hashes = recovery_codes.iter().map(|code| hash_token(code)).collect();
+    data.db
+        .enable_two_factor(user.id, step, recovery_code_hashes)
+        .await?;
+    Ok(HttpResponse::Ok().json(RecoveryCodesResponse { recovery_codes }))
 }
 
-#[get("/board_game/get_all")]
-async fn get_board_games(Auth(user): Auth<HAS_TOKEN>, data: Data<AppState>) -> HttpResponse {
-    match data.db.get_board_games(user.sub).await {
-        Ok(board_games) => HttpResponse::Ok().json(board_games),
-        Err(_) => {
-            HttpResponse::InternalServerError().finish() // Failed to get board games data from the database
-        }
+/// Turns off 2FA, which has to be confirmed with a current code.
+#[utoipa::path(
+    delete,
+    path = "/me/2fa",
+    tag = "auth",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "2FA turned off"),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 401, description = "Invalid token or code", body = ErrorResponse),
+    )
+)]
+async fn disable_two_factor(
+    form: Json<TwoFactorCodeFormData>,
+    Auth(user, _): Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_two_factor_user(&data.db, user.sub).await?;
+    if !verify_two_factor_code(&data.db, &user, &form.code).await? {
+        return Err(ApiError::InvalidTwoFactorCode);
     }
+
+    data.db.disable_two_factor(user.id).await?;
+    Ok(HttpResponse::Ok().finish())
 }
 
-#[get("/board_game/get_all_admin")]
-async fn get_board_games_admin(
-    Auth(_user): Auth<HAS_ADMIN_TOKEN>,
+/// Replaces the user's recovery codes with new ones, which has to be confirmed with a current code.
+#[utoipa::path(
+    post,
+    path = "/me/2fa/recovery_codes",
+    tag = "auth",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "New recovery codes", body = RecoveryCodesResponse),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 401, description = "Invalid token or code", body = ErrorResponse),
+    )
+)]
+async fn regenerate_recovery_codes(
+    form: Json<TwoFactorCodeFormData>,
+    Auth(user, _): Auth<HasToken>,
     data: Data<AppState>,
-) -> HttpResponse {
-    match data.db.get_board_games_admin().await {
-        Ok(board_games) => HttpResponse::Ok().json(board_games),
-        Err(_) => {
-            HttpResponse::InternalServerError().body("Failed to get board games data from database")
-        }
+) -> Result<HttpResponse, ApiError> {
+    let user = get_two_factor_user(&data.db, user.sub).await?;
+    if !verify_two_factor_code(&data.db, &user, &form.code).await? {
+        return Err(ApiError::InvalidTwoFactorCode);
     }
+
+    let recovery_codes = generate_recovery_codes();
+    let recovery_code_hashes = recovery_codes.iter().map(|code| hash_token(code)).collect();
+    data.db
+        .save_recovery_codes(user.id, recovery_code_hashes)
+        .await?;
+    Ok(HttpResponse::Ok().json(RecoveryCodesResponse { recovery_codes }))
 }
 
-#[get("/board_game/delete/{id}")]
-async fn delete_board_game(
+/// Turns off 2FA of the given user, e.g. when they lost both their authenticator and recovery codes.
+#[utoipa::path(
+    delete,
+    path = "/users/{id}/2fa",
+    tag = "users",
+    security(("bearer_auth" = ["manage_users"])),
+    responses(
+        (status = 200, description = "2FA turned off"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+        (status = 404, description = "User not found", body = ErrorResponse),
+    )
+)]
+async fn reset_two_factor(
     id: web::Path<i32>,
-    Auth(_user): Auth<HAS_ADMIN_TOKEN>,
+    Auth(user, _): Auth<ManageUsers>,
     data: Data<AppState>,
-) -> HttpResponse {
+) -> Result<HttpResponse, ApiError> {
     let id = id.into_inner();
-    match data.db.delete_board_game(id).await {
-        Ok(_) => HttpResponse::Ok().finish(),
-        Err(_) => {
-            HttpResponse::InternalServerError().body("Failed to delete board game from database")
-        }
-    }
+    check_can_manage_user(&data.db, &user, id).await?;
+
+    data.db.disable_two_factor(id).await?;
+    Ok(HttpResponse::Ok().finish())
 }
 
-/// id = 0 ==> insert new rental
-#[post("/rental/save/{id}")]
-async fn save_rental(
+/// Unlocks the account of the given user, locked after too many failed logins.
+#[utoipa::path(
+    delete,
+    path = "/users/{id}/lock",
+    tag = "users",
+    security(("bearer_auth" = ["manage_users"])),
+    responses(
+        (status = 200, description = "Account unlocked"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+async fn unlock_user(
     id: web::Path<i32>,
-    form: Form<RentalFormData>,
-    Auth(user): Auth<HAS_TOKEN>,
+    Auth(user, _): Auth<ManageUsers>,
     data: Data<AppState>,
 ) -> HttpResponse {
-    // TODO: users can only add or update their own rentals
-
     let id = id.into_inner();
-    let rental_date = match Date::parse_from_str(form.rental_date.as_str(), "%d.%m.%Y") {
-        Ok(date) => date,
-        Err(_) => return build_error_response(StatusCode::BAD_REQUEST, "Niepoprawny format daty"),
-    };
-    let return_date = match Date::parse_from_str(form.return_date.as_str(), "%d.%m.%Y") {
-        Ok(date) => date,
-        Err(_) => return build_error_response(StatusCode::BAD_REQUEST, "Niepoprawny format daty"),
-    };
-
-    let rental = RentalActiveModel {
-        id: if id == 0 { NotSet } else { Set(id) },
-        game_id: Set(form.game_id),
-        user_id: Set(user.sub), // TODO: is it okay?
-        rental_date: Set(rental_date),
-        return_date: Set(return_date),
-        extension_date: Set(None),
-        ..Default::default()
-    };
+    data.rate_limiter.unlock_account(id);
+    eprintln!("Account {} unlocked by admin {}", id, user.sub);
+    HttpResponse::Ok().finish()
+}
 
-    match data.db.save_rental(rental).await {
-        Ok(_) => HttpResponse::Ok().finish(),
-        Err(_) => HttpResponse::InternalServerError().finish(), // Failed to save rental into the database
+/// Retrieves the user of the given ID, provided that they have 2FA turned on.
+async fn get_two_factor_user(db: &DatabaseManager, id: i32) -> Result<UserModel, ApiError> {
+    match db.get_user(id).await? {
+        Some(user) if user.totp_enabled => Ok(user),
+        Some(_) => Err(ApiError::TwoFactorNotEnabled),
+        None => Err(ApiError::UserNotFound),
     }
 }
 
-#[get("/rental/get_all")]
-async fn get_rentals(Auth(_user): Auth<HAS_ADMIN_TOKEN>, data: Data<AppState>) -> HttpResponse {
-    match data.db.get_rentals().await {
-        Ok(rentals) => HttpResponse::Ok().json(rentals),
-        Err(_) => HttpResponse::InternalServerError().body("Failed to get rentals from database"),
+#[utoipa::path(
+    post,
+    path = "/users",
+    tag = "auth",
+    responses(
+        (status = 200, description = "User registered", body = String, content_type = "text/plain"),
+        (status = 422, description = "Invalid form fields", body = ErrorResponse),
+        (status = 429, description = "Too many registrations", body = ErrorResponse),
+    )
+)]
+async fn register(
+    req: HttpRequest,
+    form: Json<RegisterFormData>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let ip = client_ip(&req, &data.config.trusted_proxies);
+    if let Err(retry_at) = data
+        .rate_limiter
+        .check_ip(LimitedAction::Register, &ip, Utc::now())
+    {
+        eprintln!("Too many registrations from {}", ip);
+        return Err(ApiError::TooManyRegistrations { retry_at });
     }
-}
 
-#[get("/rental/get")]
-async fn get_my_rentals(Auth(user): Auth<HAS_TOKEN>, data: Data<AppState>) -> HttpResponse {
-    match data.db.get_user_rentals(user.sub).await {
-        Ok(rentals) => HttpResponse::Ok().json(rentals),
-        Err(_) => HttpResponse::InternalServerError().finish(), // Failed to get rentals from the database
+    let name = form.name.trim();
+    let surname = form.surname.trim();
+    let email = form.email.trim();
+    let errors = validate_registration(form.id, name, surname, email, &form.password);
+    if !errors.is_empty() {
+        return Err(ApiError::InvalidFields(errors));
+    }
+    let errors = check_registration_taken(&data.db, form.id, email).await?;
+    if !errors.is_empty() {
+        return Err(ApiError::InvalidFields(errors));
     }
-}
 
-#[get("/rental/get/{id}")]
-async fn get_user_rentals(
-    id: web::Path<i32>,
-    Auth(_user): Auth<HAS_ADMIN_TOKEN>,
-    data: Data<AppState>,
-) -> HttpResponse {
-    let id = id.into_inner();
-    match data.db.get_user_rentals_admin(id).await {
-        Ok(rentals) => HttpResponse::Ok().json(rentals),
-        Err(_) => HttpResponse::InternalServerError().body("Failed to get rentals from database"),
+    let password_hash = hash_password(form.password.clone())?;
+    let uuid = Uuid::new_v4();
+    let user = UserActiveModel {
+        id: Set(form.id),
+        name: Set(name.to_string()),
+        surname: Set(surname.to_string()),
+        email: Set(email.to_string()),
+        password_hash: Set(password_hash),
+        confirmation_token: Set(Some(uuid)),
+        confirmation_sent_at: Set(Some(Utc::now().naive_utc())),
+        ..Default::default()
+    };
+
+    // Someone could have taken the ID or email since the check, which is reported as a conflict.
+    let user = data.db.insert_user(user).await?;
+    if let Err(err) = deliver(data.mailer.clone(), confirmation_email(&user, uuid)).await {
+        // Without the email the account could never be confirmed, while its ID and email
+        // would stay taken, so the registration is undone and can be tried again.
+        data.db.delete_user(user.id).await?;
+        return Err(err.into());
     }
+    Ok(HttpResponse::Ok().body("User registered"))
 }
 
-#[get("/rental/archive/{id}")]
-async fn archive_rental(
+#[utoipa::path(
+    post,
+    path = "/users/{id}/confirmation",
+    tag = "auth",
+    responses(
+        (status = 200, description = "Account confirmed"),
+        (status = 400, description = "Invalid confirmation link", body = ErrorResponse),
+        (status = 409, description = "Account is already confirmed", body = ErrorResponse),
+        (status = 410, description = "Confirmation link expired", body = ErrorResponse),
+    )
+)]
+async fn confirm_user(
     id: web::Path<i32>,
-    Auth(user): Auth<HAS_TOKEN>,
+    form: Json<ConfirmationFormData>,
     data: Data<AppState>,
-) -> HttpResponse {
+) -> Result<HttpResponse, ApiError> {
+    let (id, token) = (id.into_inner(), form.token);
+    let user = data
+        .db
+        .get_user(id)
+        .await?
+        .ok_or(ApiError::InvalidConfirmationLink)?; // User doesn't exist
+
+    if user.confirmation_token.is_none() {
+        return Err(ApiError::AccountAlreadyConfirmed);
+    }
+    if user.confirmation_token != Some(token) {
+        return Err(ApiError::InvalidConfirmationLink);
+    }
+    let expires_at = user
+        .confirmation_sent_at
+        .map(|sent_at| sent_at + Duration::hours(CONFIRMATION_EXPIRY_TIME));
+    if expires_at.is_none_or(|expires_at| expires_at <= Utc::now().naive_utc()) {
+        return Err(ApiError::ConfirmationLinkExpired);
+    }
+
+    let user = UserActiveModel {
+        id: Set(id),
+        confirmation_token: Set(None),
+        confirmation_sent_at: Set(None),
+        ..Default::default()
+    };
+    data.db.update_user(user).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Sends a new confirmation link to the given email address, invalidating the previous one.
+/// Responds the same way whether there is an unconfirmed account with this email or not,
+/// sending the email in the background like `forgot_password`.
+#[utoipa::path(
+    post,
+    path = "/confirmation_emails",
+    tag = "auth",
+    responses(
+        (status = 200, description = "Link sent if there is an unconfirmed account with this email"),
+        (status = 429, description = "Too many confirmation links requested", body = ErrorResponse),
+    )
+)]
+async fn resend_confirmation(
+    req: HttpRequest,
+    form: Json<ResendConfirmationFormData>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let ip = client_ip(&req, &data.config.trusted_proxies);
+    if let Err(retry_at) =
+        data.rate_limiter
+            .check_ip(LimitedAction::ConfirmationEmail, &ip, Utc::now())
+    {
+        eprintln!("Too many confirmation emails requested from {}", ip);
+        return Err(ApiError::TooManyConfirmationEmails { retry_at });
+    }
+
+    let email = form.into_inner().email;
+    actix_web::rt::spawn(async move {
+        if let Err(err) = send_confirmation_email(&data, &email).await {
+            eprintln!("Failed to send confirmation email: {}", err);
+        }
+    });
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Saves a new confirmation token for the unconfirmed account with the given email
+/// and sends the link to the user. Does nothing if there is no such account.
+async fn send_confirmation_email(data: &AppState, email: &str) -> Result<(), ApiError> {
+    let user = match data.db.get_user_by_email(email).await? {
+        Some(user) if user.confirmation_token.is_some() => user,
+        _ => return Ok(()),
+    };
+
+    let uuid = Uuid::new_v4();
+    let updated_user = UserActiveModel {
+        id: Set(user.id),
+        confirmation_token: Set(Some(uuid)),
+        confirmation_sent_at: Set(Some(Utc::now().naive_utc())),
+        ..Default::default()
+    };
+    data.db.update_user(updated_user).await?;
+
+    deliver(data.mailer.clone(), confirmation_email(&user, uuid)).await?;
+    Ok(())
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/{id}",
+    tag = "users",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "User", body = UserModel),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+        (status = 404, description = "User not found", body = ErrorResponse),
+    )
+)]
+async fn get_user(
+    id: web::Path<i32>,
+    Auth(user, _): Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    // Non-admin user can only check info about themselves.
+    let id = id.into_inner();
+    is_self_request(&user, id, Permission::ViewUsers)?;
+
+    let user = data.db.get_user(id).await?.ok_or(ApiError::UserNotFound)?;
+    Ok(HttpResponse::Ok().json(user))
+}
+
+#[utoipa::path(
+    get,
+    path = "/users",
+    tag = "users",
+    params(PageParams),
+    security(("bearer_auth" = ["view_users"])),
+    responses(
+        (status = 200, description = "Page of users", body = Page<GetUsersQueryResult>),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+async fn get_users(
+    page: web::Query<PageParams>,
+    Auth(_user, _): Auth<ViewUsers>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let users = data.db.get_users(page.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(users))
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/{id}/penalized",
+    tag = "penalties",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Whether the user is penalized", body = bool),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+async fn is_penalized(
+    id: web::Path<i32>,
+    Auth(user, _): Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    // Non-admin user can only check info about themselves.
+    let id = id.into_inner();
+    is_self_request(&user, id, Permission::ViewUsers)?;
+
+    let res = data.db.is_user_penalized(id).await?;
+    Ok(HttpResponse::Ok().json(res))
+}
+
+#[utoipa::path(
+    get,
+    path = "/me/penalties",
+    tag = "penalties",
+    params(PageParams),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Penalty points of the user and a page of their penalty events", body = GetPenaltiesQueryResult),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+    )
+)]
+async fn get_my_penalties(
+    page: web::Query<PageParams>,
+    Auth(user, _): Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let penalties = data
+        .db
+        .get_user_penalties(user.sub, page.into_inner())
+        .await?;
+    Ok(HttpResponse::Ok().json(penalties))
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/{id}/penalties",
+    tag = "penalties",
+    params(PageParams),
+    security(("bearer_auth" = ["view_users"])),
+    responses(
+        (status = 200, description = "Penalty points of the user and a page of their penalty events", body = GetPenaltiesQueryResult),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+async fn get_user_penalties(
+    id: web::Path<i32>,
+    page: web::Query<PageParams>,
+    Auth(_user, _): Auth<ViewUsers>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let id = id.into_inner();
+    let penalties = data.db.get_user_penalties(id, page.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(penalties))
+}
+
+/// Gives penalty points to a user by hand. Negative points can be used to cancel out earlier ones.
+/// Points expire after `expiry_days` days, or after `PENALTY_EXPIRY_DAYS` if not given.
+#[utoipa::path(
+    post,
+    path = "/users/{id}/penalties",
+    tag = "penalties",
+    security(("bearer_auth" = ["manage_users"])),
+    responses(
+        (status = 200, description = "Penalty added"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+async fn add_penalty(
+    id: web::Path<i32>,
+    form: Json<PenaltyFormData>,
+    Auth(_user, _): Auth<ManageUsers>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let user_id = id.into_inner();
+    let penalty_event = penalty_event(
+        user_id,
+        None,
+        PenaltyReason::Manual,
+        form.points,
+        form.description.clone(),
+        form.expiry_days.unwrap_or(PENALTY_EXPIRY_DAYS),
+    );
+
+    data.db.insert_penalty_event(penalty_event).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[utoipa::path(
+    delete,
+    path = "/penalties/{id}",
+    tag = "penalties",
+    security(("bearer_auth" = ["manage_users"])),
+    responses(
+        (status = 200, description = "Penalty deleted"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+async fn delete_penalty(
+    id: web::Path<i32>,
+    Auth(_user, _): Auth<ManageUsers>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let id = id.into_inner();
+    data.db.delete_penalty_event(id).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Cancels reservations that were never picked up and penalizes their users.
+#[utoipa::path(
+    post,
+    path = "/penalties/no_shows",
+    tag = "penalties",
+    security(("bearer_auth" = ["manage_rentals"])),
+    responses(
+        (status = 200, description = "Number of cancelled reservations", body = u64),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+async fn process_no_shows(
+    Auth(_user, _): Auth<ManageRentals>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let cancelled = penalize_no_shows(&data.db, today()).await?;
+    Ok(HttpResponse::Ok().json(cancelled.len()))
+}
+
+#[utoipa::path(
+    put,
+    path = "/users/{id}/password",
+    tag = "users",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Password changed"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+        (status = 404, description = "User not found", body = ErrorResponse),
+        (status = 422, description = "Password too weak", body = ErrorResponse),
+    )
+)]
+async fn change_password(
+    id: web::Path<i32>,
+    form: Json<ChangePasswordFormData>,
+    Auth(user, _): Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    // Non-admin user can only change their own password.
+    let id = id.into_inner();
+    is_self_request(&user, id, Permission::ManageUsers)?;
+    if id != user.sub {
+        check_can_manage_user(&data.db, &user, id).await?;
+    }
+    let errors = validate_password(&form.password);
+    if !errors.is_empty() {
+        return Err(ApiError::InvalidFields(errors));
+    }
+
+    let user = UserActiveModel {
+        id: Set(id),
+        password_hash: Set(hash_password(form.password.clone())?),
+        ..Default::default()
+    };
+    // All sessions, including the current one, have to be started again with the new password.
+    data.db
+        .update_user_password(user, Utc::now().naive_utc())
+        .await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Ends all sessions of the given user, invalidating all their tokens.
+#[utoipa::path(
+    delete,
+    path = "/users/{id}/sessions",
+    tag = "auth",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "All sessions ended"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+async fn logout_all(
+    id: web::Path<i32>,
+    Auth(user, _): Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    // Non-admin user can only log out themselves.
+    let id = id.into_inner();
+    is_self_request(&user, id, Permission::ManageUsers)?;
+
+    data.db
+        .revoke_user_tokens(id, Utc::now().naive_utc())
+        .await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Sends a password reset link to the given email address.
+/// Responds the same way whether the account exists or not, so that it can't be used
+/// to find out who has an account. The email is sent in the background for the same reason,
+/// as the response time would give it away otherwise.
+#[utoipa::path(
+    post,
+    path = "/password_reset_emails",
+    tag = "auth",
+    responses(
+        (status = 200, description = "Link sent if there is an account with this email"),
+    )
+)]
+async fn forgot_password(form: Json<ForgotPasswordFormData>, data: Data<AppState>) -> HttpResponse {
+    let email = form.into_inner().email;
+    actix_web::rt::spawn(async move {
+        if let Err(err) = send_password_reset_email(&data, &email).await {
+            eprintln!("Failed to send password reset email: {}", err);
+        }
+    });
+    HttpResponse::Ok().finish()
+}
+
+/// Saves a new password reset token for the account with the given email and sends it to the user.
+/// Does nothing if there is no such account.
+async fn send_password_reset_email(data: &AppState, email: &str) -> Result<(), ApiError> {
+    let user = match data.db.get_user_by_email(email).await? {
+        Some(user) => user,
+        None => return Ok(()),
+    };
+
+    let token = generate_token();
+    let now = Utc::now().naive_utc();
+    let reset_token = PasswordResetTokenActiveModel {
+        user_id: Set(user.id),
+        token_hash: Set(hash_token(&token)),
+        created_at: Set(now),
+        expires_at: Set(now + Duration::minutes(PASSWORD_RESET_EXPIRY_TIME)),
+        ..Default::default()
+    };
+    data.db.save_password_reset_token(reset_token).await?;
+
+    deliver(data.mailer.clone(), password_reset_email(&user, &token)).await?;
+    Ok(())
+}
+
+/// Sets a new password using a token from a password reset link.
+/// All tokens issued to the user before the reset stop working.
+#[utoipa::path(
+    post,
+    path = "/password_resets",
+    tag = "auth",
+    responses(
+        (status = 200, description = "Password changed"),
+        (status = 400, description = "Invalid or expired link", body = ErrorResponse),
+        (status = 422, description = "Password too weak", body = ErrorResponse),
+    )
+)]
+async fn reset_password(
+    form: Json<ResetPasswordFormData>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let errors = validate_password(&form.password);
+    if !errors.is_empty() {
+        return Err(ApiError::InvalidFields(errors));
+    }
+
+    let password_hash = hash_password(form.password.clone())?;
+    let now = Utc::now().naive_utc();
+    let reset = data
+        .db
+        .reset_password(&hash_token(&form.token), password_hash, now)
+        .await?;
+    if !reset {
+        return Err(ApiError::InvalidPasswordResetLink);
+    }
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[utoipa::path(
+    patch,
+    path = "/users/{id}",
+    tag = "users",
+    security(("bearer_auth" = ["manage_users"])),
+    responses(
+        (status = 200, description = "Done"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+        (status = 404, description = "User not found", body = ErrorResponse),
+        (status = 422, description = "Invalid form fields", body = ErrorResponse),
+    )
+)]
+async fn update_user(
+    id: web::Path<i32>,
+    form: Json<UpdateUserFormData>,
+    Auth(user, _): Auth<ManageUsers>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let id = id.into_inner();
+    check_can_manage_user(&data.db, &user, id).await?;
+    let name = form.name.as_deref().map(str::trim);
+    let surname = form.surname.as_deref().map(str::trim);
+    let email = form.email.as_deref().map(str::trim);
+    let errors = validate_user_update(name, surname, email, form.password.as_deref());
+    if !errors.is_empty() {
+        return Err(ApiError::InvalidFields(errors));
+    }
+    if let Some(email) = email {
+        if matches!(data.db.get_user_by_email(email).await?, Some(other) if other.id != id) {
+            let errors = vec![FieldError::new("email", FieldIssue::Taken)];
+            return Err(ApiError::InvalidFields(errors));
+        }
+    }
+
+    let password_hash = match &form.password {
+        Some(password) => Set(hash_password(password.clone())?),
+        None => NotSet,
+    };
+    let user = UserActiveModel {
+        id: Set(id),
+        name: name.map_or(NotSet, |name| Set(name.to_string())),
+        surname: surname.map_or(NotSet, |surname| Set(surname.to_string())),
+        email: email.map_or(NotSet, |email| Set(email.to_string())),
+        password_hash,
+        ..Default::default()
+    };
+    // A new password should end old sessions.
+    if form.password.is_some() {
+        data.db
+            .update_user_password(user, Utc::now().naive_utc())
+            .await?;
+    } else {
+        data.db.update_user(user).await?;
+    }
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Assigns a role to the given user.
+#[utoipa::path(
+    put,
+    path = "/users/{id}/role",
+    tag = "roles",
+    security(("bearer_auth" = ["manage_roles"])),
+    responses(
+        (status = 200, description = "Role assigned"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+        (status = 404, description = "User or role not found", body = ErrorResponse),
+    )
+)]
+async fn set_user_role(
+    id: web::Path<i32>,
+    form: Json<SetRoleFormData>,
+    Auth(user, _): Auth<ManageRoles>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let id = id.into_inner();
+    check_can_manage_user(&data.db, &user, id).await?;
+    data.db
+        .get_role(form.role_id)
+        .await?
+        .ok_or(ApiError::RoleNotFound)?;
+    check_has_role_permissions(&data.db, &user, form.role_id).await?;
+
+    let updated_user = UserActiveModel {
+        id: Set(id),
+        role_id: Set(form.role_id),
+        ..Default::default()
+    };
+    data.db.update_user(updated_user).await?;
+    eprintln!(
+        "Role of user {} set to {} by admin {}",
+        id, form.role_id, user.sub
+    );
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[utoipa::path(
+    get,
+    path = "/roles",
+    tag = "roles",
+    security(("bearer_auth" = ["manage_roles"])),
+    responses(
+        (status = 200, description = "All roles", body = Vec<GetRolesQueryResult>),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+async fn get_roles(
+    Auth(_user, _): Auth<ManageRoles>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let roles = data.db.get_roles().await?;
+    Ok(HttpResponse::Ok().json(roles))
+}
+
+/// Checks whether the user can manage the account of the given user.
+/// Users can't manage accounts which have permissions they don't have themselves,
+/// so that e.g. they can't take over a superadmin account by changing its password.
+async fn check_can_manage_user(
+    db: &DatabaseManager,
+    user: &Claims,
+    id: i32,
+) -> Result<(), ApiError> {
+    let managed_user = db.get_user(id).await?.ok_or(ApiError::UserNotFound)?;
+    check_has_role_permissions(db, user, managed_user.role_id).await
+}
+
+/// Checks whether the user has all permissions of the given role.
+async fn check_has_role_permissions(
+    db: &DatabaseManager,
+    user: &Claims,
+    role_id: i32,
+) -> Result<(), ApiError> {
+    let permissions = db.get_role_permissions(role_id).await?;
+    if permissions.iter().all(|p| user.has_permission(*p)) {
+        Ok(())
+    } else {
+        Err(ApiError::Forbidden)
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/users/{id}",
+    tag = "users",
+    security(("bearer_auth" = ["manage_users"])),
+    responses(
+        (status = 200, description = "User deleted"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+        (status = 404, description = "User not found", body = ErrorResponse),
+    )
+)]
+async fn delete_user(
+    id: web::Path<i32>,
+    Auth(user, _): Auth<ManageUsers>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let id = id.into_inner();
+    check_can_manage_user(&data.db, &user, id).await?;
+
+    data.db.delete_user(id).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[utoipa::path(
+    post,
+    path = "/board_games",
+    tag = "board_games",
+    security(("bearer_auth" = ["manage_catalog"])),
+    request_body(content = BoardGameFormData, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "Board game added"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+        (status = 413, description = "Image too large", body = ErrorResponse),
+        (status = 415, description = "Unsupported image type", body = ErrorResponse),
+        (status = 422, description = "Invalid form fields", body = ErrorResponse),
+    )
+)]
+async fn create_board_game(
+    MultipartForm(form): MultipartForm<BoardGameFormData>,
+    Auth(_user, _): Auth<ManageCatalog>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    save_board_game(None, form, &data).await
+}
+
+/// Changes a board game. The image can be left out to keep the current one.
+#[utoipa::path(
+    put,
+    path = "/board_games/{id}",
+    tag = "board_games",
+    security(("bearer_auth" = ["manage_catalog"])),
+    request_body(content = BoardGameFormData, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "Board game saved"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+        (status = 404, description = "Board game not found", body = ErrorResponse),
+        (status = 413, description = "Image too large", body = ErrorResponse),
+        (status = 415, description = "Unsupported image type", body = ErrorResponse),
+        (status = 422, description = "Invalid form fields", body = ErrorResponse),
+    )
+)]
+async fn update_board_game(
+    id: web::Path<i32>,
+    MultipartForm(form): MultipartForm<BoardGameFormData>,
+    Auth(_user, _): Auth<ManageCatalog>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    save_board_game(Some(id.into_inner()), form, &data).await
+}
+
+/// Inserts a new board game or changes the one of the given ID, storing its image.
+async fn save_board_game(
+    id: Option<i32>,
+    form: BoardGameFormData,
+    data: &AppState,
+) -> Result<HttpResponse, ApiError> {
+    let old_board_game = match id {
+        Some(id) => {
+            let board_game = data
+                .db
+                .get_board_game(id)
+                .await?
+                .ok_or(ApiError::BoardGameNotFound)?;
+            Some(board_game)
+        }
+        None => None,
+    };
+
+    // The image can be left out when only the other data of an existing game is changed.
+    // Browsers send an empty file if none was chosen.
+    let image = form.image.filter(|image| image.size > 0);
+    let new_file_name = match image {
+        Some(image) => Some(store_image(data.media.clone(), image).await?),
+        None if id.is_none() => {
+            return Err(ApiError::InvalidFields(vec![FieldError::new(
+                "image",
+                FieldIssue::Required,
+            )]))
+        }
+        None => None,
+    };
+
+    let additional_info = form.additional_info.into_inner();
+    let additional_info = if additional_info.is_empty() {
+        None
+    } else {
+        Some(additional_info)
+    };
+    let board_game = BoardGameActiveModel {
+        id: id.map_or(NotSet, Set),
+        title: Set(form.title.into_inner()),
+        weight: Set(form.weight.into_inner()),
+        photo_filename: match &new_file_name {
+            Some(file_name) => Set(file_name.clone()),
+            None => NotSet,
+        },
+        min_players: Set(form.min_players.into_inner()),
+        max_players: Set(form.max_players.into_inner()),
+        min_playtime: Set(form.min_playtime.into_inner()),
+        max_playtime: Set(form.max_playtime.into_inner()),
+        additional_info: Set(additional_info),
+    };
+
+    if let Err(err) = data.db.save_board_game(board_game).await {
+        if let Some(file_name) = &new_file_name {
+            remove_unused_image(&data.db, data.media.clone(), file_name).await?;
+        }
+        return Err(err.into());
+    }
+
+    // The replaced image is no longer needed.
+    if let (Some(old_board_game), Some(_)) = (old_board_game, new_file_name) {
+        remove_unused_image(&data.db, data.media.clone(), &old_board_game.photo_filename).await?;
+    }
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[utoipa::path(
+    get,
+    path = "/board_games/{id}",
+    tag = "board_games",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Board game", body = BoardGameResponse),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 404, description = "Board game not found", body = ErrorResponse),
+    )
+)]
+async fn get_board_game(
+    id: web::Path<i32>,
+    Auth(_user, _): Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let id = id.into_inner();
+    let board_game = data
+        .db
+        .get_board_game(id)
+        .await?
+        .ok_or(ApiError::BoardGameNotFound)?;
+    Ok(HttpResponse::Ok().json(BoardGameResponse::from(board_game)))
+}
+
+/// Searches the catalogue. See `BoardGameFilter` for the supported query parameters.
+#[utoipa::path(
+    get,
+    path = "/board_games",
+    tag = "board_games",
+    params(BoardGameFilter, PageParams),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Page of matching board games", body = Page<WithPhotoUrls<GetBoardGamesQueryResult>>),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+    )
+)]
+async fn search_board_games(
+    query: web::Query<BoardGameFilter>,
+    page: web::Query<PageParams>,
+    Auth(user, _): Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let board_games = data
+        .db
+        .search_board_games(user.sub, &query, page.into_inner())
+        .await?
+        .map(WithPhotoUrls::from);
+    Ok(HttpResponse::Ok().json(board_games))
+}
+
+#[utoipa::path(
+    get,
+    path = "/inventory",
+    tag = "board_games",
+    params(PageParams),
+    security(("bearer_auth" = ["manage_catalog"])),
+    responses(
+        (status = 200, description = "Page of board games with their copies and rentals", body = Page<BoardGameAdminResponse>),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+async fn get_board_games_admin(
+    page: web::Query<PageParams>,
+    Auth(_user, _): Auth<ManageCatalog>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let board_games = data.db.get_board_games_admin(page.into_inner()).await?.map(
+        |(board_game, copies, rentals)| {
+            BoardGameAdminResponse(BoardGameResponse::from(board_game), copies, rentals)
+        },
+    );
+    Ok(HttpResponse::Ok().json(board_games))
+}
+
+/// Returns free and busy copies of the board game for every day of the given period.
+/// Both dates are optional and default to the next `AVAILABILITY_DEFAULT_DAYS` days.
+#[utoipa::path(
+    get,
+    path = "/board_games/{id}/availability",
+    tag = "board_games",
+    params(AvailabilityQuery),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Availability of the copies", body = Vec<AvailabilityInterval>),
+        (status = 400, description = "Invalid dates", body = ErrorResponse),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+    )
+)]
+async fn get_board_game_availability(
+    id: web::Path<i32>,
+    query: web::Query<AvailabilityQuery>,
+    Auth(_user, _): Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let parse_date = |date: &Option<String>, default: Date| match date {
+        Some(date) if !date.is_empty() => {
+            Date::parse_from_str(date, "%Y-%m-%d").map_err(|_| ApiError::InvalidDateFormat)
+        }
+        _ => Ok(default),
+    };
+    let from = parse_date(&query.from, today())?;
+    let to = parse_date(&query.to, from + Days::new(AVAILABILITY_DEFAULT_DAYS))?;
+    if from > to || (to - from).num_days() >= AVAILABILITY_MAX_DAYS {
+        return Err(ApiError::InvalidDateRange);
+    }
+
+    let id = id.into_inner();
+    let availability = data.db.get_board_game_availability(id, from, to).await?;
+    Ok(HttpResponse::Ok().json(availability))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/board_games/{id}",
+    tag = "board_games",
+    security(("bearer_auth" = ["manage_catalog"])),
+    responses(
+        (status = 200, description = "Board game deleted"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+        (status = 409, description = "Board game is still referenced", body = ErrorResponse),
+    )
+)]
+async fn delete_board_game(
+    id: web::Path<i32>,
+    Auth(_user, _): Auth<ManageCatalog>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let id = id.into_inner();
+    if let Some(board_game) = data.db.get_board_game(id).await? {
+        data.db.delete_board_game(id).await?;
+        remove_unused_image(&data.db, data.media.clone(), &board_game.photo_filename).await?;
+    }
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[utoipa::path(
+    post,
+    path = "/copies",
+    tag = "board_games",
+    security(("bearer_auth" = ["manage_catalog"])),
+    responses(
+        (status = 200, description = "Copy added"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+async fn create_game_copy(
+    form: Json<GameCopyFormData>,
+    Auth(_user, _): Auth<ManageCatalog>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    save_game_copy(None, &form, &data).await
+}
+
+#[utoipa::path(
+    put,
+    path = "/copies/{id}",
+    tag = "board_games",
+    security(("bearer_auth" = ["manage_catalog"])),
+    responses(
+        (status = 200, description = "Copy saved"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+async fn update_game_copy(
+    id: web::Path<i32>,
+    form: Json<GameCopyFormData>,
+    Auth(_user, _): Auth<ManageCatalog>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    save_game_copy(Some(id.into_inner()), &form, &data).await
+}
+
+/// Inserts a new copy of a board game or changes the one of the given ID.
+async fn save_game_copy(
+    id: Option<i32>,
+    form: &GameCopyFormData,
+    data: &AppState,
+) -> Result<HttpResponse, ApiError> {
+    let label = if form.label.is_empty() {
+        None
+    } else {
+        Some(form.label.clone())
+    };
+    let game_copy = GameCopyActiveModel {
+        id: id.map_or(NotSet, Set),
+        game_id: Set(form.game_id),
+        label: Set(label),
+    };
+
+    data.db.save_game_copy(game_copy).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[utoipa::path(
+    get,
+    path = "/board_games/{id}/copies",
+    tag = "board_games",
+    params(PageParams),
+    security(("bearer_auth" = ["manage_catalog"])),
+    responses(
+        (status = 200, description = "Page of the copies of the board game", body = Page<GameCopyModel>),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+async fn get_game_copies(
+    id: web::Path<i32>,
+    page: web::Query<PageParams>,
+    Auth(_user, _): Auth<ManageCatalog>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let game_id = id.into_inner();
+    let game_copies = data.db.get_game_copies(game_id, page.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(game_copies))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/copies/{id}",
+    tag = "board_games",
+    security(("bearer_auth" = ["manage_catalog"])),
+    responses(
+        (status = 200, description = "Copy deleted"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+        (status = 409, description = "Copy is still referenced", body = ErrorResponse),
+    )
+)]
+async fn delete_game_copy(
+    id: web::Path<i32>,
+    Auth(_user, _): Auth<ManageCatalog>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let id = id.into_inner();
+    data.db.delete_game_copy(id).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[utoipa::path(
+    get,
+    path = "/categories",
+    tag = "labels",
+    params(PageParams),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Page of categories", body = Page<CategoryModel>),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+    )
+)]
+async fn get_categories(
+    page: web::Query<PageParams>,
+    Auth(_user, _): Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let categories = data.db.get_categories(page.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(categories))
+}
+
+#[utoipa::path(
+    post,
+    path = "/categories",
+    tag = "labels",
+    security(("bearer_auth" = ["manage_catalog"])),
+    responses(
+        (status = 200, description = "Category added"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+        (status = 409, description = "Category already exists", body = ErrorResponse),
+        (status = 422, description = "Invalid form fields", body = ErrorResponse),
+    )
+)]
+async fn create_category(
+    form: Json<LabelFormData>,
+    Auth(_user, _): Auth<ManageCatalog>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    save_category(None, &form, &data).await
+}
+
+#[utoipa::path(
+    put,
+    path = "/categories/{id}",
+    tag = "labels",
+    security(("bearer_auth" = ["manage_catalog"])),
+    responses(
+        (status = 200, description = "Category saved"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+        (status = 409, description = "Category already exists", body = ErrorResponse),
+        (status = 422, description = "Invalid form fields", body = ErrorResponse),
+    )
+)]
+async fn update_category(
+    id: web::Path<i32>,
+    form: Json<LabelFormData>,
+    Auth(_user, _): Auth<ManageCatalog>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    save_category(Some(id.into_inner()), &form, &data).await
+}
+
+/// Inserts a new category or renames the one of the given ID.
+async fn save_category(
+    id: Option<i32>,
+    form: &LabelFormData,
+    data: &AppState,
+) -> Result<HttpResponse, ApiError> {
+    let category = CategoryActiveModel {
+        id: id.map_or(NotSet, Set),
+        name: Set(label_name(&form.name)?),
+    };
+
+    data.db.save_category(category).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[utoipa::path(
+    delete,
+    path = "/categories/{id}",
+    tag = "labels",
+    security(("bearer_auth" = ["manage_catalog"])),
+    responses(
+        (status = 200, description = "Category deleted"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+async fn delete_category(
+    id: web::Path<i32>,
+    Auth(_user, _): Auth<ManageCatalog>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let id = id.into_inner();
+    data.db.delete_category(id).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Replaces the categories of a board game with the given ones.
+#[utoipa::path(
+    put,
+    path = "/board_games/{id}/categories",
+    tag = "labels",
+    security(("bearer_auth" = ["manage_catalog"])),
+    responses(
+        (status = 200, description = "Categories set"),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+async fn set_board_game_categories(
+    id: web::Path<i32>,
+    form: Json<BoardGameCategoriesFormData>,
+    Auth(_user, _): Auth<ManageCatalog>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let game_id = id.into_inner();
+    let category_ids = unique_ids(&form.category_ids);
+    data.db
+        .set_board_game_categories(game_id, category_ids)
+        .await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[utoipa::path(
+    get,
+    path = "/tags",
+    tag = "labels",
+    params(PageParams),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Page of tags", body = Page<TagModel>),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+    )
+)]
+async fn get_tags(
+    page: web::Query<PageParams>,
+    Auth(_user, _): Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let tags = data.db.get_tags(page.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(tags))
+}
+
+#[utoipa::path(
+    post,
+    path = "/tags",
+    tag = "labels",
+    security(("bearer_auth" = ["manage_catalog"])),
+    responses(
+        (status = 200, description = "Tag added"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+        (status = 409, description = "Tag already exists", body = ErrorResponse),
+        (status = 422, description = "Invalid form fields", body = ErrorResponse),
+    )
+)]
+async fn create_tag(
+    form: Json<LabelFormData>,
+    Auth(_user, _): Auth<ManageCatalog>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    save_tag(None, &form, &data).await
+}
+
+#[utoipa::path(
+    put,
+    path = "/tags/{id}",
+    tag = "labels",
+    security(("bearer_auth" = ["manage_catalog"])),
+    responses(
+        (status = 200, description = "Tag saved"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+        (status = 409, description = "Tag already exists", body = ErrorResponse),
+        (status = 422, description = "Invalid form fields", body = ErrorResponse),
+    )
+)]
+async fn update_tag(
+    id: web::Path<i32>,
+    form: Json<LabelFormData>,
+    Auth(_user, _): Auth<ManageCatalog>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    save_tag(Some(id.into_inner()), &form, &data).await
+}
+
+/// Inserts a new tag or renames the one of the given ID.
+async fn save_tag(
+    id: Option<i32>,
+    form: &LabelFormData,
+    data: &AppState,
+) -> Result<HttpResponse, ApiError> {
+    let tag = TagActiveModel {
+        id: id.map_or(NotSet, Set),
+        name: Set(label_name(&form.name)?),
+    };
+
+    data.db.save_tag(tag).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[utoipa::path(
+    delete,
+    path = "/tags/{id}",
+    tag = "labels",
+    security(("bearer_auth" = ["manage_catalog"])),
+    responses(
+        (status = 200, description = "Tag deleted"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+async fn delete_tag(
+    id: web::Path<i32>,
+    Auth(_user, _): Auth<ManageCatalog>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let id = id.into_inner();
+    data.db.delete_tag(id).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Replaces the tags of a board game with the given ones.
+#[utoipa::path(
+    put,
+    path = "/board_games/{id}/tags",
+    tag = "labels",
+    security(("bearer_auth" = ["manage_catalog"])),
+    responses(
+        (status = 200, description = "Tags set"),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+async fn set_board_game_tags(
+    id: web::Path<i32>,
+    form: Json<BoardGameTagsFormData>,
+    Auth(_user, _): Auth<ManageCatalog>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let game_id = id.into_inner();
+    let tag_ids = unique_ids(&form.tag_ids);
+    data.db.set_board_game_tags(game_id, tag_ids).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Normalizes the name of a category or tag, so that the catalogue can be filtered by it.
+fn label_name(name: &str) -> Result<String, ApiError> {
+    let name = name.trim().to_lowercase();
+    let errors = validate_label(&name);
+    if !errors.is_empty() {
+        return Err(ApiError::InvalidFields(errors));
+    }
+    Ok(name)
+}
+
+/// Removes repeated IDs from a list, keeping the order of the first occurrences.
+fn unique_ids(ids: &[i32]) -> Vec<i32> {
+    let mut unique = Vec::new();
+    for id in ids {
+        if !unique.contains(id) {
+            unique.push(*id);
+        }
+    }
+    unique
+}
+
+/// Books any copy of the game that is free for the whole period.
+#[utoipa::path(
+    post,
+    path = "/rentals",
+    tag = "rentals",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Rental booked"),
+        (status = 400, description = "Invalid dates", body = ErrorResponse),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Rental denied by the rental policy", body = ErrorResponse),
+        (status = 409, description = "No copy is free on these dates", body = ErrorResponse),
+    )
+)]
+async fn create_rental(
+    form: Json<RentalFormData>,
+    Auth(user, _): Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let (rental_date, return_date) = (form.rental_date, form.return_date);
+    if rental_date > return_date {
+        return Err(ApiError::ReturnBeforeRentalDate);
+    }
+    if rental_date < today() {
+        return Err(ApiError::RentalDateInPast);
+    }
+    if let Some(denial) =
+        check_new_rental(&data.db, &data.config, user.sub, rental_date, return_date).await?
+    {
+        return Err(ApiError::RentalDenied(denial));
+    }
+
+    // Holds that have run out must not block other users.
+    data.db
+        .expire_waitlist_holds(Utc::now().naive_utc())
+        .await?;
+
+    data.db
+        .book_free_game_copy(form.game_id, user.sub, rental_date, return_date)
+        .await?
+        .ok_or(ApiError::DatesUnavailable)?;
+
+    // The user got the game, so they no longer need to wait for it.
+    data.db.leave_waitlist(user.sub, form.game_id).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Changes the dates of a rental. It keeps its copy, so only the copy's other bookings are checked.
+#[utoipa::path(
+    put,
+    path = "/rentals/{id}",
+    tag = "rentals",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Rental saved"),
+        (status = 400, description = "Invalid dates", body = ErrorResponse),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Rental denied by the rental policy, or someone else's rental", body = ErrorResponse),
+        (status = 404, description = "Rental not found", body = ErrorResponse),
+        (status = 409, description = "The copy is booked on these dates, or the game is picked up and needs an extension request", body = ErrorResponse),
+    )
+)]
+async fn update_rental(
+    id: web::Path<i32>,
+    form: Json<RentalDatesFormData>,
+    Auth(user, _): Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let id = id.into_inner();
+    let (rental_date, return_date) = (form.rental_date, form.return_date);
+    if rental_date > return_date {
+        return Err(ApiError::ReturnBeforeRentalDate);
+    }
+
+    let rental = data
+        .db
+        .get_rental(id)
+        .await?
+        .ok_or(ApiError::RentalNotFound)?;
+
+    // Non-admin user can only modify their own rentals.
+    let is_staff = user.has_permission(Permission::ManageRentals);
+    if !is_staff && rental.user_id != user.sub {
+        return Err(ApiError::Forbidden);
+    }
+    let dates_changed = (rental_date, return_date) != (rental.rental_date, rental.return_date);
+    // Once the user has the game, keeping it longer has to be approved by staff.
+    if rental.picked_up && !is_staff && dates_changed {
+        return Err(ApiError::ExtensionRequired);
+    }
+    if rental_date != rental.rental_date && rental_date < today() {
+        return Err(ApiError::RentalDateInPast);
+    }
+    if let Some(denial) = check_rental_update(
+        &data.db,
+        &data.config,
+        rental.user_id,
+        rental_date,
+        return_date,
+    )
+    .await?
+    {
+        return Err(ApiError::RentalDenied(denial));
+    }
+    if !data
+        .db
+        .reschedule_rental(&rental, rental_date, return_date)
+        .await?
+    {
+        return Err(ApiError::DatesUnavailable);
+    }
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[utoipa::path(
+    get,
+    path = "/rentals",
+    tag = "rentals",
+    params(PageParams),
+    security(("bearer_auth" = ["manage_rentals"])),
+    responses(
+        (status = 200, description = "Page of rentals", body = Page<WithPhotoUrls<GetRentalsQueryResult>>),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+async fn get_rentals(
+    page: web::Query<PageParams>,
+    Auth(_user, _): Auth<ManageRentals>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let rentals = data
+        .db
+        .get_rentals(page.into_inner())
+        .await?
+        .map(WithPhotoUrls::from);
+    Ok(HttpResponse::Ok().json(rentals))
+}
+
+#[utoipa::path(
+    get,
+    path = "/me/rentals",
+    tag = "rentals",
+    params(PageParams),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Page of the user's rentals", body = Page<WithPhotoUrls<GetUserRentalsQueryResult>>),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+    )
+)]
+async fn get_my_rentals(
+    page: web::Query<PageParams>,
+    Auth(user, _): Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let rentals = data
+        .db
+        .get_user_rentals(user.sub, page.into_inner())
+        .await?
+        .map(WithPhotoUrls::from);
+    Ok(HttpResponse::Ok().json(rentals))
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/{id}/rentals",
+    tag = "rentals",
+    params(PageParams),
+    security(("bearer_auth" = ["manage_rentals"])),
+    responses(
+        (status = 200, description = "Page of the user's rentals", body = Page<WithPhotoUrls<GetUserRentalsAdminQueryResult>>),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+async fn get_user_rentals(
+    id: web::Path<i32>,
+    page: web::Query<PageParams>,
+    Auth(_user, _): Auth<ManageRentals>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let id = id.into_inner();
+    let rentals = data
+        .db
+        .get_user_rentals_admin(id, page.into_inner())
+        .await?
+        .map(WithPhotoUrls::from);
+    Ok(HttpResponse::Ok().json(rentals))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/rentals/{id}",
+    tag = "rentals",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Rental archived"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+        (status = 404, description = "Rental not found", body = ErrorResponse),
+    )
+)]
+async fn archive_rental(
+    id: web::Path<i32>,
+    Auth(user, _): Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
     // Non-admin user can only archive their own rentals that have not been picked up.
     let id = id.into_inner();
-    if !user.is_admin {
-        let rental = match data.db.get_rental(id).await {
-            Ok(Some(rental)) => rental,
-            Ok(None) => {
-                return build_error_response(
-                    StatusCode::NOT_FOUND,
-                    "Nie znaleziono wypożyczenia w bazie danych",
-                )
-            }
-            Err(_) => return HttpResponse::InternalServerError().finish(), // Failed to get rental data from the database
-        };
+    if !user.has_permission(Permission::ManageRentals) {
+        let rental = data
+            .db
+            .get_rental(id)
+            .await?
+            .ok_or(ApiError::RentalNotFound)?;
         if rental.user_id != user.sub || rental.picked_up {
-            return HttpResponse::Forbidden().finish();
+            return Err(ApiError::Forbidden);
         }
     }
 
-    match data.db.archive_rental(id).await {
-        Ok(_) => HttpResponse::Ok().finish(),
-        Err(_) => HttpResponse::InternalServerError().finish(), // Failed to delete rental from the database
-    }
+    data.db.archive_rental(id, None, None).await?;
+    Ok(HttpResponse::Ok().finish())
 }
 
-#[get("/history/get_all")]
-async fn get_rental_history(
-    Auth(_user): Auth<HAS_ADMIN_TOKEN>,
+/// Marks a rental as picked up by the user, handed over by the staff member making the request.
+#[utoipa::path(
+    post,
+    path = "/rentals/{id}/pick_up",
+    tag = "rentals",
+    security(("bearer_auth" = ["manage_rentals"])),
+    responses(
+        (status = 200, description = "Rental picked up"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+        (status = 404, description = "Rental not found", body = ErrorResponse),
+        (status = 409, description = "Rental already picked up or not started yet", body = ErrorResponse),
+    )
+)]
+async fn pick_up_rental(
+    id: web::Path<i32>,
+    Auth(user, _): Auth<ManageRentals>,
     data: Data<AppState>,
-) -> HttpResponse {
-    match data.db.get_rental_history().await {
-        Ok(rental_history) => HttpResponse::Ok().json(rental_history),
-        Err(_) => HttpResponse::InternalServerError()
-            .body("Failed to get rental history data from database"),
+) -> Result<HttpResponse, ApiError> {
+    let id = id.into_inner();
+    let rental = data
+        .db
+        .get_rental(id)
+        .await?
+        .ok_or(ApiError::RentalNotFound)?;
+
+    if rental.picked_up {
+        return Err(ApiError::AlreadyPickedUp);
+    }
+    if rental.rental_date > today() {
+        return Err(ApiError::RentalNotStarted);
     }
+
+    data.db.pick_up_rental(id, user.sub).await?;
+    Ok(HttpResponse::Ok().finish())
 }
 
-#[get("/history/get")]
-async fn get_my_rental_history(Auth(user): Auth<HAS_TOKEN>, data: Data<AppState>) -> HttpResponse {
-    match data.db.get_user_rental_history(user.sub).await {
-        Ok(rental_history) => HttpResponse::Ok().json(rental_history),
-        Err(_) => HttpResponse::InternalServerError().finish(), // Failed to get rental history data from the database
+/// Marks a rental as returned, taken back by the staff member making the request,
+/// and moves it to the rental history.
+#[utoipa::path(
+    post,
+    path = "/rentals/{id}/return",
+    tag = "rentals",
+    security(("bearer_auth" = ["manage_rentals"])),
+    responses(
+        (status = 200, description = "Rental returned"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+        (status = 404, description = "Rental not found", body = ErrorResponse),
+        (status = 409, description = "Rental not picked up", body = ErrorResponse),
+    )
+)]
+async fn return_rental(
+    id: web::Path<i32>,
+    Auth(user, _): Auth<ManageRentals>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let id = id.into_inner();
+    let rental = data
+        .db
+        .get_rental(id)
+        .await?
+        .ok_or(ApiError::RentalNotFound)?;
+
+    if !rental.picked_up {
+        return Err(ApiError::NotPickedUp);
     }
+
+    let penalty = late_return_penalty(&rental, today());
+    data.db.archive_rental(id, Some(user.sub), penalty).await?;
+    Ok(HttpResponse::Ok().finish())
 }
 
-#[get("/history/get/{id}")]
+#[utoipa::path(
+    get,
+    path = "/history",
+    tag = "history",
+    params(PageParams),
+    security(("bearer_auth" = ["manage_rentals"])),
+    responses(
+        (status = 200, description = "Page of finished rentals", body = Page<WithPhotoUrls<GetRentalHistoryQueryResult>>),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
+async fn get_rental_history(
+    page: web::Query<PageParams>,
+    Auth(_user, _): Auth<ManageRentals>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let rental_history = data
+        .db
+        .get_rental_history(page.into_inner())
+        .await?
+        .map(WithPhotoUrls::from);
+    Ok(HttpResponse::Ok().json(rental_history))
+}
+
+#[utoipa::path(
+    get,
+    path = "/me/history",
+    tag = "history",
+    params(PageParams),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Page of the user's finished rentals", body = Page<WithPhotoUrls<GetUserRentalHistoryQueryResult>>),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+    )
+)]
+async fn get_my_rental_history(
+    page: web::Query<PageParams>,
+    Auth(user, _): Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let rental_history = data
+        .db
+        .get_user_rental_history(user.sub, page.into_inner())
+        .await?
+        .map(WithPhotoUrls::from);
+    Ok(HttpResponse::Ok().json(rental_history))
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/{id}/history",
+    tag = "history",
+    params(PageParams),
+    security(("bearer_auth" = ["manage_rentals"])),
+    responses(
+        (status = 200, description = "Page of the user's finished rentals", body = Page<WithPhotoUrls<GetUserRentalHistoryAdminQueryResult>>),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
 async fn get_user_rental_history(
     id: web::Path<i32>,
-    Auth(_user): Auth<HAS_ADMIN_TOKEN>,
+    page: web::Query<PageParams>,
+    Auth(_user, _): Auth<ManageRentals>,
     data: Data<AppState>,
-) -> HttpResponse {
+) -> Result<HttpResponse, ApiError> {
     let id = id.into_inner();
-    match data.db.get_user_rental_history_admin(id).await {
-        Ok(rental_history) => HttpResponse::Ok().json(rental_history),
-        Err(_) => HttpResponse::InternalServerError()
-            .body("Failed to get rental history data from database"),
-    }
+    let rental_history = data
+        .db
+        .get_user_rental_history_admin(id, page.into_inner())
+        .await?
+        .map(WithPhotoUrls::from);
+    Ok(HttpResponse::Ok().json(rental_history))
 }
 
-#[get("/history/delete/{id}")]
+#[utoipa::path(
+    delete,
+    path = "/history/{id}",
+    tag = "history",
+    security(("bearer_auth" = ["manage_rentals"])),
+    responses(
+        (status = 200, description = "Entry deleted"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    )
+)]
 async fn delete_rental_history(
     id: web::Path<i32>,
-    Auth(_user): Auth<HAS_ADMIN_TOKEN>,
+    Auth(_user, _): Auth<ManageRentals>,
     data: Data<AppState>,
-) -> HttpResponse {
+) -> Result<HttpResponse, ApiError> {
     let id = id.into_inner();
-    match data.db.delete_rental_history(id).await {
-        Ok(_) => HttpResponse::Ok().finish(),
-        Err(_) => HttpResponse::InternalServerError()
-            .body("Failed to delete rental history data from database"),
-    }
+    data.db.delete_rental_history(id).await?;
+    Ok(HttpResponse::Ok().finish())
 }
 
-#[post("/extension/save/{id}")]
+#[utoipa::path(
+    put,
+    path = "/rentals/{id}/extension",
+    tag = "extensions",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Extension requested"),
+        (status = 400, description = "Invalid date", body = ErrorResponse),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Extension denied by the rental policy, or someone else's rental", body = ErrorResponse),
+        (status = 404, description = "Rental not found", body = ErrorResponse),
+        (status = 409, description = "Copy is booked on these dates", body = ErrorResponse),
+    )
+)]
 async fn save_extension_request(
     id: web::Path<i32>,
-    form: Form<ExtensionRequestFormData>,
-    Auth(user): Auth<HAS_TOKEN>,
+    form: Json<ExtensionRequestFormData>,
+    Auth(user, _): Auth<HasToken>,
     data: Data<AppState>,
-) -> HttpResponse {
+) -> Result<HttpResponse, ApiError> {
     let rental_id = id.into_inner();
-    let rental = match data.db.get_rental(rental_id).await {
-        Ok(Some(rental)) => rental,
-        Ok(None) => return HttpResponse::NotFound().body("Related rental not found"),
-        Err(_) => {
-            return HttpResponse::InternalServerError()
-                .body("Failed to get rental data from database")
-        }
-    };
+    let rental = data
+        .db
+        .get_rental(rental_id)
+        .await?
+        .ok_or(ApiError::RentalNotFound)?;
 
     // Non-admin user can only modify their own rentals.
-    if !user.is_admin && rental.user_id != user.sub {
-        return HttpResponse::Forbidden().body("Insufficient privileges");
+    if !user.has_permission(Permission::ManageRentals) && rental.user_id != user.sub {
+        return Err(ApiError::Forbidden);
+    }
+
+    let extension_date = form.extension_date;
+    check_extension_date(&data.db, &rental, extension_date).await?;
+    if let Some(denial) = check_extension(
+        &data.db,
+        &data.config,
+        rental.user_id,
+        rental.rental_date,
+        extension_date,
+    )
+    .await?
+    {
+        return Err(ApiError::RentalDenied(denial));
     }
 
-    let extension_date = match Date::parse_from_str(form.extension_date.as_str(), "%Y-%m-%d") {
-        Ok(date) => date,
-        Err(_) => return HttpResponse::BadRequest().body("Invalid date format"),
-    };
     let rental = RentalActiveModel {
         id: Set(rental_id),
         extension_date: Set(Some(extension_date)),
         ..Default::default()
     };
+    data.db.save_rental(rental).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Checks whether the extension date is later than the current return date
+/// and the rented copy is not booked by anyone else in between.
+async fn check_extension_date(
+    db: &DatabaseManager,
+    rental: &RentalModel,
+    extension_date: Date,
+) -> Result<(), ApiError> {
+    if extension_date <= rental.return_date {
+        return Err(ApiError::ExtensionNotAfterReturnDate);
+    }
 
-    match data.db.save_rental(rental).await {
-        Ok(_) => HttpResponse::Ok().finish(),
-        Err(_) => HttpResponse::InternalServerError()
-            .body("Failed to save extension request into database"),
+    let from = rental.return_date.succ_opt().unwrap_or(rental.return_date);
+    if db
+        .is_game_copy_free(rental.copy_id, from, extension_date, Some(rental.id))
+        .await?
+    {
+        Ok(())
+    } else {
+        Err(ApiError::DatesUnavailable)
     }
 }
 
-#[get("/extension/accept/{id}")]
+#[utoipa::path(
+    post,
+    path = "/rentals/{id}/extension/accept",
+    tag = "extensions",
+    security(("bearer_auth" = ["manage_rentals"])),
+    responses(
+        (status = 200, description = "Extension accepted"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+        (status = 404, description = "Rental or extension request not found", body = ErrorResponse),
+        (status = 409, description = "Copy is booked on these dates", body = ErrorResponse),
+    )
+)]
 async fn accept_extension_request(
     id: web::Path<i32>,
-    Auth(_user): Auth<HAS_ADMIN_TOKEN>,
+    Auth(_user, _): Auth<ManageRentals>,
     data: Data<AppState>,
-) -> HttpResponse {
+) -> Result<HttpResponse, ApiError> {
     let rental_id = id.into_inner();
-    let rental = match data.db.get_rental(rental_id).await {
-        Ok(Some(rental)) => rental,
-        Ok(None) => return HttpResponse::NotFound().body("Related rental not found"),
-        Err(_) => {
-            return HttpResponse::InternalServerError()
-                .body("Failed to get rental data from database")
-        }
-    };
-
-    let new_date = match rental.extension_date {
-        Some(date) => date,
-        None => return HttpResponse::BadRequest().body("Extension request not found"),
-    };
-
-    let rental = RentalActiveModel {
+    let rental = data
+        .db
+        .get_rental(rental_id)
+        .await?
+        .ok_or(ApiError::RentalNotFound)?;
+
+    let new_date = rental
+        .extension_date
+        .ok_or(ApiError::ExtensionRequestNotFound)?;
+    check_extension_date(&data.db, &rental, new_date).await?;
+
+    let extended_rental = RentalActiveModel {
         id: Set(rental_id),
         return_date: Set(new_date),
         extension_date: Set(None),
+        overdue_since: Set(None),
+        // The user is reminded again about the new return date.
+        reminder_sent_at: Set(None),
         ..Default::default()
     };
+    data.db.save_rental(extended_rental).await?;
 
-    match data.db.save_rental(rental).await {
-        Ok(_) => HttpResponse::Ok().finish(),
-        Err(_) => HttpResponse::InternalServerError()
-            .body("Failed to save extension request into database"),
-    }
+    let rental = RentalModel {
+        return_date: new_date,
+        extension_date: None,
+        overdue_since: None,
+        ..rental
+    };
+    send_rental_email(&data.db, &data.mailer, &rental, extension_accepted_email).await;
+    Ok(HttpResponse::Ok().finish())
 }
 
-#[get("/extension/delete/{id}")]
+#[utoipa::path(
+    delete,
+    path = "/rentals/{id}/extension",
+    tag = "extensions",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Extension request removed"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+        (status = 404, description = "Rental not found", body = ErrorResponse),
+    )
+)]
 async fn delete_extension_request(
     id: web::Path<i32>,
-    Auth(user): Auth<HAS_TOKEN>,
+    Auth(user, _): Auth<HasToken>,
     data: Data<AppState>,
-) -> HttpResponse {
+) -> Result<HttpResponse, ApiError> {
     let rental_id = id.into_inner();
-    let rental = match data.db.get_rental(rental_id).await {
-        Ok(Some(rental)) => rental,
-        Ok(None) => return HttpResponse::NotFound().body("Related rental not found"),
-        Err(_) => {
-            return HttpResponse::InternalServerError()
-                .body("Failed to get rental data from database")
-        }
-    };
+    let rental = data
+        .db
+        .get_rental(rental_id)
+        .await?
+        .ok_or(ApiError::RentalNotFound)?;
 
     // Non-admin user can only modify their own rentals.
-    if !user.is_admin && rental.user_id != user.sub {
-        return HttpResponse::Forbidden().body("Insufficient privileges");
+    if !user.has_permission(Permission::ManageRentals) && rental.user_id != user.sub {
+        return Err(ApiError::Forbidden);
     }
 
-    let rental = RentalActiveModel {
+    let updated_rental = RentalActiveModel {
         id: Set(rental_id),
         extension_date: Set(None),
         ..Default::default()
     };
+    data.db.save_rental(updated_rental).await?;
 
-    match data.db.save_rental(rental).await {
-        Ok(_) => HttpResponse::Ok().finish(),
-        Err(_) => HttpResponse::InternalServerError()
-            .body("Failed to save extension request into database"),
+    // An admin removing someone else's request means rejecting it.
+    if rental.extension_date.is_some() && rental.user_id != user.sub {
+        send_rental_email(&data.db, &data.mailer, &rental, extension_rejected_email).await;
     }
+    Ok(HttpResponse::Ok().finish())
 }
 
-/// Adds or removes a game from the user's favourites.
-#[get("/favourite/{id}")]
-async fn change_favourite(
+/// Adds a game to the user's favourites.
+#[utoipa::path(
+    put,
+    path = "/me/favourites/{id}",
+    tag = "favourites",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Game is in the favourites"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+    )
+)]
+async fn add_favourite(
     id: web::Path<i32>,
-    Auth(user): Auth<HAS_TOKEN>,
+    Auth(user, _): Auth<HasToken>,
     data: Data<AppState>,
-) -> HttpResponse {
+) -> Result<HttpResponse, ApiError> {
     let game_id = id.into_inner();
-    match data.db.is_favourite(user.sub, game_id).await {
-        Ok(true) => {
-            match data.db.delete_favourite(user.sub, game_id).await {
-                Ok(_) => HttpResponse::Ok().finish(),
-                Err(_) => {
-                    HttpResponse::InternalServerError().finish() // Failed to delete favourite from the database
-                }
+    if !data.db.is_favourite(user.sub, game_id).await? {
+        let favourite = FavouriteActiveModel {
+            user_id: Set(user.sub),
+            game_id: Set(game_id),
+        };
+        data.db.save_favourite(favourite).await?;
+    }
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Removes a game from the user's favourites.
+#[utoipa::path(
+    delete,
+    path = "/me/favourites/{id}",
+    tag = "favourites",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Game is not in the favourites"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+    )
+)]
+async fn remove_favourite(
+    id: web::Path<i32>,
+    Auth(user, _): Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let game_id = id.into_inner();
+    data.db.delete_favourite(user.sub, game_id).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Adds the user to the waitlist of a game that has no copies available at the moment.
+#[utoipa::path(
+    post,
+    path = "/me/waitlist/{id}",
+    tag = "waitlist",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Joined the waitlist"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 409, description = "Already in the waitlist or a copy is available", body = ErrorResponse),
+    )
+)]
+async fn join_waitlist(
+    id: web::Path<i32>,
+    Auth(user, _): Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let game_id = id.into_inner();
+    data.db
+        .expire_waitlist_holds(Utc::now().naive_utc())
+        .await?;
+
+    if data
+        .db
+        .get_waitlist_entry(user.sub, game_id)
+        .await?
+        .is_some()
+    {
+        return Err(ApiError::AlreadyInWaitlist);
+    }
+    if data
+        .db
+        .get_free_game_copy(game_id, today(), today(), user.sub)
+        .await?
+        .is_some()
+    {
+        return Err(ApiError::GameAvailable);
+    }
+
+    data.db.join_waitlist(user.sub, game_id).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[utoipa::path(
+    delete,
+    path = "/me/waitlist/{id}",
+    tag = "waitlist",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Left the waitlist"),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+    )
+)]
+async fn leave_waitlist(
+    id: web::Path<i32>,
+    Auth(user, _): Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let game_id = id.into_inner();
+    data.db.leave_waitlist(user.sub, game_id).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[utoipa::path(
+    get,
+    path = "/me/waitlist",
+    tag = "waitlist",
+    params(PageParams),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Page of the waitlist entries of the user", body = Page<GetWaitlistQueryResult>),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+    )
+)]
+async fn get_my_waitlist(
+    page: web::Query<PageParams>,
+    Auth(user, _): Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    data.db
+        .expire_waitlist_holds(Utc::now().naive_utc())
+        .await?;
+
+    let waitlist = data
+        .db
+        .get_user_waitlist(user.sub, page.into_inner())
+        .await?;
+    Ok(HttpResponse::Ok().json(waitlist))
+}
+
+/// Returns the user's position in the waitlist of the given game.
+#[utoipa::path(
+    get,
+    path = "/me/waitlist/{id}",
+    tag = "waitlist",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Waitlist entry of the user", body = GetWaitlistQueryResult),
+        (status = 401, description = "Missing, invalid or revoked token", body = ErrorResponse),
+        (status = 404, description = "Waitlist entry not found", body = ErrorResponse),
+    )
+)]
+async fn get_waitlist_position(
+    id: web::Path<i32>,
+    Auth(user, _): Auth<HasToken>,
+    data: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let game_id = id.into_inner();
+    data.db
+        .expire_waitlist_holds(Utc::now().naive_utc())
+        .await?;
+
+    let entry = data
+        .db
+        .get_user_waitlist_position(user.sub, game_id)
+        .await?
+        .ok_or(ApiError::NotInWaitlist)?;
+    Ok(HttpResponse::Ok().json(entry))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mailer::{Email, MailerError, MemoryMailer};
+    use crate::media_store::LocalMediaStore;
+    use actix_web::http::{Method, StatusCode};
+    use actix_web::test;
+    use serde_json::json;
+
+    /// Mailer failing to send anything, like one with an unreachable SMTP server.
+    #[derive(Debug)]
+    struct FailingMailer;
+
+    impl Mailer for FailingMailer {
+        fn send(&self, _email: &Email) -> Result<(), MailerError> {
+            Err(MailerError::Canceled)
+        }
+    }
+
+    /// State of a server with an empty in-memory database, sending emails with the given mailer.
+    async fn test_state(mailer: Arc<dyn Mailer>) -> AppState {
+        std::env::set_var("FRONTEND_URL", "http://localhost:3000");
+        std::env::set_var("JWT_SECRET", "test-secret");
+        let config = Config::from_env();
+        AppState {
+            db: DatabaseManager::new("sqlite::memory:").await.unwrap(),
+            mailer,
+            media: Arc::new(LocalMediaStore::new(std::env::temp_dir().to_str().unwrap())),
+            rate_limiter: RateLimiter::new(Arc::new(MemoryRateLimitStore::default()), &config),
+            config,
+        }
+    }
+
+    /// Starts the `/api/v2` scope of a server with the given state.
+    macro_rules! test_app {
+        ($state:expr) => {
+            test::init_service(
+                App::new()
+                    .app_data(Data::new($state))
+                    .app_data(
+                        JsonConfig::default().error_handler(|err, _| {
+                            ApiError::InvalidRequest(err.to_string()).into()
+                        }),
+                    )
+                    .service(web::scope("/api/v2").configure(configure_api)),
+            )
+            .await
+        };
+    }
+
+    /// Waits until the given number of emails is sent in the background.
+    async fn wait_for_emails(mailer: &MemoryMailer, count: usize) -> Vec<Email> {
+        for _ in 0..100 {
+            let sent = mailer.sent();
+            if sent.len() >= count {
+                return sent;
             }
+            actix_web::rt::time::sleep(std::time::Duration::from_millis(10)).await;
         }
-        Ok(false) => {
-            let favourite = FavouriteActiveModel {
-                user_id: Set(user.sub),
-                game_id: Set(game_id),
-            };
-            match data.db.save_favourite(favourite).await {
-                Ok(_) => HttpResponse::Ok().finish(),
-                Err(_) => {
-                    HttpResponse::InternalServerError().finish() // Failed to save favourite into the database
+        panic!("Expected {} emails, got {:?}", count, mailer.sent());
+    }
+
+    /// Creates a user with the given role and a session for them, returning its `Authorization` header.
+    async fn sign_in(db: &DatabaseManager, id: i32, role_id: i32) -> (header::HeaderName, String) {
+        let user = db
+            .insert_user(UserActiveModel {
+                id: Set(id),
+                name: Set("Jan".into()),
+                surname: Set("Kowalski".into()),
+                email: Set(format!("user{}@example.com", id)),
+                password_hash: Set(String::new()),
+                role_id: Set(role_id),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let now = Utc::now().naive_utc();
+        let session = db
+            .insert_session(SessionActiveModel {
+                user_id: Set(user.id),
+                token_hash: Set(String::new()),
+                remember_me: Set(false),
+                created_at: Set(now),
+                last_used_at: Set(now),
+                expires_at: Set(now + Duration::days(1)),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let token = generate_jwt(user.id, user.token_version, session.id).unwrap();
+        (header::AUTHORIZATION, format!("Bearer {}", token))
+    }
+
+    /// Books the only copy of a new game for the user and returns the rental's ID.
+    async fn book_game(
+        db: &DatabaseManager,
+        user_id: i32,
+        from: Date,
+        to: Date,
+        picked_up: bool,
+    ) -> i32 {
+        db.save_board_game(BoardGameActiveModel {
+            title: Set("Catan".into()),
+            weight: Set(200),
+            photo_filename: Set(String::new()),
+            min_players: Set(2),
+            max_players: Set(4),
+            min_playtime: Set(30),
+            max_playtime: Set(60),
+            additional_info: Set(None),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        db.save_rental(RentalActiveModel {
+            copy_id: Set(1),
+            user_id: Set(user_id),
+            rental_date: Set(from),
+            return_date: Set(to),
+            picked_up: Set(picked_up),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        1
+    }
+
+    /// Request changing the dates of the rental.
+    fn reschedule(
+        auth: &(header::HeaderName, String),
+        id: i32,
+        from: Date,
+        to: Date,
+    ) -> test::TestRequest {
+        test::TestRequest::put()
+            .uri(&format!("/api/v2/rentals/{}", id))
+            .insert_header(auth.clone())
+            .set_json(json!({ "rental_date": from, "return_date": to }))
+    }
+
+    fn proxied_request(headers: &[(&str, &str)]) -> HttpRequest {
+        let mut req = test::TestRequest::default().peer_addr("10.0.0.1:4711".parse().unwrap());
+        for &(name, value) in headers {
+            req = req.insert_header((name, value));
+        }
+        req.to_http_request()
+    }
+
+    #[actix_web::test]
+    async fn list_items_are_sent_with_photo_urls() {
+        let db = DatabaseManager::new("sqlite::memory:").await.unwrap();
+        db.save_board_game(BoardGameActiveModel {
+            title: Set("Carcassonne".into()),
+            weight: Set(200),
+            photo_filename: Set("abc.png".into()),
+            min_players: Set(2),
+            max_players: Set(5),
+            min_playtime: Set(30),
+            max_playtime: Set(45),
+            additional_info: Set(None),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        let board_games = db
+            .get_board_games(1, PageParams::default())
+            .await
+            .unwrap()
+            .map(WithPhotoUrls::from);
+        let json = serde_json::to_value(board_games).unwrap();
+        let item = &json["items"][0];
+        assert_eq!(item["title"], "Carcassonne");
+        assert_eq!(item["photo"]["original"], "/static/abc.png");
+        assert_eq!(
+            item["photo"]["thumbnail"]["webp"],
+            "/static/thumbnail/abc.webp"
+        );
+        assert_eq!(item["photo"]["medium"]["png"], "/static/medium/abc.png");
+        assert!(item.get("photoFilename").is_none());
+    }
+
+    #[actix_web::test]
+    async fn waitlist_is_paginated_with_queue_positions() {
+        let db = DatabaseManager::new("sqlite::memory:").await.unwrap();
+        for (id, email) in [(1, "jan@example.com"), (2, "anna@example.com")] {
+            db.insert_user(UserActiveModel {
+                id: Set(id),
+                name: Set("Jan".into()),
+                surname: Set("Kowalski".into()),
+                email: Set(email.into()),
+                password_hash: Set(String::new()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        }
+        for title in ["Catan", "Dixit"] {
+            db.save_board_game(BoardGameActiveModel {
+                title: Set(title.into()),
+                weight: Set(200),
+                photo_filename: Set(String::new()),
+                min_players: Set(2),
+                max_players: Set(4),
+                min_playtime: Set(30),
+                max_playtime: Set(60),
+                additional_info: Set(None),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        }
+        db.join_waitlist(2, 2).await.unwrap();
+        db.join_waitlist(1, 1).await.unwrap();
+        db.join_waitlist(1, 2).await.unwrap();
+
+        let page = PageParams {
+            page: Some(2),
+            limit: Some(1),
+        };
+        let waitlist = serde_json::to_value(db.get_user_waitlist(1, page).await.unwrap()).unwrap();
+        assert_eq!(waitlist["totalItems"], 2);
+        assert_eq!(waitlist["items"].as_array().unwrap().len(), 1);
+        assert_eq!(waitlist["items"][0]["title"], "Dixit");
+        assert_eq!(waitlist["items"][0]["position"], 2);
+        assert_eq!(waitlist["items"][0]["queueLength"], 2);
+
+        let entry = db.get_user_waitlist_position(2, 2).await.unwrap().unwrap();
+        assert_eq!(serde_json::to_value(entry).unwrap()["position"], 1);
+        assert!(db.get_user_waitlist_position(2, 1).await.unwrap().is_none());
+    }
+
+    #[actix_web::test]
+    async fn client_ip_ignores_headers_of_untrusted_peers() {
+        let req = proxied_request(&[("X-Forwarded-For", "203.0.113.7")]);
+        assert_eq!(client_ip(&req, &[]), "10.0.0.1");
+    }
+
+    #[actix_web::test]
+    async fn client_ip_skips_trusted_proxies() {
+        let proxies = ["10.0.0.1".parse().unwrap(), "10.0.0.2".parse().unwrap()];
+        // The first address could have been sent by the client, so it is not trusted.
+        let req = proxied_request(&[("X-Forwarded-For", "192.0.2.1, 203.0.113.7, 10.0.0.2")]);
+        assert_eq!(client_ip(&req, &proxies), "203.0.113.7");
+
+        let req = proxied_request(&[(
+            "Forwarded",
+            r#"for=192.0.2.1;proto=https, for="[2001:db8::1]:4711", for=10.0.0.2:80"#,
+        )]);
+        assert_eq!(client_ip(&req, &proxies), "2001:db8::1");
+
+        // Without the headers, the request is attributed to the proxy itself.
+        assert_eq!(client_ip(&proxied_request(&[]), &proxies), "10.0.0.1");
+    }
+
+    fn registration(id: i32, email: &str) -> serde_json::Value {
+        json!({
+            "id": id,
+            "name": "Jan",
+            "surname": "Kowalski",
+            "email": email,
+            "password": "planszowki1",
+        })
+    }
+
+    #[actix_web::test]
+    async fn register_sends_confirmation_email() {
+        let mailer = Arc::new(MemoryMailer::default());
+        let state = test_state(mailer.clone()).await;
+        let db = state.db.clone();
+        let app = test_app!(state);
+
+        let req = test::TestRequest::post()
+            .uri("/api/v2/users")
+            .set_json(registration(1, "jan@example.com"))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+        let token = db.get_user(1).await.unwrap().unwrap().confirmation_token;
+        let sent = mailer.sent();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].to, "jan@example.com");
+        // The link leads to the frontend, which confirms the account through the API.
+        let link = format!(
+            "http://localhost:3000/confirm_account?id=1&token={}",
+            token.unwrap()
+        );
+        assert!(sent[0].body.contains(&link));
+    }
+
+    #[actix_web::test]
+    async fn register_is_undone_when_email_fails() {
+        let state = test_state(Arc::new(FailingMailer)).await;
+        let db = state.db.clone();
+        let app = test_app!(state);
+
+        for _ in 0..2 {
+            let req = test::TestRequest::post()
+                .uri("/api/v2/users")
+                .set_json(registration(1, "jan@example.com"))
+                .to_request();
+            let status = test::call_service(&app, req).await.status();
+            // Retrying fails the same way, instead of reporting the ID and email as taken.
+            assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+            assert!(db.get_user(1).await.unwrap().is_none());
+        }
+    }
+
+    #[actix_web::test]
+    async fn forgot_password_responds_the_same_for_any_email() {
+        let mailer = Arc::new(MemoryMailer::default());
+        let state = test_state(mailer.clone()).await;
+        let app = test_app!(state);
+
+        let req = test::TestRequest::post()
+            .uri("/api/v2/users")
+            .set_json(registration(1, "jan@example.com"))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+        for email in ["nikt@example.com", "jan@example.com"] {
+            let req = test::TestRequest::post()
+                .uri("/api/v2/password_reset_emails")
+                .set_json(json!({ "email": email }))
+                .to_request();
+            let res = test::call_service(&app, req).await;
+            assert_eq!(res.status(), StatusCode::OK);
+            assert!(test::read_body(res).await.is_empty());
+        }
+
+        let sent = wait_for_emails(&mailer, 2).await;
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[1].to, "jan@example.com");
+    }
+
+    #[actix_web::test]
+    async fn resend_confirmation_responds_the_same_for_any_email() {
+        let mailer = Arc::new(MemoryMailer::default());
+        let state = test_state(mailer.clone()).await;
+        let app = test_app!(state);
+
+        let req = test::TestRequest::post()
+            .uri("/api/v2/users")
+            .set_json(registration(1, "jan@example.com"))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+        for email in ["nikt@example.com", "jan@example.com"] {
+            let req = test::TestRequest::post()
+                .uri("/api/v2/confirmation_emails")
+                .set_json(json!({ "email": email }))
+                .to_request();
+            let res = test::call_service(&app, req).await;
+            assert_eq!(res.status(), StatusCode::OK);
+            assert!(test::read_body(res).await.is_empty());
+        }
+        let sent = wait_for_emails(&mailer, 2).await;
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[1].to, "jan@example.com");
+
+        // Two requests were already counted towards the limit.
+        for expected in [StatusCode::OK; 3]
+            .into_iter()
+            .chain([StatusCode::TOO_MANY_REQUESTS])
+        {
+            let req = test::TestRequest::post()
+                .uri("/api/v2/confirmation_emails")
+                .set_json(json!({ "email": "nikt@example.com" }))
+                .to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), expected);
+        }
+    }
+
+    #[actix_web::test]
+    async fn forgot_password_hides_email_failures() {
+        let state = test_state(Arc::new(FailingMailer)).await;
+        let db = state.db.clone();
+        let app = test_app!(state);
+
+        let user = UserActiveModel {
+            id: Set(1),
+            name: Set("Jan".into()),
+            surname: Set("Kowalski".into()),
+            email: Set("jan@example.com".into()),
+            password_hash: Set(hash_password("planszowki1".into()).unwrap()),
+            ..Default::default()
+        };
+        db.insert_user(user).await.unwrap();
+        let req = test::TestRequest::post()
+            .uri("/api/v2/password_reset_emails")
+            .set_json(json!({ "email": "jan@example.com" }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn password_can_be_reset_with_emailed_token() {
+        let mailer = Arc::new(MemoryMailer::default());
+        let state = test_state(mailer.clone()).await;
+        let app = test_app!(state);
+
+        let req = test::TestRequest::post()
+            .uri("/api/v2/users")
+            .set_json(registration(1, "jan@example.com"))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+        let req = test::TestRequest::post()
+            .uri("/api/v2/password_reset_emails")
+            .set_json(json!({ "email": "jan@example.com" }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+        let sent = wait_for_emails(&mailer, 2).await;
+        let token = sent[1].body.split("token=").nth(1).unwrap();
+        let token = token.split_whitespace().next().unwrap();
+        // A password rejected by the policy doesn't use up the token.
+        let req = test::TestRequest::post()
+            .uri("/api/v2/password_resets")
+            .set_json(json!({ "token": token, "password": "haslo" }))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+        let req = test::TestRequest::post()
+            .uri("/api/v2/password_resets")
+            .set_json(json!({ "token": token, "password": "nowehaslo2" }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+        // Reset tokens are single-use.
+        let req = test::TestRequest::post()
+            .uri("/api/v2/password_resets")
+            .set_json(json!({ "token": token, "password": "nowehaslo3" }))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[actix_web::test]
+    async fn penalized_users_cannot_reschedule_rentals() {
+        let state = test_state(Arc::new(MemoryMailer::default())).await;
+        let db = state.db.clone();
+        let app = test_app!(state);
+        let auth = sign_in(&db, 1, 1).await;
+        let from = today() + Days::new(1);
+        let id = book_game(&db, 1, from, from + Days::new(2), false).await;
+
+        // One point more than the threshold.
+        db.insert_penalty_event(penalty_event(
+            1,
+            None,
+            PenaltyReason::Manual,
+            3,
+            String::new(),
+            1,
+        ))
+        .await
+        .unwrap();
+        let status = test::call_service(
+            &app,
+            reschedule(&auth, id, from, from + Days::new(3)).to_request(),
+        )
+        .await
+        .status();
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    #[actix_web::test]
+    async fn picked_up_rentals_are_rescheduled_only_by_staff() {
+        let state = test_state(Arc::new(MemoryMailer::default())).await;
+        let db = state.db.clone();
+        let app = test_app!(state);
+        let member = sign_in(&db, 1, 1).await;
+        let desk_staff = sign_in(&db, 2, 2).await;
+        let from = today() - Days::new(1);
+        let id = book_game(&db, 1, from, from + Days::new(2), true).await;
+
+        // Members have to ask for an extension instead.
+        let status = test::call_service(
+            &app,
+            reschedule(&member, id, from, from + Days::new(3)).to_request(),
+        )
+        .await
+        .status();
+        assert_eq!(status, StatusCode::CONFLICT);
+        let status = test::call_service(
+            &app,
+            reschedule(&desk_staff, id, from, from + Days::new(3)).to_request(),
+        )
+        .await
+        .status();
+        assert_eq!(status, StatusCode::OK);
+        let rental = db.get_rental(id).await.unwrap().unwrap();
+        assert_eq!(rental.return_date, from + Days::new(3));
+    }
+
+    #[actix_web::test]
+    async fn rentals_cannot_be_moved_to_the_past() {
+        let state = test_state(Arc::new(MemoryMailer::default())).await;
+        let db = state.db.clone();
+        let app = test_app!(state);
+        let auth = sign_in(&db, 1, 1).await;
+        let from = today() + Days::new(1);
+        let id = book_game(&db, 1, from, from + Days::new(2), false).await;
+
+        let yesterday = today() - Days::new(1);
+        let status = test::call_service(&app, reschedule(&auth, id, yesterday, from).to_request())
+            .await
+            .status();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let status = test::call_service(&app, reschedule(&auth, id, today(), from).to_request())
+            .await
+            .status();
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    /// `api_routes!` and `api_handlers!` document every registered handler, so it's enough
+    /// to check that every documented operation is routed with its method.
+    #[actix_web::test]
+    async fn openapi_spec_matches_routes() {
+        let app = test::init_service(
+            App::new()
+                .app_data(
+                    PathConfig::default()
+                        .error_handler(|err, _| ApiError::InvalidRequest(err.to_string()).into()),
+                )
+                .service(web::scope("/api/v2").configure(configure_api))
+                .service(web::scope("/api").configure(api_v1::configure)),
+        )
+        .await;
+
+        let specs = [
+            ("/api/v2", ApiDoc::openapi()),
+            ("/api", api_v1::ApiDoc::openapi()),
+        ];
+        for (scope, spec) in specs {
+            let mut operations = 0;
+            for (path, item) in spec.paths.paths {
+                // Path parameters are filled in with anything, the handlers fail before using them.
+                let uri = path
+                    .split('/')
+                    .map(|segment| {
+                        if segment.starts_with('{') {
+                            "1"
+                        } else {
+                            segment
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join("/");
+                let methods = [
+                    (Method::GET, &item.get),
+                    (Method::POST, &item.post),
+                    (Method::PUT, &item.put),
+                    (Method::PATCH, &item.patch),
+                    (Method::DELETE, &item.delete),
+                ];
+                for (method, _) in methods.iter().filter(|(_, operation)| operation.is_some()) {
+                    let req = test::TestRequest::default()
+                        .method(method.clone())
+                        .uri(&format!("{}{}", scope, uri))
+                        .to_request();
+                    let status = test::call_service(&app, req).await.status();
+                    assert!(
+                        status != StatusCode::NOT_FOUND && status != StatusCode::METHOD_NOT_ALLOWED,
+                        "{} {}{} is documented but not routed",
+                        method,
+                        scope,
+                        path
+                    );
+                    operations += 1;
                 }
             }
+            assert!(operations > 0);
         }
-        Err(_) => HttpResponse::InternalServerError().finish(), // Failed to check if the game is a favourite
     }
 }
@@ -0,0 +1,49 @@
+use utoipa::openapi::path::Operation;
+use utoipa::openapi::security::{Http, HttpAuthScheme, SecurityScheme};
+use utoipa::openapi::{Deprecated, OpenApi};
+use utoipa::Modify;
+
+/// Name of the security scheme of the access tokens. Endpoints requiring a permission
+/// list it as the scheme's role, e.g. `("bearer_auth" = ["manage_users"])`.
+pub(crate) const BEARER_AUTH: &str = "bearer_auth";
+
+/// Adds the bearer token scheme used by `Auth` to the specification.
+pub(crate) struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            BEARER_AUTH,
+            SecurityScheme::Http(
+                Http::builder()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .description(Some(
+                        "Access token returned when logging in or refreshing the session.",
+                    ))
+                    .build(),
+            ),
+        );
+    }
+}
+
+/// Marks all the operations of the specification as deprecated.
+pub(crate) struct DeprecatedAddon;
+
+impl Modify for DeprecatedAddon {
+    fn modify(&self, openapi: &mut OpenApi) {
+        for item in openapi.paths.paths.values_mut() {
+            let operations: [&mut Option<Operation>; 5] = [
+                &mut item.get,
+                &mut item.post,
+                &mut item.put,
+                &mut item.patch,
+                &mut item.delete,
+            ];
+            for operation in operations.into_iter().flatten() {
+                operation.deprecated = Some(Deprecated::True);
+            }
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use crate::db_manager::DatabaseManager;
+use chrono::{Days, Duration, Utc};
+use entity::penalty_event::ActiveModel as PenaltyEventActiveModel;
+use entity::rental::Model as RentalModel;
+use entity::sea_orm_active_enums::PenaltyReason;
+use sea_orm::prelude::Date;
+use sea_orm::ActiveValue::Set;
+use sea_orm::DbErr;
+
+/// A late return is given one point for every started period of this many days.
+const LATE_RETURN_DAYS_PER_POINT: i64 = 3;
+const LATE_RETURN_MAX_POINTS: i32 = 3;
+const NO_SHOW_POINTS: i32 = 1;
+/// Number of days after the rental date within which the game has to be picked up.
+const NO_SHOW_GRACE_DAYS: u64 = 2;
+/// Number of days after which automatically given penalty points stop counting.
+pub(crate) const PENALTY_EXPIRY_DAYS: i64 = 180;
+
+/// Calculates the penalty points for returning a game on `returned_on`
+/// instead of `return_date`, scaled by the number of days overdue.
+pub(crate) fn late_return_points(return_date: Date, returned_on: Date) -> i32 {
+    let days_overdue = (returned_on - return_date).num_days();
+    if days_overdue <= 0 {
+        return 0;
+    }
+
+    let points = (days_overdue + LATE_RETURN_DAYS_PER_POINT - 1) / LATE_RETURN_DAYS_PER_POINT;
+    points.min(LATE_RETURN_MAX_POINTS as i64) as i32
+}
+
+/// Builds a penalty event that expires after the given number of days.
+pub(crate) fn penalty_event(
+    user_id: i32,
+    rental_id: Option<i32>,
+    reason: PenaltyReason,
+    points: i32,
+    description: String,
+    expiry_days: i64,
+) -> PenaltyEventActiveModel {
+    let now = Utc::now().naive_utc();
+    PenaltyEventActiveModel {
+        user_id: Set(user_id),
+        rental_id: Set(rental_id),
+        reason: Set(reason),
+        points: Set(points),
+        description: Set(Some(description)),
+        created_at: Set(now),
+        expires_at: Set(Some(now + Duration::days(expiry_days))),
+        ..Default::default()
+    }
+}
+
+/// Builds the penalty event for returning the rental on `returned_on`,
+/// if it was returned after its return date.
+pub(crate) fn late_return_penalty(
+    rental: &RentalModel,
+    returned_on: Date,
+) -> Option<PenaltyEventActiveModel> {
+    let points = late_return_points(rental.return_date, returned_on);
+    if points == 0 {
+        return None;
+    }
+
+    let description = format!(
+        "Zwrot {} dni po terminie ({})",
+        (returned_on - rental.return_date).num_days(),
+        rental.return_date
+    );
+    Some(penalty_event(
+        rental.user_id,
+        Some(rental.id),
+        PenaltyReason::LateReturn,
+        points,
+        description,
+        PENALTY_EXPIRY_DAYS,
+    ))
+}
+
+/// Cancels reservations that haven't been picked up within `NO_SHOW_GRACE_DAYS` days
+/// from their rental date and gives penalty points to their users.
+/// Returns the cancelled reservations.
+pub(crate) async fn penalize_no_shows(
+    db: &DatabaseManager,
+    today: Date,
+) -> Result<Vec<RentalModel>, DbErr> {
+    let deadline = today - Days::new(NO_SHOW_GRACE_DAYS);
+    let rentals = db.get_unclaimed_rentals(deadline).await?;
+
+    for rental in &rentals {
+        let description = format!("Nieodebrana rezerwacja z dnia {}", rental.rental_date);
+        let penalty = penalty_event(
+            rental.user_id,
+            Some(rental.id),
+            PenaltyReason::NoShow,
+            NO_SHOW_POINTS,
+            description,
+            PENALTY_EXPIRY_DAYS,
+        );
+        db.archive_rental(rental.id, None, Some(penalty)).await?;
+    }
+    Ok(rentals)
+}
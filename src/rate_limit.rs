@@ -0,0 +1,281 @@
+use crate::config::Config;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// Number of login attempts allowed from a single IP address within `LOGIN_WINDOW`.
+const LOGIN_ATTEMPTS_PER_IP: u32 = 20;
+const LOGIN_WINDOW: Duration = Duration::minutes(15);
+/// Number of registrations allowed from a single IP address within `REGISTRATION_WINDOW`.
+const REGISTRATIONS_PER_IP: u32 = 5;
+const REGISTRATION_WINDOW: Duration = Duration::hours(1);
+/// Number of confirmation emails that can be requested from a single IP address within `CONFIRMATION_EMAIL_WINDOW`.
+const CONFIRMATION_EMAILS_PER_IP: u32 = 5;
+const CONFIRMATION_EMAIL_WINDOW: Duration = Duration::hours(1);
+/// Window in which failed logins to a single account are counted.
+const FAILED_LOGINS_WINDOW: Duration = Duration::minutes(15);
+/// Number of expired counters after which the in-memory store cleans them up.
+const MEMORY_STORE_CLEANUP_SIZE: usize = 10_000;
+
+/// Value of a counter along with the time it expires at.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Counter {
+    pub(crate) value: u32,
+    pub(crate) expires_at: DateTime<Utc>,
+}
+
+/// Storage of expiring counters used for rate limiting.
+pub(crate) trait RateLimitStore: Send + Sync + fmt::Debug {
+    /// Increments the counter of the key and returns it. A counter that doesn't exist
+    /// or has expired starts again from zero and expires after `ttl`.
+    fn increment(&self, key: &str, ttl: Duration, now: DateTime<Utc>) -> Counter;
+
+    /// Returns the counter of the key, if it exists and hasn't expired.
+    fn get(&self, key: &str, now: DateTime<Utc>) -> Option<Counter>;
+
+    fn remove(&self, key: &str);
+}
+
+/// Keeps the counters in memory, so they are lost on restart and not shared between instances.
+#[derive(Debug, Default)]
+pub(crate) struct MemoryRateLimitStore {
+    counters: Mutex<HashMap<String, Counter>>,
+}
+
+impl RateLimitStore for MemoryRateLimitStore {
+    fn increment(&self, key: &str, ttl: Duration, now: DateTime<Utc>) -> Counter {
+        let mut counters = self.counters.lock().unwrap();
+        if counters.len() >= MEMORY_STORE_CLEANUP_SIZE {
+            counters.retain(|_, counter| counter.expires_at > now);
+        }
+
+        let counter = counters.entry(key.to_string()).or_insert(Counter {
+            value: 0,
+            expires_at: now + ttl,
+        });
+        if counter.expires_at <= now {
+            *counter = Counter {
+                value: 0,
+                expires_at: now + ttl,
+            };
+        }
+        counter.value += 1;
+        *counter
+    }
+
+    fn get(&self, key: &str, now: DateTime<Utc>) -> Option<Counter> {
+        let counters = self.counters.lock().unwrap();
+        counters
+            .get(key)
+            .filter(|counter| counter.expires_at > now)
+            .copied()
+    }
+
+    fn remove(&self, key: &str) {
+        self.counters.lock().unwrap().remove(key);
+    }
+}
+
+/// Actions limited per IP address.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum LimitedAction {
+    Login,
+    Register,
+    ConfirmationEmail,
+}
+
+impl LimitedAction {
+    fn limit(self) -> (&'static str, u32, Duration) {
+        match self {
+            LimitedAction::Login => ("login", LOGIN_ATTEMPTS_PER_IP, LOGIN_WINDOW),
+            LimitedAction::Register => ("register", REGISTRATIONS_PER_IP, REGISTRATION_WINDOW),
+            LimitedAction::ConfirmationEmail => (
+                "confirmation_email",
+                CONFIRMATION_EMAILS_PER_IP,
+                CONFIRMATION_EMAIL_WINDOW,
+            ),
+        }
+    }
+}
+
+/// Throttles requests per IP address and locks accounts after repeated failed logins.
+#[derive(Debug, Clone)]
+pub(crate) struct RateLimiter {
+    store: Arc<dyn RateLimitStore>,
+    max_failed_logins: u32,
+    lockout: Duration,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(store: Arc<dyn RateLimitStore>, config: &Config) -> Self {
+        Self {
+            store,
+            max_failed_logins: config.max_failed_logins,
+            lockout: Duration::minutes(config.lockout_minutes),
+        }
+    }
+
+    /// Counts a request from the given IP address.
+    /// If there were too many, returns the time after which requests are accepted again.
+    pub(crate) fn check_ip(
+        &self,
+        action: LimitedAction,
+        ip: &str,
+        now: DateTime<Utc>,
+    ) -> Result<(), DateTime<Utc>> {
+        let (name, limit, window) = action.limit();
+        let counter = self
+            .store
+            .increment(&format!("{}:ip:{}", name, ip), window, now);
+        if counter.value > limit {
+            Err(counter.expires_at)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns the time the account of the given user is locked until, if it is locked.
+    pub(crate) fn locked_until(&self, user_id: i32, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.store
+            .get(&lock_key(user_id), now)
+            .map(|counter| counter.expires_at)
+    }
+
+    /// Counts a failed login to the account of the given user, locking it after too many.
+    /// Returns whether the account has just been locked.
+    pub(crate) fn record_failed_login(&self, user_id: i32, now: DateTime<Utc>) -> bool {
+        let failures = self
+            .store
+            .increment(&failures_key(user_id), FAILED_LOGINS_WINDOW, now);
+        if failures.value < self.max_failed_logins {
+            return false;
+        }
+
+        self.store.remove(&failures_key(user_id));
+        self.store.increment(&lock_key(user_id), self.lockout, now);
+        true
+    }
+
+    /// Forgets the failed logins to the account of the given user.
+    pub(crate) fn record_successful_login(&self, user_id: i32) {
+        self.store.remove(&failures_key(user_id));
+    }
+
+    /// Unlocks the account of the given user and forgets their failed logins.
+    pub(crate) fn unlock_account(&self, user_id: i32) {
+        self.store.remove(&lock_key(user_id));
+        self.store.remove(&failures_key(user_id));
+    }
+}
+
+fn failures_key(user_id: i32) -> String {
+    format!("login:failures:{}", user_id)
+}
+
+fn lock_key(user_id: i32) -> String {
+    format!("login:lock:{}", user_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const MAX_FAILED_LOGINS: u32 = 3;
+    const LOCKOUT: Duration = Duration::minutes(30);
+
+    fn rate_limiter() -> RateLimiter {
+        RateLimiter {
+            store: Arc::new(MemoryRateLimitStore::default()),
+            max_failed_logins: MAX_FAILED_LOGINS,
+            lockout: LOCKOUT,
+        }
+    }
+
+    fn start() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 3, 22, 12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn ip_limit_resets_after_window() {
+        let limiter = rate_limiter();
+        let now = start();
+        for _ in 0..LOGIN_ATTEMPTS_PER_IP {
+            assert_eq!(
+                limiter.check_ip(LimitedAction::Login, "192.0.2.1", now),
+                Ok(())
+            );
+        }
+        assert_eq!(
+            limiter.check_ip(LimitedAction::Login, "192.0.2.1", now),
+            Err(now + LOGIN_WINDOW)
+        );
+        // Other addresses and actions are counted separately.
+        assert_eq!(
+            limiter.check_ip(LimitedAction::Login, "192.0.2.2", now),
+            Ok(())
+        );
+        assert_eq!(
+            limiter.check_ip(LimitedAction::Register, "192.0.2.1", now),
+            Ok(())
+        );
+
+        let later = now + LOGIN_WINDOW;
+        assert_eq!(
+            limiter.check_ip(LimitedAction::Login, "192.0.2.1", later),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn account_is_locked_after_too_many_failed_logins() {
+        let limiter = rate_limiter();
+        let now = start();
+        for _ in 1..MAX_FAILED_LOGINS {
+            assert!(!limiter.record_failed_login(1, now));
+        }
+        assert_eq!(limiter.locked_until(1, now), None);
+
+        assert!(limiter.record_failed_login(1, now));
+        assert_eq!(limiter.locked_until(1, now), Some(now + LOCKOUT));
+        assert_eq!(limiter.locked_until(2, now), None);
+        assert_eq!(limiter.locked_until(1, now + LOCKOUT), None);
+    }
+
+    #[test]
+    fn failed_logins_expire_after_window() {
+        let limiter = rate_limiter();
+        let now = start();
+        for _ in 1..MAX_FAILED_LOGINS {
+            assert!(!limiter.record_failed_login(1, now));
+        }
+        assert!(!limiter.record_failed_login(1, now + FAILED_LOGINS_WINDOW));
+    }
+
+    #[test]
+    fn successful_login_resets_failed_logins() {
+        let limiter = rate_limiter();
+        let now = start();
+        for _ in 1..MAX_FAILED_LOGINS {
+            assert!(!limiter.record_failed_login(1, now));
+        }
+        limiter.record_successful_login(1);
+        for _ in 1..MAX_FAILED_LOGINS {
+            assert!(!limiter.record_failed_login(1, now));
+        }
+        assert_eq!(limiter.locked_until(1, now), None);
+    }
+
+    #[test]
+    fn unlocked_account_starts_counting_again() {
+        let limiter = rate_limiter();
+        let now = start();
+        for _ in 0..MAX_FAILED_LOGINS {
+            limiter.record_failed_login(1, now);
+        }
+        limiter.unlock_account(1);
+        assert_eq!(limiter.locked_until(1, now), None);
+        assert!(!limiter.record_failed_login(1, now));
+    }
+}
@@ -0,0 +1,122 @@
+use crate::api_error::Language;
+use crate::config::Config;
+use crate::db_manager::{DatabaseManager, PENALTY_THRESHOLD};
+use sea_orm::prelude::Date;
+use sea_orm::DbErr;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// Reason for refusing a user a new rental or an extension.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub(crate) enum RentalDenial {
+    Penalized { points: i64, threshold: i64 },
+    TooManyRentals { active: u64, limit: u64 },
+    TooLong { days: i64, limit: i64 },
+}
+
+impl RentalDenial {
+    /// Returns a message that can be shown to the user.
+    pub(crate) fn message(&self, language: Language) -> String {
+        match (self, language) {
+            (RentalDenial::Penalized { points, threshold }, Language::Pl) => format!(
+                "Masz {} punktów karnych (limit: {}), nie możesz wypożyczać gier",
+                points, threshold
+            ),
+            (RentalDenial::Penalized { points, threshold }, Language::En) => format!(
+                "You have {} penalty points (limit: {}), so you cannot rent games",
+                points, threshold
+            ),
+            (RentalDenial::TooManyRentals { limit, .. }, Language::Pl) => {
+                format!("Możesz mieć jednocześnie najwyżej {} wypożyczenia", limit)
+            }
+            (RentalDenial::TooManyRentals { limit, .. }, Language::En) => {
+                format!("You can have at most {} rentals at a time", limit)
+            }
+            (RentalDenial::TooLong { limit, .. }, Language::Pl) => {
+                format!("Wypożyczenie może trwać najwyżej {} dni", limit)
+            }
+            (RentalDenial::TooLong { limit, .. }, Language::En) => {
+                format!("A rental can last at most {} days", limit)
+            }
+        }
+    }
+}
+
+/// Checks whether the user can book a new rental for the given period.
+pub(crate) async fn check_new_rental(
+    db: &DatabaseManager,
+    config: &Config,
+    user_id: i32,
+    rental_date: Date,
+    return_date: Date,
+) -> Result<Option<RentalDenial>, DbErr> {
+    if let Some(denial) = check_penalty(db, user_id).await? {
+        return Ok(Some(denial));
+    }
+
+    let active = db.count_user_rentals(user_id).await?;
+    if active >= config.max_active_rentals {
+        return Ok(Some(RentalDenial::TooManyRentals {
+            active,
+            limit: config.max_active_rentals,
+        }));
+    }
+
+    Ok(check_length(config, rental_date, return_date))
+}
+
+/// Checks whether the user can change an existing rental to the given period.
+pub(crate) async fn check_rental_update(
+    db: &DatabaseManager,
+    config: &Config,
+    user_id: i32,
+    rental_date: Date,
+    return_date: Date,
+) -> Result<Option<RentalDenial>, DbErr> {
+    if let Some(denial) = check_penalty(db, user_id).await? {
+        return Ok(Some(denial));
+    }
+
+    Ok(check_length(config, rental_date, return_date))
+}
+
+/// Checks whether the user can extend a rental lasting from `rental_date` until `extension_date`.
+pub(crate) async fn check_extension(
+    db: &DatabaseManager,
+    config: &Config,
+    user_id: i32,
+    rental_date: Date,
+    extension_date: Date,
+) -> Result<Option<RentalDenial>, DbErr> {
+    if let Some(denial) = check_penalty(db, user_id).await? {
+        return Ok(Some(denial));
+    }
+
+    Ok(check_length(config, rental_date, extension_date))
+}
+
+async fn check_penalty(db: &DatabaseManager, user_id: i32) -> Result<Option<RentalDenial>, DbErr> {
+    let points = db.get_penalty_points(user_id).await?;
+    if points > PENALTY_THRESHOLD {
+        Ok(Some(RentalDenial::Penalized {
+            points,
+            threshold: PENALTY_THRESHOLD,
+        }))
+    } else {
+        Ok(None)
+    }
+}
+
+fn check_length(config: &Config, from: Date, to: Date) -> Option<RentalDenial> {
+    // Both the rental and the return day count.
+    let days = (to - from).num_days() + 1;
+    if days > config.max_rental_days {
+        Some(RentalDenial::TooLong {
+            days,
+            limit: config.max_rental_days,
+        })
+    } else {
+        None
+    }
+}
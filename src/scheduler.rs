@@ -0,0 +1,375 @@
+use crate::config::Config;
+use crate::db_manager::DatabaseManager;
+use crate::emails::{
+    due_reminder_email, extension_expired_email, no_show_email, overdue_email, send_rental_email,
+    RentalTemplate,
+};
+use crate::mailer::Mailer;
+use crate::penalties::penalize_no_shows;
+use chrono::{DateTime, Days, Local, NaiveDate as Date, NaiveDateTime, Utc};
+use entity::rental::{ActiveModel as RentalActiveModel, Model as RentalModel};
+use sea_orm::ActiveValue::Set;
+use sea_orm::DbErr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Source of the current time for the background jobs.
+/// Jobs never read the system time directly, so that they can be run at any given moment.
+pub(crate) trait Clock: Send + Sync {
+    /// Returns the current UTC time.
+    fn now(&self) -> DateTime<Utc>;
+
+    /// Returns the current local date.
+    fn today(&self) -> Date {
+        self.now().with_timezone(&Local).date_naive()
+    }
+}
+
+/// Clock reading the system time.
+pub(crate) struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Something the user of a rental should be told about, produced by the background jobs.
+pub(crate) enum Notice {
+    /// The rental has to be returned tomorrow.
+    DueTomorrow(RentalModel),
+    /// The rental wasn't returned on time.
+    Overdue(RentalModel),
+    /// The reservation wasn't picked up and has been cancelled.
+    NoShow(RentalModel),
+    /// The extension request wasn't answered before the return date and has been dropped.
+    ExtensionExpired(RentalModel),
+}
+
+/// Runs the periodic maintenance jobs in the background of the server.
+pub(crate) struct Scheduler {
+    db: DatabaseManager,
+    mailer: Arc<dyn Mailer>,
+    clock: Arc<dyn Clock>,
+    interval: Duration,
+    unconfirmed_account_days: i64,
+}
+
+impl Scheduler {
+    pub(crate) fn new(
+        db: DatabaseManager,
+        mailer: Arc<dyn Mailer>,
+        clock: Arc<dyn Clock>,
+        config: &Config,
+    ) -> Self {
+        Self {
+            db,
+            mailer,
+            clock,
+            interval: Duration::from_secs(config.jobs_interval_hours.max(1) * 60 * 60),
+            unconfirmed_account_days: config.unconfirmed_account_days,
+        }
+    }
+
+    /// Spawns a task running all jobs right away and then once every interval.
+    /// Has to be called from within the actix runtime.
+    pub(crate) fn start(self) {
+        actix_web::rt::spawn(async move {
+            let mut interval = actix_web::rt::time::interval(self.interval);
+            loop {
+                interval.tick().await;
+                if let Err(err) = self.run_jobs().await {
+                    eprintln!("Background jobs failed: {}", err);
+                }
+            }
+        });
+    }
+
+    /// Runs all jobs once, using the time given by the clock.
+    /// Each job sends its notices as soon as it is done, so that an error in a later job
+    /// doesn't lose them. Returns the notices that were delivered.
+    pub(crate) async fn run_jobs(&self) -> Result<Vec<Notice>, DbErr> {
+        let today = self.clock.today();
+        let mut notices = Vec::new();
+
+        self.db
+            .expire_waitlist_holds(self.clock.now().naive_utc())
+            .await?;
+        notices.extend(self.flag_overdue_rentals(today).await?);
+        notices.extend(self.expire_extension_requests(today).await?);
+        notices.extend(self.cancel_no_shows(today).await?);
+        notices.extend(
+            self.remind_due_rentals(today, self.clock.now().naive_utc())
+                .await?,
+        );
+
+        let confirmation_deadline =
+            self.clock.now() - chrono::Duration::days(self.unconfirmed_account_days);
+        self.db
+            .delete_unconfirmed_users(confirmation_deadline.naive_utc())
+            .await?;
+        self.db
+            .delete_expired_sessions(self.clock.now().naive_utc())
+            .await?;
+        Ok(notices)
+    }
+
+    /// Sends the notice to the user of the rental it is about. Returns whether it was delivered.
+    pub(crate) async fn notify(&self, notice: &Notice) -> bool {
+        let (rental, template): (&RentalModel, RentalTemplate) = match notice {
+            Notice::DueTomorrow(rental) => (rental, due_reminder_email),
+            Notice::Overdue(rental) => (rental, overdue_email),
+            Notice::NoShow(rental) => (rental, no_show_email),
+            Notice::ExtensionExpired(rental) => (rental, extension_expired_email),
+        };
+        send_rental_email(&self.db, &self.mailer, rental, template).await
+    }
+
+    /// Flags the rentals that weren't returned on time.
+    /// A rental is flagged only once its user has been told, so that the email is retried
+    /// on the next run if it couldn't be delivered.
+    async fn flag_overdue_rentals(&self, today: Date) -> Result<Vec<Notice>, DbErr> {
+        let rentals = self.db.get_unflagged_overdue_rentals(today).await?;
+        let mut notices = Vec::with_capacity(rentals.len());
+        for mut rental in rentals {
+            let overdue_since = rental.return_date + Days::new(1);
+            rental.overdue_since = Some(overdue_since);
+            let notice = Notice::Overdue(rental);
+            if self.notify(&notice).await {
+                self.db
+                    .flag_overdue_rental(notice.rental().id, overdue_since)
+                    .await?;
+                notices.push(notice);
+            }
+        }
+        Ok(notices)
+    }
+
+    /// Drops the extension requests that weren't accepted before the rental's return date.
+    async fn expire_extension_requests(&self, today: Date) -> Result<Vec<Notice>, DbErr> {
+        let rentals = self.db.get_expired_extension_requests(today).await?;
+        let mut notices = Vec::with_capacity(rentals.len());
+        for rental in rentals {
+            self.db
+                .save_rental(RentalActiveModel {
+                    id: Set(rental.id),
+                    extension_date: Set(None),
+                    ..Default::default()
+                })
+                .await?;
+            let notice = Notice::ExtensionExpired(rental);
+            if self.notify(&notice).await {
+                notices.push(notice);
+            }
+        }
+        Ok(notices)
+    }
+
+    /// Cancels the reservations that weren't picked up and penalizes their users.
+    async fn cancel_no_shows(&self, today: Date) -> Result<Vec<Notice>, DbErr> {
+        let rentals = penalize_no_shows(&self.db, today).await?;
+        let mut notices = Vec::with_capacity(rentals.len());
+        for rental in rentals {
+            let notice = Notice::NoShow(rental);
+            if self.notify(&notice).await {
+                notices.push(notice);
+            }
+        }
+        Ok(notices)
+    }
+
+    /// Reminds the users about rentals that have to be returned tomorrow.
+    /// A rental is marked as reminded about only once the email is delivered,
+    /// so each user gets a single reminder, however often the jobs run.
+    async fn remind_due_rentals(
+        &self,
+        today: Date,
+        now: NaiveDateTime,
+    ) -> Result<Vec<Notice>, DbErr> {
+        let rentals = self
+            .db
+            .get_unreminded_rentals_due_on(today + Days::new(1))
+            .await?;
+        let mut notices = Vec::with_capacity(rentals.len());
+        for mut rental in rentals {
+            rental.reminder_sent_at = Some(now);
+            let notice = Notice::DueTomorrow(rental);
+            if self.notify(&notice).await {
+                self.db.mark_reminder_sent(notice.rental().id, now).await?;
+                notices.push(notice);
+            }
+        }
+        Ok(notices)
+    }
+}
+
+impl Notice {
+    /// Returns the rental the notice is about.
+    fn rental(&self) -> &RentalModel {
+        match self {
+            Notice::DueTomorrow(rental)
+            | Notice::Overdue(rental)
+            | Notice::NoShow(rental)
+            | Notice::ExtensionExpired(rental) => rental,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mailer::{Email, MailerError, MemoryMailer};
+    use chrono::TimeZone;
+    use entity::board_game::ActiveModel as BoardGameActiveModel;
+    use entity::user::ActiveModel as UserActiveModel;
+
+    /// Mailer failing to send anything, like one with an unreachable SMTP server.
+    #[derive(Debug)]
+    struct FailingMailer;
+
+    impl Mailer for FailingMailer {
+        fn send(&self, _email: &Email) -> Result<(), MailerError> {
+            Err(MailerError::Canceled)
+        }
+    }
+
+    /// Clock stopped at the given moment.
+    struct FixedClock(DateTime<Utc>);
+
+    impl Clock for FixedClock {
+        fn now(&self) -> DateTime<Utc> {
+            self.0
+        }
+    }
+
+    /// Inserts a user with an overdue rental of copy 1, a reservation of copy 2 that wasn't
+    /// picked up and a rental of copy 3 due tomorrow.
+    async fn insert_rentals(db: &DatabaseManager, today: Date) {
+        db.insert_user(UserActiveModel {
+            id: Set(1),
+            name: Set("Jan".into()),
+            surname: Set("Kowalski".into()),
+            email: Set("jan@example.com".into()),
+            password_hash: Set(String::new()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        // Every board game gets a single copy, with the same ID as the game.
+        for title in ["Catan", "Dixit", "Azul"] {
+            db.save_board_game(BoardGameActiveModel {
+                title: Set(title.into()),
+                weight: Set(200),
+                photo_filename: Set(String::new()),
+                min_players: Set(2),
+                max_players: Set(4),
+                min_playtime: Set(30),
+                max_playtime: Set(60),
+                additional_info: Set(None),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        }
+        let rentals = [
+            // Picked up and should have been returned yesterday.
+            (1, today - Days::new(7), today - Days::new(1), true),
+            // Booked three days ago, but never picked up.
+            (2, today - Days::new(3), today + Days::new(4), false),
+            // Picked up and due tomorrow.
+            (3, today - Days::new(5), today + Days::new(1), true),
+        ];
+        for (copy_id, rental_date, return_date, picked_up) in rentals {
+            db.save_rental(RentalActiveModel {
+                copy_id: Set(copy_id),
+                user_id: Set(1),
+                rental_date: Set(rental_date),
+                return_date: Set(return_date),
+                picked_up: Set(picked_up),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        }
+    }
+
+    #[actix_web::test]
+    async fn run_jobs_reports_each_rental_once() {
+        let db = DatabaseManager::new("sqlite::memory:").await.unwrap();
+        let clock = FixedClock(Utc.with_ymd_and_hms(2025, 3, 10, 12, 0, 0).unwrap());
+        let today = clock.today();
+        insert_rentals(&db, today).await;
+
+        let scheduler = Scheduler::new(
+            db.clone(),
+            Arc::new(MemoryMailer::default()),
+            Arc::new(clock),
+            &Config::from_env(),
+        );
+        let notices = scheduler.run_jobs().await.unwrap();
+        assert_eq!(notices.len(), 3);
+        assert!(notices.iter().any(|notice| matches!(
+            notice,
+            Notice::Overdue(rental) if rental.copy_id == 1 && rental.overdue_since == Some(today)
+        )));
+        assert!(notices
+            .iter()
+            .any(|notice| matches!(notice, Notice::NoShow(rental) if rental.copy_id == 2)));
+        assert!(notices
+            .iter()
+            .any(|notice| matches!(notice, Notice::DueTomorrow(rental) if rental.copy_id == 3)));
+        assert!(db.get_rental(2).await.unwrap().is_none());
+        assert_eq!(db.get_penalty_points(1).await.unwrap(), 1);
+
+        // Running the jobs again, e.g. after a restart, doesn't report anything twice.
+        assert!(scheduler.run_jobs().await.unwrap().is_empty());
+        assert_eq!(db.get_penalty_points(1).await.unwrap(), 1);
+    }
+
+    #[actix_web::test]
+    async fn undelivered_notices_are_sent_on_the_next_run() {
+        let db = DatabaseManager::new("sqlite::memory:").await.unwrap();
+        let now = Utc.with_ymd_and_hms(2025, 3, 10, 12, 0, 0).unwrap();
+        insert_rentals(&db, FixedClock(now).today()).await;
+
+        let scheduler = Scheduler::new(
+            db.clone(),
+            Arc::new(FailingMailer),
+            Arc::new(FixedClock(now)),
+            &Config::from_env(),
+        );
+        assert!(scheduler.run_jobs().await.unwrap().is_empty());
+        // The reservation is cancelled anyway, but the other rentals aren't marked as reported.
+        assert!(db.get_rental(2).await.unwrap().is_none());
+        assert_eq!(db.get_rental(1).await.unwrap().unwrap().overdue_since, None);
+        assert_eq!(
+            db.get_rental(3).await.unwrap().unwrap().reminder_sent_at,
+            None
+        );
+
+        let mailer = Arc::new(MemoryMailer::default());
+        let scheduler = Scheduler::new(
+            db.clone(),
+            mailer.clone(),
+            Arc::new(FixedClock(now)),
+            &Config::from_env(),
+        );
+        let notices = scheduler.run_jobs().await.unwrap();
+        assert_eq!(notices.len(), 2);
+        assert_eq!(mailer.sent().len(), 2);
+        assert!(db
+            .get_rental(1)
+            .await
+            .unwrap()
+            .unwrap()
+            .overdue_since
+            .is_some());
+        assert!(db
+            .get_rental(3)
+            .await
+            .unwrap()
+            .unwrap()
+            .reminder_sent_at
+            .is_some());
+    }
+}
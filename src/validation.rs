@@ -0,0 +1,380 @@
+use crate::api_error::Language;
+use crate::db_manager::DatabaseManager;
+use sea_orm::DbErr;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+const MAX_NAME_LENGTH: usize = 50;
+const MAX_EMAIL_LENGTH: usize = 254;
+const MIN_PASSWORD_LENGTH: usize = 8;
+const MAX_PASSWORD_LENGTH: usize = 128;
+
+/// Problem with a single field of a submitted form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FieldIssue {
+    Required,
+    TooLong,
+    InvalidCharacters,
+    InvalidId,
+    InvalidEmail,
+    PasswordTooShort,
+    PasswordTooLong,
+    PasswordTooWeak,
+    Taken,
+}
+
+impl FieldIssue {
+    pub(crate) fn code(&self) -> &'static str {
+        match self {
+            FieldIssue::Required => "REQUIRED",
+            FieldIssue::TooLong => "TOO_LONG",
+            FieldIssue::InvalidCharacters => "INVALID_CHARACTERS",
+            FieldIssue::InvalidId => "INVALID_ID",
+            FieldIssue::InvalidEmail => "INVALID_EMAIL",
+            FieldIssue::PasswordTooShort => "PASSWORD_TOO_SHORT",
+            FieldIssue::PasswordTooLong => "PASSWORD_TOO_LONG",
+            FieldIssue::PasswordTooWeak => "PASSWORD_TOO_WEAK",
+            FieldIssue::Taken => "TAKEN",
+        }
+    }
+
+    /// Returns a message that can be shown to the user next to the field.
+    pub(crate) fn message(&self, language: Language) -> String {
+        match (self, language) {
+            (FieldIssue::Required, Language::Pl) => "To pole jest wymagane".to_string(),
+            (FieldIssue::Required, Language::En) => "This field is required".to_string(),
+            (FieldIssue::TooLong, Language::Pl) => {
+                format!("Maksymalna długość to {} znaków", MAX_NAME_LENGTH)
+            }
+            (FieldIssue::TooLong, Language::En) => {
+                format!("The maximum length is {} characters", MAX_NAME_LENGTH)
+            }
+            (FieldIssue::InvalidCharacters, Language::Pl) => {
+                "Pole zawiera niedozwolone znaki".to_string()
+            }
+            (FieldIssue::InvalidCharacters, Language::En) => {
+                "The field contains invalid characters".to_string()
+            }
+            (FieldIssue::InvalidId, Language::Pl) => "Nieprawidłowy numer indeksu".to_string(),
+            (FieldIssue::InvalidId, Language::En) => "Invalid student ID".to_string(),
+            (FieldIssue::InvalidEmail, Language::Pl) => "Nieprawidłowy adres email".to_string(),
+            (FieldIssue::InvalidEmail, Language::En) => "Invalid email address".to_string(),
+            (FieldIssue::PasswordTooShort, Language::Pl) => {
+                format!("Hasło musi mieć co najmniej {} znaków", MIN_PASSWORD_LENGTH)
+            }
+            (FieldIssue::PasswordTooShort, Language::En) => format!(
+                "The password has to be at least {} characters long",
+                MIN_PASSWORD_LENGTH
+            ),
+            (FieldIssue::PasswordTooLong, Language::Pl) => {
+                format!("Hasło może mieć najwyżej {} znaków", MAX_PASSWORD_LENGTH)
+            }
+            (FieldIssue::PasswordTooLong, Language::En) => format!(
+                "The password can be at most {} characters long",
+                MAX_PASSWORD_LENGTH
+            ),
+            (FieldIssue::PasswordTooWeak, Language::Pl) => {
+                "Hasło musi zawierać literę i cyfrę".to_string()
+            }
+            (FieldIssue::PasswordTooWeak, Language::En) => {
+                "The password has to contain a letter and a digit".to_string()
+            }
+            (FieldIssue::Taken, Language::Pl) => "Ta wartość jest już zajęta".to_string(),
+            (FieldIssue::Taken, Language::En) => "This value is already taken".to_string(),
+        }
+    }
+}
+
+/// Problem with the named field of a submitted form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct FieldError {
+    pub(crate) field: &'static str,
+    pub(crate) issue: FieldIssue,
+}
+
+/// Field error as sent to the client.
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct FieldErrorResponse {
+    field: &'static str,
+    code: &'static str,
+    error: String,
+}
+
+impl FieldError {
+    pub(crate) fn new(field: &'static str, issue: FieldIssue) -> Self {
+        FieldError { field, issue }
+    }
+
+    pub(crate) fn to_response(self, language: Language) -> FieldErrorResponse {
+        FieldErrorResponse {
+            field: self.field,
+            code: self.issue.code(),
+            error: self.issue.message(language),
+        }
+    }
+}
+
+/// Checks the data of a new account. Returns all problems found, so that they can be shown at once.
+pub(crate) fn validate_registration(
+    id: i32,
+    name: &str,
+    surname: &str,
+    email: &str,
+    password: &str,
+) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+    if id <= 0 {
+        errors.push(FieldError::new("id", FieldIssue::InvalidId));
+    }
+    if let Some(issue) = check_name(name) {
+        errors.push(FieldError::new("name", issue));
+    }
+    if let Some(issue) = check_name(surname) {
+        errors.push(FieldError::new("surname", issue));
+    }
+    if let Some(issue) = check_email(email) {
+        errors.push(FieldError::new("email", issue));
+    }
+    if let Some(issue) = check_password(password) {
+        errors.push(FieldError::new("password", issue));
+    }
+    errors
+}
+
+/// Checks the fields of an account that are being changed, the same way as on registration.
+pub(crate) fn validate_user_update(
+    name: Option<&str>,
+    surname: Option<&str>,
+    email: Option<&str>,
+    password: Option<&str>,
+) -> Vec<FieldError> {
+    let checks = [
+        ("name", name.and_then(check_name)),
+        ("surname", surname.and_then(check_name)),
+        ("email", email.and_then(check_email)),
+        ("password", password.and_then(check_password)),
+    ];
+    checks
+        .into_iter()
+        .filter_map(|(field, issue)| issue.map(|issue| FieldError::new(field, issue)))
+        .collect()
+}
+
+/// Checks a new password set without the rest of the registration form.
+pub(crate) fn validate_password(password: &str) -> Vec<FieldError> {
+    check_password(password)
+        .map(|issue| vec![FieldError::new("password", issue)])
+        .unwrap_or_default()
+}
+
+/// Checks the name of a board game category or tag.
+pub(crate) fn validate_label(name: &str) -> Vec<FieldError> {
+    let issue = if name.is_empty() {
+        Some(FieldIssue::Required)
+    } else if name.chars().count() > MAX_NAME_LENGTH {
+        Some(FieldIssue::TooLong)
+    } else if name.contains(',') {
+        // Commas separate names when filtering the catalogue.
+        Some(FieldIssue::InvalidCharacters)
+    } else {
+        None
+    };
+    issue
+        .map(|issue| vec![FieldError::new("name", issue)])
+        .unwrap_or_default()
+}
+
+/// Checks whether the ID and email of a new account are not used by anyone else yet.
+pub(crate) async fn check_registration_taken(
+    db: &DatabaseManager,
+    id: i32,
+    email: &str,
+) -> Result<Vec<FieldError>, DbErr> {
+    let mut errors = Vec::new();
+    if db.get_user(id).await?.is_some() {
+        errors.push(FieldError::new("id", FieldIssue::Taken));
+    }
+    if db.email_exists(email).await? {
+        errors.push(FieldError::new("email", FieldIssue::Taken));
+    }
+    Ok(errors)
+}
+
+/// Names can contain letters, spaces, hyphens and apostrophes, e.g. "Anna-Maria" or "O'Neill".
+fn check_name(name: &str) -> Option<FieldIssue> {
+    if name.trim().is_empty() {
+        Some(FieldIssue::Required)
+    } else if name.chars().count() > MAX_NAME_LENGTH {
+        Some(FieldIssue::TooLong)
+    } else if !name
+        .chars()
+        .all(|c| c.is_alphabetic() || c == ' ' || c == '-' || c == '\'')
+    {
+        Some(FieldIssue::InvalidCharacters)
+    } else {
+        None
+    }
+}
+
+/// Only checks the basic structure of the address, the confirmation email verifies the rest.
+fn check_email(email: &str) -> Option<FieldIssue> {
+    if email.is_empty() {
+        return Some(FieldIssue::Required);
+    }
+    if email.len() > MAX_EMAIL_LENGTH || email.chars().any(|c| c.is_whitespace()) {
+        return Some(FieldIssue::InvalidEmail);
+    }
+
+    let valid = match email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.contains('@')
+                && domain
+                    .split('.')
+                    .all(|label| !label.is_empty() && !label.starts_with('-'))
+                && domain.contains('.')
+        }
+        None => false,
+    };
+    if valid {
+        None
+    } else {
+        Some(FieldIssue::InvalidEmail)
+    }
+}
+
+fn check_password(password: &str) -> Option<FieldIssue> {
+    let length = password.chars().count();
+    if length == 0 {
+        Some(FieldIssue::Required)
+    } else if length < MIN_PASSWORD_LENGTH {
+        Some(FieldIssue::PasswordTooShort)
+    } else if length > MAX_PASSWORD_LENGTH {
+        Some(FieldIssue::PasswordTooLong)
+    } else if !password.chars().any(|c| c.is_alphabetic())
+        || !password.chars().any(|c| c.is_ascii_digit())
+    {
+        Some(FieldIssue::PasswordTooWeak)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_email_accepts_usual_addresses() {
+        for email in [
+            "jan@example.com",
+            "jan.kowalski+gry@student.uni.edu.pl",
+            "o'neill@example.co.uk",
+        ] {
+            assert_eq!(check_email(email), None, "{}", email);
+        }
+    }
+
+    #[test]
+    fn check_email_rejects_malformed_addresses() {
+        assert_eq!(check_email(""), Some(FieldIssue::Required));
+        for email in [
+            "jan",
+            "@example.com",
+            "jan@",
+            "jan@localhost",
+            "jan@@example.com",
+            "jan@exa@mple.com",
+            "jan@example..com",
+            "jan@.example.com",
+            "jan@example.com.",
+            "jan@-example.com",
+            "jan kowalski@example.com",
+            "jan@example.com\n",
+        ] {
+            assert_eq!(
+                check_email(email),
+                Some(FieldIssue::InvalidEmail),
+                "{}",
+                email
+            );
+        }
+        let too_long = format!("{}@example.com", "a".repeat(MAX_EMAIL_LENGTH));
+        assert_eq!(check_email(&too_long), Some(FieldIssue::InvalidEmail));
+    }
+
+    #[test]
+    fn check_name_accepts_names_with_separators() {
+        for name in ["Jan", "Anna-Maria", "O'Neill", "Łukasz", "Van der Berg"] {
+            assert_eq!(check_name(name), None, "{}", name);
+        }
+        assert_eq!(check_name(&"a".repeat(MAX_NAME_LENGTH)), None);
+    }
+
+    #[test]
+    fn check_name_rejects_invalid_names() {
+        assert_eq!(check_name(""), Some(FieldIssue::Required));
+        assert_eq!(check_name("   "), Some(FieldIssue::Required));
+        // Length is counted in characters, not bytes.
+        assert_eq!(check_name(&"ł".repeat(MAX_NAME_LENGTH)), None);
+        assert_eq!(
+            check_name(&"a".repeat(MAX_NAME_LENGTH + 1)),
+            Some(FieldIssue::TooLong)
+        );
+        for name in ["Jan2", "Jan_Kowalski", "<script>", "Jan."] {
+            assert_eq!(
+                check_name(name),
+                Some(FieldIssue::InvalidCharacters),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn check_password_enforces_length_and_strength() {
+        assert_eq!(check_password(""), Some(FieldIssue::Required));
+        assert_eq!(
+            check_password("abc1234"),
+            Some(FieldIssue::PasswordTooShort)
+        );
+        assert_eq!(check_password("abcd1234"), None);
+        // Length is counted in characters, so Polish letters don't make a password longer.
+        assert_eq!(
+            check_password("żółć123"),
+            Some(FieldIssue::PasswordTooShort)
+        );
+        assert_eq!(check_password("żółć1234"), None);
+        let longest = format!("{}1", "a".repeat(MAX_PASSWORD_LENGTH - 1));
+        assert_eq!(check_password(&longest), None);
+        let too_long = format!("{}1", "a".repeat(MAX_PASSWORD_LENGTH));
+        assert_eq!(check_password(&too_long), Some(FieldIssue::PasswordTooLong));
+        assert_eq!(
+            check_password("abcdefgh"),
+            Some(FieldIssue::PasswordTooWeak)
+        );
+        assert_eq!(
+            check_password("12345678"),
+            Some(FieldIssue::PasswordTooWeak)
+        );
+    }
+
+    #[test]
+    fn validate_registration_reports_all_fields() {
+        let errors = validate_registration(0, "", "Kowalski1", "jan", "haslo");
+        let fields: Vec<_> = errors.iter().map(|error| error.field).collect();
+        assert_eq!(fields, ["id", "name", "surname", "email", "password"]);
+        assert!(
+            validate_registration(1, "Jan", "Kowalski", "jan@example.com", "haslo123").is_empty()
+        );
+    }
+
+    #[test]
+    fn validate_user_update_checks_only_given_fields() {
+        assert!(validate_user_update(None, None, None, None).is_empty());
+        assert!(validate_user_update(Some("Jan"), None, Some("jan@example.com"), None).is_empty());
+        let errors = validate_user_update(Some(""), None, Some("jan"), Some("haslo"));
+        let fields: Vec<_> = errors.iter().map(|error| error.field).collect();
+        assert_eq!(fields, ["name", "email", "password"]);
+    }
+}